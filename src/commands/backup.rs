@@ -4,14 +4,41 @@ use anyhow::{bail, Context, Result};
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use owo_colors::OwoColorize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use tar::Builder;
 
-use super::utils;
+use super::{list, utils};
 use crate::config;
-use crate::cursor::{folder_id, workspace};
+use crate::cursor::{chat_sessions, folder_id, workspace};
+
+/// Current backup format version
+///
+/// Version 2 adds the project's chat data from the global state DB, version 3
+/// per-file checksums.
+pub const MANIFEST_VERSION: u32 = 3;
+
+/// First backup format version whose manifests carry checksums
+pub const CHECKSUMS_VERSION: u32 = 3;
+
+/// Archive entry holding the project's rows from the global state DB
+pub const GLOBAL_CHAT_FILE: &str = "global-chat.jsonl";
+
+/// Archive entry listing the projects of a full-profile backup
+pub const PROFILE_MANIFEST_FILE: &str = "profile.json";
+
+/// Archive entry holding `globalStorage/storage.json` in a full-profile backup
+pub const STORAGE_JSON_FILE: &str = "storage.json";
+
+/// Archive directory of the `~/.cursor/projects` data no backed-up project
+/// uses, in a full-profile backup
+pub const UNLINKED_PROJECTS_DIR: &str = "projects";
+
+/// Archive entry holding a multi-root workspace's `.code-workspace` file
+pub const CODE_WORKSPACE_FILE: &str = "workspace.code-workspace";
 
 /// Backup metadata
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -28,6 +55,54 @@ pub struct BackupManifest {
     pub created_at: i64,
     /// What was included in the backup
     pub includes: BackupContents,
+    /// SHA-256 of every archived file, keyed by archive path (version 3+)
+    #[serde(default)]
+    pub checksums: BTreeMap<String, String>,
+    /// What kind of workspace was backed up
+    #[serde(default)]
+    pub kind: WorkspaceKind,
+}
+
+/// Kind of a backed-up workspace
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorkspaceKind {
+    /// A local folder; `project_path` is the folder
+    #[default]
+    Folder,
+    /// A local multi-root workspace; `project_path` is its `.code-workspace` file
+    MultiRoot,
+    /// A remote workspace; `project_path` is for display only
+    Remote,
+}
+
+/// Metadata of a full-profile backup (`backup --all`)
+///
+/// Each project is archived under `workspaces/<workspace hash>/` in the layout
+/// of a single-project backup, with checksums relative to that directory. The
+/// remaining `~/.cursor/projects` directories go under `projects/<folder id>/`.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct ProfileManifest {
+    /// Version of the backup format
+    pub version: u32,
+    /// Timestamp of backup creation
+    pub created_at: i64,
+    /// Whether `globalStorage/storage.json` was included
+    pub storage_json: bool,
+    /// Every backed-up project
+    pub projects: Vec<BackupManifest>,
+    /// Folder IDs of `~/.cursor/projects` directories no backed-up project
+    /// uses (a folder without a workspace, remote and multi-root data)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unlinked_projects_data: Vec<String>,
+    /// SHA-256 of the files outside the project directories
+    #[serde(default)]
+    pub checksums: BTreeMap<String, String>,
+}
+
+/// Archive directory of a project in a full-profile backup
+pub fn profile_project_dir(manifest: &BackupManifest) -> String {
+    format!("workspaces/{}", manifest.workspace_hash)
 }
 
 /// What's included in the backup
@@ -37,6 +112,19 @@ pub struct BackupContents {
     pub workspace_storage: bool,
     /// Whether projects data was included
     pub projects_data: bool,
+    /// Number of chat sessions taken from the global state DB (version 2+)
+    #[serde(default)]
+    pub global_chat_sessions: usize,
+}
+
+/// One line of the global chat file
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GlobalChatRow {
+    /// An entry of `composer.composerHeaders` (`allComposers`)
+    Header { value: serde_json::Value },
+    /// A `cursorDiskKV` row (`composerData:<id>` or `bubbleId:<id>:<bubble>`)
+    DiskKv { key: String, value: String },
 }
 
 /// Execute the backup command
@@ -66,8 +154,13 @@ pub fn execute(project_path: &str, backup_file: &str) -> Result<()> {
     // Check what exists
     let has_projects = projects_dir.exists();
     let has_workspace = workspace_dir.exists();
+    let chat_data = match chat_sessions::open_global_state_db()? {
+        Some(conn) => chat_sessions::load_workspace_chat_data(&conn, &workspace_dir)?,
+        None => chat_sessions::WorkspaceChatData::default(),
+    };
+    let has_chat = !chat_data.headers.is_empty() || !chat_data.rows.is_empty();
 
-    if !has_projects && !has_workspace {
+    if !has_projects && !has_workspace && !has_chat {
         bail!("No Cursor data found for: {}", project_path.display());
     }
 
@@ -82,25 +175,273 @@ pub fn execute(project_path: &str, backup_file: &str) -> Result<()> {
     if has_workspace {
         println!("{} workspaceStorage/ data", "Found:".green());
     }
+    if has_chat {
+        println!(
+            "{} {} chat sessions in the global state DB",
+            "Found:".green(),
+            chat_data.headers.len()
+        );
+    }
     println!();
 
     // Create backup manifest
-    let manifest = BackupManifest {
-        version: 1,
+    let mut manifest = BackupManifest {
+        version: MANIFEST_VERSION,
         project_path: project_path.to_string_lossy().to_string(),
         folder_id: folder_id.clone(),
         workspace_hash: workspace_hash.clone(),
-        created_at: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.as_secs() as i64)
-            .unwrap_or(0),
+        created_at: now(),
         includes: BackupContents {
             workspace_storage: has_workspace,
             projects_data: has_projects,
+            global_chat_sessions: chat_data.headers.len(),
         },
+        checksums: BTreeMap::new(),
+        kind: WorkspaceKind::Folder,
     };
 
-    // Create tar.gz archive
+    let (mut archive, backup_path) = create_archive(backup_file)?;
+
+    if has_workspace {
+        println!("Adding workspaceStorage/...");
+    }
+    if has_projects {
+        println!("Adding projects/...");
+    }
+    if has_chat {
+        println!("Adding {}...", GLOBAL_CHAT_FILE);
+    }
+    add_project_to_archive(
+        &mut archive,
+        Path::new(""),
+        &mut manifest,
+        &workspace_dir,
+        &projects_dir,
+        chat_data,
+    )?;
+
+    // Add manifest last, once the checksums are known
+    let manifest_json = serde_json::to_string_pretty(&manifest)?;
+    add_file_to_archive(&mut archive, "manifest.json", manifest_json.as_bytes())?;
+
+    finish_archive(archive, &backup_path)
+}
+
+/// Execute `backup --all`: every workspace, its chat data, `~/.cursor/projects`
+/// data and `storage.json` in one archive
+pub fn execute_all(backup_file: &str) -> Result<()> {
+    let cursor_projects_dir = config::cursor_projects_dir()?;
+    let workspace_storage_dir = config::workspace_storage_dir()?;
+    let storage_json = config::global_storage_dir()?.join("storage.json");
+
+    let projects = list::discover_projects()?;
+    if projects.is_empty() {
+        bail!("No Cursor projects found");
+    }
+    let global_conn = chat_sessions::open_global_state_db()?;
+
+    println!(
+        "Creating full-profile backup of {} projects",
+        projects.len()
+    );
+    println!();
+
+    let mut profile = ProfileManifest {
+        version: MANIFEST_VERSION,
+        created_at: now(),
+        storage_json: storage_json.exists(),
+        projects: Vec::new(),
+        unlinked_projects_data: Vec::new(),
+        checksums: BTreeMap::new(),
+    };
+    let (mut archive, backup_path) = create_archive(backup_file)?;
+
+    for project in &projects {
+        let workspace_dir = workspace_storage_dir.join(&project.folder_id);
+        let (kind, folder_id) = if project.remote.is_some() {
+            (WorkspaceKind::Remote, String::new())
+        } else if project
+            .path
+            .extension()
+            .is_some_and(|ext| ext == "code-workspace")
+        {
+            (WorkspaceKind::MultiRoot, String::new())
+        } else {
+            (
+                WorkspaceKind::Folder,
+                folder_id::path_to_folder_id(&project.path),
+            )
+        };
+        let projects_dir = cursor_projects_dir.join(&folder_id);
+        let has_projects = !folder_id.is_empty() && projects_dir.exists();
+
+        let chat_data = match &global_conn {
+            Some(conn) => chat_sessions::load_workspace_chat_data(conn, &workspace_dir)?,
+            None => chat_sessions::WorkspaceChatData::default(),
+        };
+
+        let mut manifest = BackupManifest {
+            version: MANIFEST_VERSION,
+            project_path: match kind {
+                WorkspaceKind::Remote => project.display_path(),
+                _ => project.path.to_string_lossy().to_string(),
+            },
+            folder_id,
+            workspace_hash: project.folder_id.clone(),
+            created_at: profile.created_at,
+            includes: BackupContents {
+                workspace_storage: true,
+                projects_data: has_projects,
+                global_chat_sessions: chat_data.headers.len(),
+            },
+            checksums: BTreeMap::new(),
+            kind,
+        };
+        println!(
+            "{} {} ({} chat sessions)",
+            "Adding:".green(),
+            manifest.project_path,
+            manifest.includes.global_chat_sessions
+        );
+        let root = PathBuf::from(profile_project_dir(&manifest));
+        add_project_to_archive(
+            &mut archive,
+            &root,
+            &mut manifest,
+            &workspace_dir,
+            &projects_dir,
+            chat_data,
+        )?;
+        if kind == WorkspaceKind::MultiRoot && project.path.is_file() {
+            let content = fs::read(&project.path)
+                .with_context(|| format!("Failed to read: {}", project.path.display()))?;
+            manifest
+                .checksums
+                .insert(CODE_WORKSPACE_FILE.to_string(), sha256_hex(&content));
+            add_file_to_archive(
+                &mut archive,
+                &archive_key(&root.join(CODE_WORKSPACE_FILE)),
+                &content,
+            )?;
+        }
+        profile.projects.push(manifest);
+    }
+
+    profile.unlinked_projects_data = unlinked_folder_ids(&cursor_projects_dir, &profile.projects);
+    for id in &profile.unlinked_projects_data {
+        println!(
+            "{} ~/.cursor/projects/{} (no matching workspace)",
+            "Adding:".green(),
+            id
+        );
+        add_dir_to_archive(
+            &mut archive,
+            &cursor_projects_dir.join(id),
+            Path::new(""),
+            &format!("{}/{}", UNLINKED_PROJECTS_DIR, id),
+            &mut profile.checksums,
+        )?;
+    }
+
+    if profile.storage_json {
+        println!("{} {}", "Adding:".green(), STORAGE_JSON_FILE);
+        let content = fs::read(&storage_json)
+            .with_context(|| format!("Failed to read: {}", storage_json.display()))?;
+        profile
+            .checksums
+            .insert(STORAGE_JSON_FILE.to_string(), sha256_hex(&content));
+        add_file_to_archive(&mut archive, STORAGE_JSON_FILE, &content)?;
+    }
+
+    // Add manifest last, once the checksums are known
+    let manifest_json = serde_json::to_string_pretty(&profile)?;
+    add_file_to_archive(
+        &mut archive,
+        PROFILE_MANIFEST_FILE,
+        manifest_json.as_bytes(),
+    )?;
+
+    finish_archive(archive, &backup_path)
+}
+
+/// Directories under `~/.cursor/projects` that none of `projects` archived
+fn unlinked_folder_ids(cursor_projects_dir: &Path, projects: &[BackupManifest]) -> Vec<String> {
+    let mut ids: Vec<String> = fs::read_dir(cursor_projects_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|id| {
+            !projects
+                .iter()
+                .any(|p| p.includes.projects_data && &p.folder_id == id)
+        })
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Add a project's workspaceStorage, projects and chat data under `root`
+///
+/// Checksums are recorded in `manifest` relative to `root`.
+fn add_project_to_archive<W: Write>(
+    archive: &mut Builder<W>,
+    root: &Path,
+    manifest: &mut BackupManifest,
+    workspace_dir: &Path,
+    projects_dir: &Path,
+    chat_data: chat_sessions::WorkspaceChatData,
+) -> Result<()> {
+    if manifest.includes.workspace_storage {
+        add_dir_to_archive(
+            archive,
+            workspace_dir,
+            root,
+            "workspaceStorage",
+            &mut manifest.checksums,
+        )?;
+    }
+
+    if manifest.includes.projects_data {
+        add_dir_to_archive(
+            archive,
+            projects_dir,
+            root,
+            "projects",
+            &mut manifest.checksums,
+        )?;
+    }
+
+    // Add chat data from the global state DB
+    if !chat_data.headers.is_empty() || !chat_data.rows.is_empty() {
+        let mut content = Vec::new();
+        let headers = chat_data
+            .headers
+            .into_iter()
+            .map(|value| GlobalChatRow::Header { value });
+        let rows = chat_data
+            .rows
+            .into_iter()
+            .map(|(key, value)| GlobalChatRow::DiskKv { key, value });
+        for row in headers.chain(rows) {
+            serde_json::to_writer(&mut content, &row)?;
+            content.push(b'\n');
+        }
+        manifest
+            .checksums
+            .insert(GLOBAL_CHAT_FILE.to_string(), sha256_hex(&content));
+        add_file_to_archive(
+            archive,
+            &archive_key(&root.join(GLOBAL_CHAT_FILE)),
+            &content,
+        )?;
+    }
+    Ok(())
+}
+
+/// Create the tar.gz archive, adding the extension if missing
+fn create_archive(backup_file: &str) -> Result<(Builder<GzEncoder<File>>, PathBuf)> {
     let backup_path = if backup_file.ends_with(".tar.gz") {
         PathBuf::from(backup_file)
     } else {
@@ -111,30 +452,16 @@ pub fn execute(project_path: &str, backup_file: &str) -> Result<()> {
         .with_context(|| format!("Failed to create: {}", backup_path.display()))?;
 
     let encoder = GzEncoder::new(file, Compression::default());
-    let mut archive = Builder::new(encoder);
-
-    // Add manifest
-    let manifest_json = serde_json::to_string_pretty(&manifest)?;
-    add_file_to_archive(&mut archive, "manifest.json", manifest_json.as_bytes())?;
-
-    // Add workspace storage
-    if has_workspace {
-        println!("Adding workspaceStorage/...");
-        add_dir_to_archive(&mut archive, &workspace_dir, "workspaceStorage")?;
-    }
-
-    // Add projects data
-    if has_projects {
-        println!("Adding projects/...");
-        add_dir_to_archive(&mut archive, &projects_dir, "projects")?;
-    }
+    Ok((Builder::new(encoder), backup_path))
+}
 
-    // Finish archive
+/// Finish the archive and report its size
+fn finish_archive(archive: Builder<GzEncoder<File>>, backup_path: &Path) -> Result<()> {
     let encoder = archive.into_inner()?;
     encoder.finish()?;
 
     // Get file size
-    let size = fs::metadata(&backup_path)?.len();
+    let size = fs::metadata(backup_path)?.len();
 
     println!();
     println!(
@@ -147,6 +474,14 @@ pub fn execute(project_path: &str, backup_file: &str) -> Result<()> {
     Ok(())
 }
 
+/// Current Unix time in seconds
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
 /// Add a file with content to the archive
 fn add_file_to_archive<W: Write>(
     archive: &mut Builder<W>,
@@ -168,11 +503,14 @@ fn add_file_to_archive<W: Write>(
     Ok(())
 }
 
-/// Add a directory recursively to the archive
+/// Add a directory recursively to the archive as `root/prefix`, recording
+/// file checksums relative to `root`
 fn add_dir_to_archive<W: Write>(
     archive: &mut Builder<W>,
     source: &Path,
+    root: &Path,
     prefix: &str,
+    checksums: &mut BTreeMap<String, String>,
 ) -> Result<()> {
     for entry in walkdir::WalkDir::new(source)
         .into_iter()
@@ -183,21 +521,64 @@ fn add_dir_to_archive<W: Write>(
             .strip_prefix(source)
             .with_context(|| format!("Failed to strip prefix from: {}", path.display()))?;
 
-        let archive_path = if relative.as_os_str().is_empty() {
+        let key_path = if relative.as_os_str().is_empty() {
             PathBuf::from(prefix)
         } else {
             PathBuf::from(prefix).join(relative)
         };
+        let archive_path = root.join(&key_path);
 
         if path.is_dir() {
             archive.append_dir(&archive_path, path)?;
         } else if path.is_file() {
-            archive.append_path_with_name(path, &archive_path)?;
+            // Hash the bytes as they are archived rather than re-reading the file
+            let file =
+                File::open(path).with_context(|| format!("Failed to open: {}", path.display()))?;
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&file.metadata()?);
+            let mut reader = HashingReader {
+                inner: file,
+                hasher: Sha256::new(),
+            };
+            archive
+                .append_data(&mut header, &archive_path, &mut reader)
+                .with_context(|| format!("Failed to archive: {}", path.display()))?;
+            checksums.insert(
+                archive_key(&key_path),
+                format!("{:x}", reader.hasher.finalize()),
+            );
         }
     }
     Ok(())
 }
 
+/// Archive path as stored in the manifest, with `/` separators
+pub fn archive_key(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Hex-encoded SHA-256 of some bytes
+pub fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Reader that hashes everything read through it
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -213,7 +594,10 @@ mod tests {
             includes: BackupContents {
                 workspace_storage: true,
                 projects_data: true,
+                global_chat_sessions: 3,
             },
+            checksums: BTreeMap::from([("projects/mcp.json".to_string(), "ab12".to_string())]),
+            kind: WorkspaceKind::Folder,
         };
 
         // Should serialize to JSON without error
@@ -221,6 +605,8 @@ mod tests {
         assert!(json.contains("\"version\":1"));
         assert!(json.contains("\"project_path\":\"/home/user/project\""));
         assert!(json.contains("\"workspace_storage\":true"));
+        assert!(json.contains("\"global_chat_sessions\":3"));
+        assert!(json.contains("\"checksums\":{\"projects/mcp.json\":\"ab12\"}"));
     }
 
     #[test]
@@ -242,6 +628,27 @@ mod tests {
         assert_eq!(manifest.project_path, "/test/path");
         assert!(manifest.includes.workspace_storage);
         assert!(!manifest.includes.projects_data);
+        // Version 1 backups have no global chat data or checksums
+        assert_eq!(manifest.includes.global_chat_sessions, 0);
+        assert!(manifest.checksums.is_empty());
+        assert_eq!(manifest.kind, WorkspaceKind::Folder);
+    }
+
+    #[test]
+    fn test_global_chat_row_serialization() {
+        let row = GlobalChatRow::DiskKv {
+            key: "bubbleId:c1:b1".to_string(),
+            value: "{}".to_string(),
+        };
+        let line = serde_json::to_string(&row).unwrap();
+        assert_eq!(
+            line,
+            r#"{"kind":"disk_kv","key":"bubbleId:c1:b1","value":"{}"}"#
+        );
+
+        let row: GlobalChatRow =
+            serde_json::from_str(r#"{"kind":"header","value":{"composerId":"c1"}}"#).unwrap();
+        assert!(matches!(row, GlobalChatRow::Header { value } if value["composerId"] == "c1"));
     }
 
     #[test]
@@ -249,6 +656,7 @@ mod tests {
         let contents = BackupContents {
             workspace_storage: false,
             projects_data: false,
+            global_chat_sessions: 0,
         };
         assert!(!contents.workspace_storage);
         assert!(!contents.projects_data);
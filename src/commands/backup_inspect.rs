@@ -0,0 +1,586 @@
+//! Backup inspect and verify commands - Check a backup archive without restoring it
+
+use anyhow::{bail, Context, Result};
+use comfy_table::{presets::UTF8_FULL_CONDENSED, Cell, ContentArrangement, Table};
+use owo_colors::OwoColorize;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use super::backup::{
+    self, BackupManifest, GlobalChatRow, ProfileManifest, WorkspaceKind, GLOBAL_CHAT_FILE,
+};
+use super::restore::{self, ArchiveManifest};
+use super::utils;
+
+/// A chat session found in a backup
+#[derive(Debug, Clone, PartialEq)]
+struct BackupSession {
+    id: String,
+    title: Option<String>,
+    created_at_ms: Option<i64>,
+    updated_at_ms: Option<i64>,
+    /// Number of message bubbles (only known for global chat data)
+    messages: Option<usize>,
+}
+
+/// Show what a backup contains
+pub fn inspect(backup_file: &str) -> Result<()> {
+    let backup_path = existing_backup(backup_file)?;
+    let manifest = restore::read_archive_manifest(&backup_path)?;
+    let unpacked = restore::unpack_archive(&backup_path)?;
+    let root = unpacked.path();
+
+    println!(
+        "Backup: {} ({})",
+        backup_path.display(),
+        utils::format_size(fs::metadata(&backup_path)?.len())
+    );
+    match manifest {
+        ArchiveManifest::Project(manifest) => inspect_project(&manifest, root),
+        ArchiveManifest::Profile(profile) => {
+            inspect_profile(&profile, root);
+            Ok(())
+        }
+    }
+}
+
+fn format_created(created_at: i64) -> String {
+    chrono::DateTime::from_timestamp(created_at, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// List the projects of a full-profile backup
+fn inspect_profile(profile: &ProfileManifest, root: &Path) {
+    println!("  Format version: {}", profile.version);
+    println!("  Created: {}", format_created(profile.created_at));
+    println!("  Full profile: {} project(s)", profile.projects.len());
+    println!(
+        "  storage.json: {}",
+        if profile.storage_json {
+            "included"
+        } else {
+            "not included"
+        }
+    );
+    println!();
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "Project",
+            "Kind",
+            "Workspace",
+            "Files",
+            "Size",
+            "Sessions",
+        ]);
+    for project in &profile.projects {
+        let kind = match project.kind {
+            WorkspaceKind::Folder => "folder",
+            WorkspaceKind::MultiRoot => "multi-root",
+            WorkspaceKind::Remote => "remote",
+        };
+        let (files, size) = count_files(&root.join(backup::profile_project_dir(project)));
+        table.add_row(vec![
+            Cell::new(&project.project_path),
+            Cell::new(kind),
+            Cell::new(&project.workspace_hash),
+            Cell::new(files),
+            Cell::new(utils::format_size(size)),
+            Cell::new(project.includes.global_chat_sessions),
+        ]);
+    }
+    println!("{}", table);
+    if !profile.unlinked_projects_data.is_empty() {
+        println!();
+        println!("~/.cursor/projects data without a workspace:");
+        for id in &profile.unlinked_projects_data {
+            let (files, size) = count_files(&root.join(backup::UNLINKED_PROJECTS_DIR).join(id));
+            println!("  {} ({} files, {})", id, files, utils::format_size(size));
+        }
+    }
+    println!();
+    println!(
+        "{} session(s)",
+        profile
+            .projects
+            .iter()
+            .map(|p| p.includes.global_chat_sessions)
+            .sum::<usize>()
+    );
+}
+
+/// Show the contents and chat sessions of a single-project backup
+fn inspect_project(manifest: &BackupManifest, root: &Path) -> Result<()> {
+    println!("  Format version: {}", manifest.version);
+    println!("  Created: {}", format_created(manifest.created_at));
+    println!("  Original path: {}", manifest.project_path);
+    println!("  Folder ID: {}", manifest.folder_id);
+    println!("  Workspace hash: {}", manifest.workspace_hash);
+    println!();
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Content", "Files", "Size"]);
+    for (name, label) in [
+        ("workspaceStorage", "workspaceStorage/"),
+        ("projects", "projects/"),
+        (GLOBAL_CHAT_FILE, GLOBAL_CHAT_FILE),
+    ] {
+        let path = root.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let label = match workspace_folder(&path) {
+            Some(folder) => format!("{} ({})", label, folder),
+            None => label.to_string(),
+        };
+        let (files, size) = count_files(&path);
+        table.add_row(vec![
+            Cell::new(label),
+            Cell::new(files),
+            Cell::new(utils::format_size(size)),
+        ]);
+    }
+    println!("{}", table);
+    println!();
+
+    let sessions = load_sessions(root)?;
+    if sessions.is_empty() {
+        println!("No chat sessions in this backup.");
+        return Ok(());
+    }
+
+    let format_ms = |ms: Option<i64>| {
+        ms.and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "Session",
+            "Title",
+            "Created",
+            "Last Updated",
+            "Messages",
+        ]);
+    for session in &sessions {
+        table.add_row(vec![
+            Cell::new(&session.id),
+            Cell::new(session.title.as_deref().unwrap_or("(untitled)")),
+            Cell::new(format_ms(session.created_at_ms)),
+            Cell::new(format_ms(session.updated_at_ms)),
+            Cell::new(
+                session
+                    .messages
+                    .map_or_else(|| "-".to_string(), |n| n.to_string()),
+            ),
+        ]);
+    }
+    println!("{}", table);
+    println!();
+    println!("{} session(s)", sessions.len());
+
+    Ok(())
+}
+
+/// Check a backup's checksums, databases and chat data
+pub fn verify(backup_file: &str) -> Result<()> {
+    let backup_path = existing_backup(backup_file)?;
+    let manifest = restore::read_archive_manifest(&backup_path)?;
+    let unpacked = restore::unpack_archive(&backup_path)?;
+    let root = unpacked.path();
+
+    let (version, mut problems, checksum_count) = match &manifest {
+        ArchiveManifest::Project(manifest) => (
+            manifest.version,
+            check_checksums(&manifest.checksums, root, &["manifest.json"]),
+            manifest.checksums.len(),
+        ),
+        ArchiveManifest::Profile(profile) => {
+            let dirs: Vec<String> = profile
+                .projects
+                .iter()
+                .map(backup::profile_project_dir)
+                .collect();
+            let mut ignored = vec![backup::PROFILE_MANIFEST_FILE];
+            ignored.extend(dirs.iter().map(String::as_str));
+            let mut problems = check_checksums(&profile.checksums, root, &ignored);
+            for (project, dir) in profile.projects.iter().zip(&dirs) {
+                problems.extend(
+                    check_checksums(&project.checksums, &root.join(dir), &[])
+                        .into_iter()
+                        .map(|problem| format!("{}/{}", dir, problem)),
+                );
+            }
+            let count = profile.checksums.len()
+                + profile
+                    .projects
+                    .iter()
+                    .map(|p| p.checksums.len())
+                    .sum::<usize>();
+            (profile.version, problems, count)
+        }
+    };
+
+    println!("Verifying: {}", backup_path.display());
+    println!("  Format version: {}", version);
+    println!();
+
+    if checksum_count == 0 && version >= backup::CHECKSUMS_VERSION {
+        // A stripped manifest must not pass as verified
+        problems.push(format!(
+            "No checksums recorded, though format version {} requires them",
+            version
+        ));
+    } else if checksum_count == 0 {
+        println!(
+            "{} No checksums recorded (format version {})",
+            "Warning:".yellow(),
+            version
+        );
+    } else if problems.is_empty() {
+        println!(
+            "{} {} file(s) match their SHA-256 checksums",
+            "OK:".green(),
+            checksum_count
+        );
+    }
+
+    for db in walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() == "state.vscdb")
+    {
+        let name = backup::archive_key(db.path().strip_prefix(root).unwrap_or(db.path()));
+        match integrity_check(db.path()) {
+            Ok(()) => println!("{} {}: integrity check passed", "OK:".green(), name),
+            Err(e) => problems.push(format!("{}: {:#}", name, e)),
+        }
+    }
+
+    for chat_file in walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() == GLOBAL_CHAT_FILE)
+    {
+        let name = backup::archive_key(
+            chat_file
+                .path()
+                .strip_prefix(root)
+                .unwrap_or(chat_file.path()),
+        );
+        match read_chat_rows(chat_file.path()) {
+            Ok(rows) => println!("{} {}: {} row(s) readable", "OK:".green(), name, rows.len()),
+            Err(e) => problems.push(format!("{}: {:#}", name, e)),
+        }
+    }
+
+    for problem in &problems {
+        println!("{} {}", "FAILED:".red(), problem);
+    }
+    println!();
+    if !problems.is_empty() {
+        bail!("Backup verification failed: {} problem(s)", problems.len());
+    }
+    println!("{}", "Backup is good.".green());
+    Ok(())
+}
+
+fn existing_backup(backup_file: &str) -> Result<PathBuf> {
+    let backup_path = PathBuf::from(backup_file);
+    if !backup_path.exists() {
+        bail!("Backup file does not exist: {}", backup_path.display());
+    }
+    Ok(backup_path)
+}
+
+/// Compare the files unpacked under `root` against their checksums
+///
+/// Files and directories in `ignored` are not reported as unlisted.
+fn check_checksums(
+    checksums: &BTreeMap<String, String>,
+    root: &Path,
+    ignored: &[&str],
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    if checksums.is_empty() {
+        return problems;
+    }
+
+    for (name, expected) in checksums {
+        let path = root.join(name);
+        let actual = File::open(&path).and_then(|mut file| {
+            let mut hasher = sha2::Sha256::default();
+            std::io::copy(&mut file, &mut hasher)?;
+            Ok(format!("{:x}", sha2::Digest::finalize(hasher)))
+        });
+        match actual {
+            Ok(actual) if &actual == expected => {}
+            Ok(_) => problems.push(format!("{}: checksum mismatch", name)),
+            Err(_) => problems.push(format!("{}: missing from archive", name)),
+        }
+    }
+
+    for entry in walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let name = backup::archive_key(entry.path().strip_prefix(root).unwrap_or(entry.path()));
+        let is_ignored = ignored.iter().any(|ignored| {
+            name == *ignored
+                || name
+                    .strip_prefix(ignored)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        });
+        if !is_ignored && !checksums.contains_key(&name) {
+            problems.push(format!("{}: not listed in the manifest", name));
+        }
+    }
+
+    problems
+}
+
+/// Run `PRAGMA integrity_check` on a database
+fn integrity_check(db_path: &Path) -> Result<()> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .with_context(|| format!("Failed to open database: {}", db_path.display()))?;
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let results = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Integrity check could not run")?;
+
+    if results != ["ok"] {
+        bail!("{}", results.join("; "));
+    }
+    Ok(())
+}
+
+fn read_chat_rows(chat_file: &Path) -> Result<Vec<GlobalChatRow>> {
+    let file = File::open(chat_file)
+        .with_context(|| format!("Failed to open: {}", chat_file.display()))?;
+    let mut rows = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid row on line {}", number + 1))?;
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Sessions from the global chat data, or the workspace DB of older backups
+fn load_sessions(root: &Path) -> Result<Vec<BackupSession>> {
+    let chat_file = root.join(GLOBAL_CHAT_FILE);
+    let mut sessions = if chat_file.exists() {
+        sessions_from_chat_rows(&read_chat_rows(&chat_file)?)
+    } else {
+        sessions_from_workspace_db(&root.join("workspaceStorage").join("state.vscdb"))?
+    };
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at_ms.or(s.created_at_ms)));
+    Ok(sessions)
+}
+
+fn sessions_from_chat_rows(rows: &[GlobalChatRow]) -> Vec<BackupSession> {
+    let mut messages: HashMap<&str, usize> = HashMap::new();
+    for row in rows {
+        if let GlobalChatRow::DiskKv { key, .. } = row {
+            if let Some((id, _)) = key
+                .strip_prefix("bubbleId:")
+                .and_then(|rest| rest.split_once(':'))
+            {
+                *messages.entry(id).or_default() += 1;
+            }
+        }
+    }
+
+    rows.iter()
+        .filter_map(|row| match row {
+            GlobalChatRow::Header { value } => parse_session(value),
+            GlobalChatRow::DiskKv { .. } => None,
+        })
+        .map(|mut session| {
+            session.messages = Some(messages.get(session.id.as_str()).copied().unwrap_or(0));
+            session
+        })
+        .collect()
+}
+
+fn sessions_from_workspace_db(db_path: &Path) -> Result<Vec<BackupSession>> {
+    if !db_path.exists() {
+        return Ok(Vec::new());
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open database: {}", db_path.display()))?;
+    let data: Option<String> = conn
+        .query_row(
+            "SELECT value FROM ItemTable WHERE key = 'composer.composerData'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to query composer.composerData")?;
+    let Some(data) = data else {
+        return Ok(Vec::new());
+    };
+
+    let json: Value =
+        serde_json::from_str(&data).context("Failed to parse workspace composer data")?;
+    Ok(json
+        .get("allComposers")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(parse_session)
+        .collect())
+}
+
+fn parse_session(value: &Value) -> Option<BackupSession> {
+    Some(BackupSession {
+        id: value.get("composerId")?.as_str()?.to_string(),
+        title: value
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string),
+        created_at_ms: value.get("createdAt").and_then(|v| v.as_i64()),
+        updated_at_ms: value.get("lastUpdatedAt").and_then(|v| v.as_i64()),
+        messages: None,
+    })
+}
+
+/// Folder or workspace URI recorded in an archived `workspace.json`
+fn workspace_folder(workspace_dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(workspace_dir.join("workspace.json")).ok()?;
+    let json: Value = serde_json::from_str(&content).ok()?;
+    json.get("folder")
+        .or_else(|| json.get("workspace"))
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+}
+
+/// Number and total size of the files under a path
+fn count_files(path: &Path) -> (usize, u64) {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .fold((0, 0), |(files, size), e| {
+            (files + 1, size + e.metadata().map(|m| m.len()).unwrap_or(0))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::backup::BackupContents;
+
+    #[test]
+    fn test_sessions_from_chat_rows() {
+        let rows = vec![
+            GlobalChatRow::Header {
+                value: serde_json::json!({"composerId": "c1", "name": " Fix bug ", "createdAt": 1000}),
+            },
+            GlobalChatRow::Header {
+                value: serde_json::json!({"composerId": "c2", "name": ""}),
+            },
+            GlobalChatRow::DiskKv {
+                key: "composerData:c1".to_string(),
+                value: "{}".to_string(),
+            },
+            GlobalChatRow::DiskKv {
+                key: "bubbleId:c1:b1".to_string(),
+                value: "{}".to_string(),
+            },
+            GlobalChatRow::DiskKv {
+                key: "bubbleId:c1:b2".to_string(),
+                value: "{}".to_string(),
+            },
+        ];
+
+        let sessions = sessions_from_chat_rows(&rows);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].title.as_deref(), Some("Fix bug"));
+        assert_eq!(sessions[0].created_at_ms, Some(1000));
+        assert_eq!(sessions[0].messages, Some(2));
+        assert_eq!(sessions[1].title, None);
+        assert_eq!(sessions[1].messages, Some(0));
+    }
+
+    #[test]
+    fn test_check_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("projects")).unwrap();
+        fs::write(dir.path().join("projects/mcp.json"), "{}").unwrap();
+        fs::write(dir.path().join("projects/extra.txt"), "x").unwrap();
+        fs::write(dir.path().join("manifest.json"), "{}").unwrap();
+
+        let mut manifest = BackupManifest {
+            version: backup::MANIFEST_VERSION,
+            project_path: "/p".to_string(),
+            folder_id: "p".to_string(),
+            workspace_hash: "h".to_string(),
+            created_at: 0,
+            includes: BackupContents {
+                workspace_storage: false,
+                projects_data: true,
+                global_chat_sessions: 0,
+            },
+            checksums: BTreeMap::from([
+                ("projects/mcp.json".to_string(), backup::sha256_hex(b"{}")),
+                ("projects/extra.txt".to_string(), backup::sha256_hex(b"x")),
+            ]),
+            kind: Default::default(),
+        };
+        assert!(check_checksums(&manifest.checksums, dir.path(), &["manifest.json"]).is_empty());
+
+        fs::write(dir.path().join("projects/extra.txt"), "changed").unwrap();
+        fs::write(dir.path().join("projects/new.txt"), "").unwrap();
+        manifest
+            .checksums
+            .insert("projects/gone.txt".to_string(), backup::sha256_hex(b""));
+        assert_eq!(
+            check_checksums(&manifest.checksums, dir.path(), &["manifest.json"]),
+            vec![
+                "projects/extra.txt: checksum mismatch",
+                "projects/gone.txt: missing from archive",
+                "projects/new.txt: not listed in the manifest",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_integrity_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("state.vscdb");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch("CREATE TABLE ItemTable (key TEXT PRIMARY KEY, value BLOB);")
+            .unwrap();
+        assert!(integrity_check(&db_path).is_ok());
+
+        fs::write(&db_path, b"not a database").unwrap();
+        assert!(integrity_check(&db_path).is_err());
+    }
+}
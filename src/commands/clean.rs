@@ -114,7 +114,7 @@ pub fn execute(dry_run: bool, yes: bool) -> Result<()> {
 }
 
 /// Find workspaces whose project folders no longer exist
-fn find_orphaned_workspaces(workspace_storage_dir: &PathBuf) -> Result<Vec<OrphanedWorkspace>> {
+pub fn find_orphaned_workspaces(workspace_storage_dir: &PathBuf) -> Result<Vec<OrphanedWorkspace>> {
     let mut orphaned = Vec::new();
 
     let entries = fs::read_dir(workspace_storage_dir)
@@ -143,7 +143,12 @@ fn find_orphaned_workspaces(workspace_storage_dir: &PathBuf) -> Result<Vec<Orpha
             Err(_) => continue,
         };
 
-        let folder_url = match ws.get("folder").and_then(|v| v.as_str()) {
+        // Multi-root workspaces are orphaned once their .code-workspace file is gone
+        let folder_url = match ws
+            .get("folder")
+            .or_else(|| ws.get("workspace"))
+            .and_then(|v| v.as_str())
+        {
             Some(f) => f.to_string(),
             None => continue,
         };
@@ -177,7 +182,7 @@ fn find_orphaned_workspaces(workspace_storage_dir: &PathBuf) -> Result<Vec<Orpha
     }
 
     // Sort by size (largest first)
-    orphaned.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes));
+    orphaned.sort_by_key(|o| std::cmp::Reverse(o.size_bytes));
 
     Ok(orphaned)
 }
@@ -4,12 +4,18 @@
 //! - Creates new UUIDs for all references
 //! - Original project remains intact
 //! - Both projects have independent chat history
+//!
+//! Each clone is journaled like a rename, so it shows up in `undo --list`
+//! and its Cursor data can be removed again with `undo`.
 
 use anyhow::{bail, Context, Result};
 use owo_colors::OwoColorize;
 use std::path::PathBuf;
 use uuid::Uuid;
 
+use super::rename::RenamePlan;
+use super::rename_journal::{self, JournalStatus, Operation, RenameJournal, RenameStep};
+use super::safety_backups;
 use super::utils;
 use crate::config;
 use crate::cursor::{folder_id, workspace};
@@ -77,14 +83,37 @@ pub fn execute(old_path: &str, new_path: &str, dry_run: bool) -> Result<()> {
         return Ok(());
     }
 
+    let new_folder_id = folder_id::path_to_folder_id(&new_path);
+    let plan = RenamePlan {
+        new_projects_dir_existed: cursor_projects_dir.join(&new_folder_id).exists(),
+        old_path: old_path.clone(),
+        new_path: new_path.clone(),
+        copy_mode: true,
+        force_index: false,
+        old_folder_id: old_folder_id.clone(),
+        new_folder_id: new_folder_id.clone(),
+        old_workspace_hash: old_workspace_hash.clone(),
+        new_workspace_hash: None,
+        new_workspace_dir_existed: false,
+        merge_existing: false,
+        folder_moved: false,
+        multi_root: Vec::new(),
+    };
+    let backup_dir = rename_journal::create_backup_dir("clone")?;
+    let mut journal = RenameJournal::new(Operation::Clone, plan, Some(backup_dir))?;
+
     // Step 1: Copy project folder
     println!("Step 1: Copying project folder...");
+    journal.start(RenameStep::ProjectFolder)?;
     utils::copy_dir(&old_path, &new_path)?;
     println!("  -> {}", new_path.display());
 
     // Compute new identifiers (after creating the folder)
-    let new_folder_id = folder_id::path_to_folder_id(&new_path);
     let new_workspace_hash = workspace::compute_workspace_hash(&new_path)?;
+    journal.plan.new_workspace_dir_existed =
+        workspace_storage_dir.join(&new_workspace_hash).exists();
+    journal.plan.new_workspace_hash = Some(new_workspace_hash.clone());
+    journal.finish(RenameStep::ProjectFolder)?;
 
     println!();
     println!("New identifiers:");
@@ -94,6 +123,7 @@ pub fn execute(old_path: &str, new_path: &str, dry_run: bool) -> Result<()> {
 
     // Step 2: Clone projects data
     let new_projects_dir = cursor_projects_dir.join(&new_folder_id);
+    journal.start(RenameStep::ProjectsData)?;
     if has_projects {
         println!("Step 2: Cloning projects/ data...");
         if let Some(parent) = new_projects_dir.parent() {
@@ -104,9 +134,11 @@ pub fn execute(old_path: &str, new_path: &str, dry_run: bool) -> Result<()> {
     } else {
         println!("Step 2: No projects/ data to clone");
     }
+    journal.finish(RenameStep::ProjectsData)?;
 
     // Step 3: Clone and update workspace storage
     let new_workspace_dir = workspace_storage_dir.join(&new_workspace_hash);
+    journal.start(RenameStep::WorkspaceStorage)?;
     if has_workspace {
         println!("Step 3: Cloning workspaceStorage/ data...");
         if let Some(parent) = new_workspace_dir.parent() {
@@ -135,6 +167,8 @@ pub fn execute(old_path: &str, new_path: &str, dry_run: bool) -> Result<()> {
     } else {
         println!("Step 3: No workspaceStorage/ data to clone");
     }
+    journal.finish(RenameStep::WorkspaceStorage)?;
+    journal.set_status(JournalStatus::Completed)?;
 
     println!();
     println!("{}", "Clone complete!".green());
@@ -143,6 +177,10 @@ pub fn execute(old_path: &str, new_path: &str, dry_run: bool) -> Result<()> {
     println!("  Original: {}", old_path.display());
     println!("  Clone: {}", new_path.display());
 
+    if let Some(backup_dir) = &journal.backup_dir {
+        safety_backups::apply_retention_policy(std::slice::from_ref(backup_dir));
+    }
+
     Ok(())
 }
 
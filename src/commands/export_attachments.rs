@@ -0,0 +1,443 @@
+//! Attachments and context referenced by chat bubbles
+//!
+//! Besides `text`, a bubble can carry the context chips the user attached:
+//! files, folders, code selections, terminal output, docs, and images, plus
+//! the code blocks an assistant bubble proposed. They
+//! are exported alongside the message so a conversation still makes sense
+//! without the original workspace at hand.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::export_tools;
+
+/// Something attached to (or referenced by) a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Attachment {
+    /// A whole file
+    File {
+        /// File path
+        path: String,
+    },
+    /// A folder
+    Folder {
+        /// Folder path
+        path: String,
+    },
+    /// A selection of code from a file
+    Selection {
+        /// File the selection came from
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        /// First selected line (1-based)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_line: Option<i64>,
+        /// Last selected line (inclusive)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        end_line: Option<i64>,
+        /// Selected text
+        text: String,
+    },
+    /// A code block proposed in the message
+    CodeBlock {
+        /// File the code block applies to
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        /// Language of the code
+        #[serde(skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+        /// Code text
+        text: String,
+    },
+    /// Selected terminal output
+    Terminal {
+        /// Selected text
+        text: String,
+    },
+    /// A documentation source
+    Doc {
+        /// Display name
+        name: String,
+        /// Source URL
+        #[serde(skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+    },
+    /// An image (the image data itself is not exported)
+    Image {
+        /// Image file path
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        /// Width in pixels
+        #[serde(skip_serializing_if = "Option::is_none")]
+        width: Option<i64>,
+        /// Height in pixels
+        #[serde(skip_serializing_if = "Option::is_none")]
+        height: Option<i64>,
+    },
+}
+
+/// Extract attachments from a bubble's `context`, `codeBlocks` and `images`
+pub fn extract_attachments(bubble: &Value) -> Vec<Attachment> {
+    let mut attachments = Vec::new();
+
+    if let Some(context) = bubble.get("context") {
+        for item in array(context, "fileSelections") {
+            if let Some(path) = uri_path(item) {
+                attachments.push(Attachment::File { path });
+            }
+        }
+
+        for item in array(context, "folderSelections") {
+            if let Some(path) =
+                str_field(item, &["relativePath", "path"]).or_else(|| uri_path(item))
+            {
+                attachments.push(Attachment::Folder { path });
+            }
+        }
+
+        for item in array(context, "selections") {
+            let Some(text) = str_field(item, &["text", "rawText"]).filter(|t| !t.is_empty()) else {
+                continue;
+            };
+            let range = item.get("range");
+            let line = |keys: &[&str]| range.and_then(|r| i64_field(r, keys));
+            let (start, end) = (
+                line(&["selectionStartLineNumber", "startLineNumber"]),
+                line(&["positionLineNumber", "endLineNumber"]),
+            );
+            // Selections made bottom-up have their anchor below the cursor
+            let (start_line, end_line) = match (start, end) {
+                (Some(a), Some(b)) if a > b => (Some(b), Some(a)),
+                other => other,
+            };
+            attachments.push(Attachment::Selection {
+                path: uri_path(item),
+                start_line,
+                end_line,
+                text,
+            });
+        }
+
+        for item in array(context, "terminalSelections") {
+            if let Some(text) = str_field(item, &["text", "rawText"]).filter(|t| !t.is_empty()) {
+                attachments.push(Attachment::Terminal { text });
+            }
+        }
+
+        for item in array(context, "selectedDocs") {
+            if let Some(name) = str_field(item, &["name", "docId"]) {
+                attachments.push(Attachment::Doc {
+                    name,
+                    url: str_field(item, &["url"]),
+                });
+            }
+        }
+    }
+
+    for item in array(bubble, "codeBlocks") {
+        if let Some(text) = str_field(item, &["content", "code"]).filter(|t| !t.is_empty()) {
+            attachments.push(Attachment::CodeBlock {
+                path: uri_path(item),
+                language: str_field(item, &["languageId", "language"]).filter(|l| !l.is_empty()),
+                text,
+            });
+        }
+    }
+
+    for item in array(bubble, "images") {
+        let dimension = item.get("dimension");
+        attachments.push(Attachment::Image {
+            path: str_field(item, &["path"]).or_else(|| uri_path(item)),
+            width: dimension.and_then(|d| i64_field(d, &["width"])),
+            height: dimension.and_then(|d| i64_field(d, &["height"])),
+        });
+    }
+
+    attachments
+}
+
+/// Render attachments as a markdown section (empty if there are none)
+pub fn attachments_to_markdown(attachments: &[Attachment]) -> String {
+    if attachments.is_empty() {
+        return String::new();
+    }
+
+    let mut md = String::from("**Attachments:**\n\n");
+    let mut blocks = String::new();
+
+    for attachment in attachments {
+        match attachment {
+            Attachment::File { path } => md.push_str(&format!("- 📄 `{}`\n", path)),
+            Attachment::Folder { path } => md.push_str(&format!("- 📁 `{}`\n", path)),
+            Attachment::Selection {
+                path,
+                start_line,
+                end_line,
+                text,
+            } => {
+                let source = path.as_deref().unwrap_or("selection");
+                let label = format!(
+                    "`{}`{}",
+                    source,
+                    export_tools::line_range(*start_line, *end_line)
+                );
+                md.push_str(&format!("- ✂️ {}\n", label));
+                blocks.push_str(&format!(
+                    "{}\n\n{}\n",
+                    label,
+                    export_tools::fenced(text, language_for_path(path.as_deref()))
+                ));
+            }
+            Attachment::CodeBlock {
+                path,
+                language,
+                text,
+            } => {
+                let label = match path {
+                    Some(path) => format!("Code block for `{}`", path),
+                    None => "Code block".to_string(),
+                };
+                md.push_str(&format!("- 🧩 {}\n", label));
+                blocks.push_str(&format!(
+                    "{}\n\n{}\n",
+                    label,
+                    export_tools::fenced(text, code_block_language(language, path))
+                ));
+            }
+            Attachment::Terminal { text } => {
+                md.push_str("- 💻 Terminal output\n");
+                blocks.push_str(&format!(
+                    "Terminal output\n\n{}\n",
+                    export_tools::fenced(text, "")
+                ));
+            }
+            Attachment::Doc { name, url } => match url {
+                Some(url) if is_web_url(url) => {
+                    md.push_str(&format!("- 📚 [{}](<{}>)\n", name, url))
+                }
+                Some(url) => md.push_str(&format!("- 📚 {} (`{}`)\n", name, url)),
+                None => md.push_str(&format!("- 📚 {}\n", name)),
+            },
+            Attachment::Image {
+                path,
+                width,
+                height,
+            } => {
+                md.push_str(&format!("- 🖼️ {}", image_label(path.as_deref())));
+                if let (Some(w), Some(h)) = (width, height) {
+                    md.push_str(&format!(" ({}×{})", w, h));
+                }
+                md.push('\n');
+            }
+        }
+    }
+
+    md.push('\n');
+    md.push_str(&blocks);
+    md
+}
+
+/// Only http(s) URLs become links; exports are shared, so a `javascript:` or
+/// `data:` URL must not be clickable
+pub(crate) fn is_web_url(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Fence language of a code block, falling back to its file extension
+pub(crate) fn code_block_language<'a>(
+    language: &'a Option<String>,
+    path: &Option<String>,
+) -> &'a str {
+    language
+        .as_deref()
+        .unwrap_or_else(|| language_for_path(path.as_deref()))
+}
+
+/// Short label for an image attachment
+fn image_label(path: Option<&str>) -> String {
+    match path {
+        Some(path) => format!("`{}`", path),
+        None => "image".to_string(),
+    }
+}
+
+/// Guess a code fence language from a file extension
+pub(crate) fn language_for_path(path: Option<&str>) -> &'static str {
+    let ext = path
+        .and_then(|p| p.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+
+    match ext.as_deref() {
+        Some("rs") => "rust",
+        Some("py") => "python",
+        Some("js" | "mjs" | "cjs") => "javascript",
+        Some("ts" | "mts") => "typescript",
+        Some("tsx") => "tsx",
+        Some("jsx") => "jsx",
+        Some("go") => "go",
+        Some("java") => "java",
+        Some("c" | "h") => "c",
+        Some("cpp" | "cc" | "hpp") => "cpp",
+        Some("rb") => "ruby",
+        Some("sh" | "bash" | "zsh") => "shell",
+        Some("json") => "json",
+        Some("toml") => "toml",
+        Some("yaml" | "yml") => "yaml",
+        Some("md") => "markdown",
+        Some("sql") => "sql",
+        Some("html") => "html",
+        Some("css") => "css",
+        _ => "",
+    }
+}
+
+/// Path of a `{uri: {fsPath | path | external}}` reference
+fn uri_path(item: &Value) -> Option<String> {
+    let uri = item.get("uri")?;
+    if let Some(s) = uri.as_str() {
+        return Some(s.to_string());
+    }
+    str_field(uri, &["fsPath", "path", "external"])
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(key)
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+}
+
+fn str_field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| value.get(*key))
+        .find_map(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+fn i64_field(value: &Value, keys: &[&str]) -> Option<i64> {
+    keys.iter()
+        .filter_map(|key| value.get(*key))
+        .find_map(|v| v.as_i64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_attachments_from_context() {
+        let bubble = serde_json::json!({
+            "text": "why does this fail?",
+            "context": {
+                "fileSelections": [{"uri": {"fsPath": "/proj/src/db.rs", "external": "file:///proj/src/db.rs"}}],
+                "folderSelections": [{"relativePath": "src/migrations"}],
+                "selections": [{
+                    "uri": {"fsPath": "/proj/src/main.rs"},
+                    "range": {"selectionStartLineNumber": 12, "positionLineNumber": 10},
+                    "text": "fn main() {}"
+                }],
+                "terminalSelections": [{"text": "error[E0308]"}],
+                "selectedDocs": [{"name": "Rust std", "url": "https://doc.rust-lang.org/std/"}]
+            },
+            "codeBlocks": [
+                {"uri": {"path": "/proj/src/db.rs"}, "content": "let pool = connect()?;", "languageId": "rust"},
+                {"uri": {"path": "/proj/src/empty.rs"}, "content": ""}
+            ],
+            "images": [{"path": "/tmp/shot.png", "dimension": {"width": 800, "height": 600}}]
+        });
+
+        assert_eq!(
+            extract_attachments(&bubble),
+            vec![
+                Attachment::File {
+                    path: "/proj/src/db.rs".to_string()
+                },
+                Attachment::Folder {
+                    path: "src/migrations".to_string()
+                },
+                Attachment::Selection {
+                    path: Some("/proj/src/main.rs".to_string()),
+                    start_line: Some(10),
+                    end_line: Some(12),
+                    text: "fn main() {}".to_string(),
+                },
+                Attachment::Terminal {
+                    text: "error[E0308]".to_string()
+                },
+                Attachment::Doc {
+                    name: "Rust std".to_string(),
+                    url: Some("https://doc.rust-lang.org/std/".to_string()),
+                },
+                Attachment::CodeBlock {
+                    path: Some("/proj/src/db.rs".to_string()),
+                    language: Some("rust".to_string()),
+                    text: "let pool = connect()?;".to_string(),
+                },
+                Attachment::Image {
+                    path: Some("/tmp/shot.png".to_string()),
+                    width: Some(800),
+                    height: Some(600),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_attachments_empty() {
+        let bubble = serde_json::json!({"text": "hi", "context": {"fileSelections": []}});
+        assert!(extract_attachments(&bubble).is_empty());
+        assert_eq!(attachments_to_markdown(&[]), "");
+    }
+
+    #[test]
+    fn test_attachments_to_markdown() {
+        let md = attachments_to_markdown(&[
+            Attachment::File {
+                path: "src/db.rs".to_string(),
+            },
+            Attachment::Selection {
+                path: Some("src/main.rs".to_string()),
+                start_line: Some(1),
+                end_line: Some(2),
+                text: "fn main() {}".to_string(),
+            },
+            Attachment::Image {
+                path: None,
+                width: Some(10),
+                height: Some(20),
+            },
+        ]);
+
+        assert!(md.starts_with("**Attachments:**\n\n- 📄 `src/db.rs`\n"));
+        assert!(md.contains("- ✂️ `src/main.rs` (lines 1–2)\n"));
+        assert!(md.contains("- 🖼️ image (10×20)\n"));
+        assert!(md.contains("```rust\nfn main() {}\n```"));
+    }
+
+    #[test]
+    fn test_attachments_to_markdown_code_blocks_and_docs() {
+        let md = attachments_to_markdown(&[
+            Attachment::CodeBlock {
+                path: Some("src/app.py".to_string()),
+                language: None,
+                text: "print(1)".to_string(),
+            },
+            Attachment::Doc {
+                name: "Guide".to_string(),
+                url: Some("https://example.com/guide".to_string()),
+            },
+            Attachment::Doc {
+                name: "Trap".to_string(),
+                url: Some("javascript:alert(1)".to_string()),
+            },
+        ]);
+
+        assert!(md.contains("- 🧩 Code block for `src/app.py`\n"));
+        assert!(md.contains("```python\nprint(1)\n```"));
+        assert!(md.contains("- 📚 [Guide](<https://example.com/guide>)\n"));
+        assert!(md.contains("- 📚 Trap (`javascript:alert(1)`)\n"));
+        assert!(!md.contains("](javascript:"));
+    }
+}
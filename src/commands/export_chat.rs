@@ -21,16 +21,22 @@ use anyhow::{bail, Context, Result};
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
 
-use super::utils;
-use crate::cursor::chat_sessions;
+use super::export_attachments::{self, Attachment};
+use super::export_tools::{self, ToolDetails};
+use super::{export_html, list, utils};
+use crate::cursor::chat_sessions::{self, SessionFilter};
+use crate::cursor::search_index;
 
 /// Output format for chat export
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExportFormat {
     Markdown,
     Json,
+    Jsonl,
+    Html,
 }
 
 impl ExportFormat {
@@ -38,13 +44,34 @@ impl ExportFormat {
         match s.to_lowercase().as_str() {
             "md" | "markdown" => Some(Self::Markdown),
             "json" => Some(Self::Json),
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            "html" | "htm" => Some(Self::Html),
             _ => None,
         }
     }
+
+    /// File extension for this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+            Self::Html => "html",
+        }
+    }
 }
 
+/// Manifest kept in the output directory by incremental split exports
+const EXPORT_MANIFEST_FILE: &str = ".cursor-helper-export.json";
+
+/// Default character limit for exported tool parameters
+pub const DEFAULT_TOOL_PARAMS_LIMIT: usize = 500;
+
+/// Default character limit for exported tool results
+pub const DEFAULT_TOOL_RESULT_LIMIT: usize = 1000;
+
 /// Export options
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct ExportOptions {
     /// Include thinking/reasoning blocks
     pub with_thinking: bool,
@@ -56,6 +83,66 @@ pub struct ExportOptions {
     pub include_archived: bool,
     /// Exclude sessions with no messages
     pub exclude_blank: bool,
+    /// Read messages from the persistent search index
+    pub use_index: bool,
+    /// Date range, session ID, and title filters
+    pub filter: SessionFilter,
+    /// Only rewrite split files for sessions that changed since the last export
+    pub incremental: bool,
+    /// With `incremental`, remove files for sessions that no longer exist
+    pub prune: bool,
+    /// Maximum characters kept from tool parameters (None = unlimited)
+    pub tool_params_limit: Option<usize>,
+    /// Maximum characters kept from tool results (None = unlimited)
+    pub tool_result_limit: Option<usize>,
+    /// Nest sub-agent and sub-composer sessions under their parent
+    pub include_children: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            with_thinking: false,
+            with_tools: false,
+            with_stats: false,
+            include_archived: false,
+            exclude_blank: false,
+            use_index: false,
+            filter: SessionFilter::default(),
+            incremental: false,
+            prune: false,
+            tool_params_limit: Some(DEFAULT_TOOL_PARAMS_LIMIT),
+            tool_result_limit: Some(DEFAULT_TOOL_RESULT_LIMIT),
+            include_children: false,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Settings that affect exported content, recorded in the incremental manifest
+    fn content_settings(&self) -> String {
+        let mut settings = Vec::new();
+        if self.with_thinking {
+            settings.push("thinking");
+        }
+        if self.with_tools {
+            settings.push("tools");
+        }
+        if self.with_stats {
+            settings.push("stats");
+        }
+        if self.include_children {
+            settings.push("children");
+        }
+
+        let limit = |limit: Option<usize>| limit.map_or("unlimited".to_string(), |n| n.to_string());
+        let mut settings: Vec<String> = settings.into_iter().map(String::from).collect();
+        if self.with_tools {
+            settings.push(format!("params={}", limit(self.tool_params_limit)));
+            settings.push(format!("result={}", limit(self.tool_result_limit)));
+        }
+        settings.join(",")
+    }
 }
 
 /// Tool call information
@@ -72,6 +159,24 @@ pub struct ToolCall {
     /// Status: completed, failed, etc.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status: Option<String>,
+    /// Set when `params` was truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params_truncation: Option<Truncation>,
+    /// Set when `result` was truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_truncation: Option<Truncation>,
+    /// Decoded fields for well-known tools (file reads, edits, commands, searches)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<ToolDetails>,
+}
+
+/// Where a field was cut off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Truncation {
+    /// Number of characters kept
+    pub truncated_at: usize,
+    /// Length of the original field in characters
+    pub original_length: usize,
 }
 
 /// A single message in a chat conversation
@@ -96,6 +201,9 @@ pub struct ChatMessage {
     /// Token count
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tokens: Option<TokenCount>,
+    /// Attached files, selections, and other context
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
 }
 
 /// Token usage statistics
@@ -121,6 +229,30 @@ pub struct ChatSession {
     /// When the session was last updated
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<i64>,
+    /// Last update time in milliseconds (change detection for incremental export)
+    #[serde(skip)]
+    pub updated_at_ms: Option<i64>,
+    /// Sub-agent and sub-composer sessions spawned from this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ChatSession>,
+}
+
+/// A single JSON Lines record: one message plus the session it belongs to
+#[derive(Debug, Serialize)]
+pub struct JsonlRecord<'a> {
+    /// Session UUID
+    pub session_id: &'a str,
+    /// Session title if available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_title: Option<&'a str>,
+    /// Project path
+    pub project_path: &'a str,
+    /// Parent session UUID, for messages from sub-agent sessions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<&'a str>,
+    /// The message itself
+    #[serde(flatten)]
+    pub message: &'a ChatMessage,
 }
 
 /// Export result containing all chat sessions
@@ -182,6 +314,13 @@ pub fn execute(
         }
     };
 
+    let project_path_str = project_path.to_string_lossy().to_string();
+
+    // JSON Lines streams records as sessions are read instead of building the export
+    if format == ExportFormat::Jsonl && !split {
+        return stream_jsonl(&workspace_dir, &project_path_str, output, options);
+    }
+
     // Extract chat sessions
     let mut sessions = extract_chat_sessions(&workspace_dir, options)?;
 
@@ -202,7 +341,6 @@ pub fn execute(
 
     println!("Found {} chat session(s)", sessions.len());
 
-    let project_path_str = project_path.to_string_lossy().to_string();
     let exported_at = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map(|d| d.as_secs() as i64)
@@ -220,6 +358,7 @@ pub fn execute(
             format,
             &project_path_str,
             exported_at,
+            options,
         )?;
     } else {
         // Build single export
@@ -233,6 +372,8 @@ pub fn execute(
         let content = match format {
             ExportFormat::Markdown => format_as_markdown(&export),
             ExportFormat::Json => serde_json::to_string_pretty(&export)?,
+            ExportFormat::Jsonl => format_as_jsonl(&export.sessions, &export.project_path)?,
+            ExportFormat::Html => export_html::format_as_html(&export),
         };
 
         // Write or print
@@ -277,6 +418,7 @@ pub fn execute_by_id(
             let content = fs::read_to_string(&workspace_json)?;
             let ws: serde_json::Value = serde_json::from_str(&content)?;
             ws.get("folder")
+                .or_else(|| ws.get("workspace"))
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string())
                 .unwrap_or_else(|| format!("workspace:{}", workspace_id))
@@ -285,6 +427,11 @@ pub fn execute_by_id(
         }
     };
 
+    // JSON Lines streams records as sessions are read instead of building the export
+    if format == ExportFormat::Jsonl && !split {
+        return stream_jsonl(&workspace_dir, &project_path, output, options);
+    }
+
     // Extract chat sessions
     let mut sessions = extract_chat_sessions(&workspace_dir, options)?;
 
@@ -316,7 +463,14 @@ pub fn execute_by_id(
             anyhow::anyhow!("--split requires --output to specify the output directory")
         })?;
 
-        write_split_output(&sessions, output_dir, format, &project_path, exported_at)?;
+        write_split_output(
+            &sessions,
+            output_dir,
+            format,
+            &project_path,
+            exported_at,
+            options,
+        )?;
     } else {
         // Build single export
         let export = ChatExport {
@@ -329,6 +483,8 @@ pub fn execute_by_id(
         let content = match format {
             ExportFormat::Markdown => format_as_markdown(&export),
             ExportFormat::Json => serde_json::to_string_pretty(&export)?,
+            ExportFormat::Jsonl => format_as_jsonl(&export.sessions, &export.project_path)?,
+            ExportFormat::Html => export_html::format_as_html(&export),
         };
 
         // Write or print
@@ -344,48 +500,477 @@ pub fn execute_by_id(
     Ok(())
 }
 
+/// Manifest of an incremental split export, mapping composer IDs to files
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ExportManifest {
+    /// File extension of the exported files
+    format: String,
+    /// Content settings the files were written with (see `ExportOptions::content_settings`)
+    settings: String,
+    /// Exported sessions keyed by composer ID
+    sessions: std::collections::BTreeMap<String, ExportManifestEntry>,
+}
+
+/// A session entry in the incremental export manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExportManifestEntry {
+    /// File name within the output directory
+    file: String,
+    /// Session update time when the file was written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated_at_ms: Option<i64>,
+    /// Latest update time across the session and its children when the file was written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tree_updated_at_ms: Option<i64>,
+}
+
+impl ExportManifest {
+    fn read(output_dir: &Path) -> Result<Self> {
+        let path = output_dir.join(EXPORT_MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse: {}", path.display()))
+    }
+
+    fn write(&self, output_dir: &Path) -> Result<()> {
+        let path = output_dir.join(EXPORT_MANIFEST_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write: {}", path.display()))
+    }
+}
+
+/// Index of an `--all` export, written next to the per-project directories
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportIndex {
+    /// Export timestamp
+    pub exported_at: i64,
+    /// Exported projects
+    pub projects: Vec<ExportIndexProject>,
+}
+
+/// A project entry in the export index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportIndexProject {
+    /// Project path (prefixed with the remote for remote projects)
+    pub project_path: String,
+    /// Workspace storage ID
+    pub workspace_id: String,
+    /// Directory (relative to the output root) holding this project's sessions
+    pub directory: String,
+    /// Exported sessions
+    pub sessions: Vec<ExportIndexSession>,
+}
+
+/// A session entry in the export index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportIndexSession {
+    /// Session UUID
+    pub id: String,
+    /// Session title if available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// File name within the project directory
+    pub file: String,
+}
+
+/// Execute the export-chat command for every discovered project
+///
+/// Writes `<output>/<project-slug>/<NNN-title>.<ext>` for each project with
+/// chat history, plus an index file at the root of `output`.
+pub fn execute_all(format: ExportFormat, output: &str, options: &ExportOptions) -> Result<()> {
+    let workspace_storage_dir = crate::config::workspace_storage_dir()?;
+    let projects = list::discover_projects()?;
+
+    if projects.is_empty() {
+        println!("No Cursor projects found.");
+        return Ok(());
+    }
+
+    fs::create_dir_all(output)
+        .with_context(|| format!("Failed to create directory: {}", output))?;
+
+    let exported_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let mut index = ExportIndex {
+        exported_at,
+        projects: Vec::new(),
+    };
+    let slugs = project_slugs(&projects);
+
+    for (project, slug) in projects.iter().zip(slugs) {
+        let workspace_dir = workspace_storage_dir.join(&project.folder_id);
+        let project_path = project.display_path();
+
+        let mut sessions = match extract_chat_sessions(&workspace_dir, options) {
+            Ok(sessions) => sessions,
+            Err(err) => {
+                eprintln!("Warning: Skipping {}: {}", project_path, err);
+                continue;
+            }
+        };
+        if options.exclude_blank {
+            sessions.retain(|s| !s.messages.is_empty());
+        }
+        let project_dir = Path::new(output).join(&slug);
+        if sessions.is_empty() {
+            // Still prune the files of a project whose sessions were all removed
+            if options.prune && project_dir.exists() {
+                write_split_output(
+                    &sessions,
+                    &project_dir.to_string_lossy(),
+                    format,
+                    &project_path,
+                    exported_at,
+                    options,
+                )?;
+            }
+            continue;
+        }
+
+        let files = write_split_output(
+            &sessions,
+            &project_dir.to_string_lossy(),
+            format,
+            &project_path,
+            exported_at,
+            options,
+        )?;
+
+        index.projects.push(ExportIndexProject {
+            project_path,
+            workspace_id: project.folder_id.clone(),
+            directory: slug,
+            sessions: sessions
+                .iter()
+                .zip(files)
+                .map(|(session, file)| ExportIndexSession {
+                    id: session.id.clone(),
+                    title: session.title.clone(),
+                    file,
+                })
+                .collect(),
+        });
+    }
+
+    let (index_name, index_content) = match format {
+        ExportFormat::Json | ExportFormat::Jsonl => {
+            ("index.json", serde_json::to_string_pretty(&index)?)
+        }
+        ExportFormat::Markdown | ExportFormat::Html => {
+            ("index.md", format_index_as_markdown(&index))
+        }
+    };
+    let index_path = Path::new(output).join(index_name);
+    fs::write(&index_path, index_content)
+        .with_context(|| format!("Failed to write: {}", index_path.display()))?;
+
+    let session_count: usize = index.projects.iter().map(|p| p.sessions.len()).sum();
+    println!(
+        "Exported {} sessions from {} projects to: {}",
+        session_count,
+        index.projects.len(),
+        output
+    );
+    println!("Index: {}", index_path.display());
+
+    Ok(())
+}
+
+/// Output directory names for `projects`, in order
+///
+/// Workspaces can share a path (local and remote, or a stale hash), so remote
+/// projects and every project with a shared slug get a suffix from their
+/// workspace ID. Names then do not depend on discovery order, which keeps
+/// `--incremental` output in place across runs.
+fn project_slugs(projects: &[list::Project]) -> Vec<String> {
+    let slugs: Vec<String> = projects
+        .iter()
+        .map(|project| project_slug(&project.display_path()))
+        .collect();
+
+    projects
+        .iter()
+        .zip(&slugs)
+        .map(|(project, slug)| {
+            let shared = slugs.iter().filter(|other| *other == slug).count() > 1;
+            if project.remote.is_some() || shared {
+                format!(
+                    "{}-{}",
+                    slug,
+                    &project.folder_id[..project.folder_id.len().min(8)]
+                )
+            } else {
+                slug.clone()
+            }
+        })
+        .collect()
+}
+
+/// Turn a project path into a single directory name
+fn project_slug(project_path: &str) -> String {
+    let mut slug = String::new();
+    for c in project_path.chars() {
+        if c.is_alphanumeric() || c == '.' || c == '_' {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug: String = slug.trim_end_matches('-').chars().take(100).collect();
+    if slug.is_empty() {
+        "project".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Format the export index as markdown with links to each session file
+fn format_index_as_markdown(index: &ExportIndex) -> String {
+    let mut md = String::new();
+
+    md.push_str("# Chat Export Index\n\n");
+    md.push_str(&format!(
+        "_Exported: {}_\n\n",
+        format_timestamp(index.exported_at)
+    ));
+
+    for project in &index.projects {
+        md.push_str(&format!("## {}\n\n", project.project_path));
+        md.push_str(&format!("_Workspace ID: {}_\n\n", project.workspace_id));
+        for session in &project.sessions {
+            let title = session.title.as_deref().unwrap_or("Untitled Session");
+            md.push_str(&format!(
+                "- [{}](<{}/{}>)\n",
+                title, project.directory, session.file
+            ));
+        }
+        md.push('\n');
+    }
+
+    md
+}
+
 /// Extract chat sessions from a workspace directory
 fn extract_chat_sessions(
     workspace_dir: &Path,
     options: &ExportOptions,
 ) -> Result<Vec<ChatSession>> {
+    let mut sessions = Vec::new();
+    for_each_chat_session(workspace_dir, options, |session| {
+        sessions.push(session);
+        Ok(())
+    })?;
+    Ok(sessions)
+}
+
+/// Read chat sessions one at a time (newest first), passing each to `visit`
+///
+/// Messages are only loaded for the session being visited, so callers that
+/// write sessions out as they go never hold the whole history in memory.
+fn for_each_chat_session(
+    workspace_dir: &Path,
+    options: &ExportOptions,
+    mut visit: impl FnMut(ChatSession) -> Result<()>,
+) -> Result<()> {
     let composers =
         chat_sessions::discover_workspace_sessions(workspace_dir, options.include_archived)?;
+    let mut composers = options.filter.apply(composers);
 
     if composers.is_empty() {
-        return Ok(vec![]);
+        return Ok(());
     }
 
+    // Sort by creation time (newest first)
+    composers.sort_by_key(|c| std::cmp::Reverse(c.created_at_ms));
+
     // Open global storage for bubble content (optional - may not exist on all setups)
     let global_conn = chat_sessions::open_global_state_db().ok().flatten();
+    let index = match (options.use_index, global_conn.as_ref()) {
+        (true, Some(gconn)) => Some(search_index::open_refreshed(gconn)?.0),
+        _ => None,
+    };
 
     // Build sessions with messages from global storage
-    let mut sessions = Vec::new();
-
     for composer in composers {
-        let messages = if let Some(ref gconn) = global_conn {
-            fetch_session_messages(gconn, &composer.composer_id, options).unwrap_or_default()
+        let (messages, children) = if let Some(ref gconn) = global_conn {
+            let conn =
+                search_index::session_connection(index.as_ref(), gconn, &composer.composer_id);
+            let messages =
+                fetch_session_messages(conn, &composer.composer_id, options).unwrap_or_default();
+            let children = if options.include_children {
+                let mut visited = std::collections::HashSet::from([composer.composer_id.clone()]);
+                fetch_child_sessions(
+                    gconn,
+                    index.as_ref(),
+                    &composer.composer_id,
+                    options,
+                    &mut visited,
+                )
+            } else {
+                vec![]
+            };
+            (messages, children)
         } else {
-            vec![]
+            (vec![], vec![])
         };
 
-        sessions.push(ChatSession {
+        visit(ChatSession {
             id: composer.composer_id.clone(),
             title: composer.title.clone(),
             messages,
             created_at: composer.created_at_ms.map(|ts| ts / 1000),
             updated_at: composer.updated_at_ms.map(|ts| ts / 1000),
+            updated_at_ms: composer.updated_at_ms,
+            children,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Recursively load the sub-agent and sub-composer sessions of a session
+///
+/// `visited` guards against cycles in the parent/child links.
+fn fetch_child_sessions(
+    global_conn: &Connection,
+    index: Option<&search_index::SearchIndex>,
+    parent_id: &str,
+    options: &ExportOptions,
+    visited: &mut std::collections::HashSet<String>,
+) -> Vec<ChatSession> {
+    let children = chat_sessions::load_child_sessions(global_conn, parent_id).unwrap_or_default();
+    let mut sessions = Vec::with_capacity(children.len());
+
+    for child in children {
+        if !visited.insert(child.composer_id.clone()) {
+            continue;
+        }
+
+        let conn = search_index::session_connection(index, global_conn, &child.composer_id);
+        let messages =
+            fetch_session_messages(conn, &child.composer_id, options).unwrap_or_default();
+        let grandchildren =
+            fetch_child_sessions(global_conn, index, &child.composer_id, options, visited);
+
+        if options.exclude_blank && messages.is_empty() && grandchildren.is_empty() {
+            continue;
+        }
+
+        sessions.push(ChatSession {
+            id: child.composer_id,
+            title: child.title,
+            messages,
+            created_at: child.created_at_ms.map(|ts| ts / 1000),
+            updated_at: child.updated_at_ms.map(|ts| ts / 1000),
+            updated_at_ms: child.updated_at_ms,
+            children: grandchildren,
         });
     }
 
-    // Sort by creation time (newest first)
-    sessions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    sessions
+}
 
-    Ok(sessions)
+/// Stream sessions as JSON Lines to a file or stdout
+///
+/// Progress goes to stderr so stdout can be piped straight into `jq`.
+fn stream_jsonl(
+    workspace_dir: &Path,
+    project_path: &str,
+    output: Option<&str>,
+    options: &ExportOptions,
+) -> Result<()> {
+    let writer: Box<dyn Write> = match output {
+        Some(output_path) => Box::new(
+            fs::File::create(output_path)
+                .with_context(|| format!("Failed to create: {}", output_path))?,
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = BufWriter::new(writer);
+
+    let mut session_count = 0;
+    let mut record_count = 0;
+
+    for_each_chat_session(workspace_dir, options, |session| {
+        if options.exclude_blank && session.messages.is_empty() {
+            return Ok(());
+        }
+        session_count += 1;
+        record_count += write_jsonl_records(&mut writer, &session, project_path)?;
+        Ok(())
+    })?;
+
+    writer
+        .flush()
+        .context("Failed to write JSON Lines output")?;
+
+    eprintln!(
+        "Exported {} message(s) from {} session(s)",
+        record_count, session_count
+    );
+    if let Some(output_path) = output {
+        eprintln!("Exported to: {}", output_path);
+    }
+
+    Ok(())
+}
+
+/// Write one JSON Lines record per message, returning the number written
+///
+/// Messages from child sessions follow their parent's, tagged with
+/// `parent_session_id`.
+fn write_jsonl_records(
+    writer: &mut impl Write,
+    session: &ChatSession,
+    project_path: &str,
+) -> Result<usize> {
+    write_jsonl_session(writer, session, None, project_path)
+}
+
+fn write_jsonl_session(
+    writer: &mut impl Write,
+    session: &ChatSession,
+    parent_session_id: Option<&str>,
+    project_path: &str,
+) -> Result<usize> {
+    for message in &session.messages {
+        let record = JsonlRecord {
+            session_id: &session.id,
+            session_title: session.title.as_deref(),
+            project_path,
+            parent_session_id,
+            message,
+        };
+        serde_json::to_writer(&mut *writer, &record)?;
+        writer.write_all(b"\n")?;
+    }
+
+    let mut count = session.messages.len();
+    for child in &session.children {
+        count += write_jsonl_session(writer, child, Some(&session.id), project_path)?;
+    }
+    Ok(count)
+}
+
+/// Format sessions as JSON Lines
+fn format_as_jsonl(sessions: &[ChatSession], project_path: &str) -> Result<String> {
+    let mut buf = Vec::new();
+    for session in sessions {
+        write_jsonl_records(&mut buf, session, project_path)?;
+    }
+    Ok(String::from_utf8(buf)?)
 }
 
 /// Fetch messages for a session from global storage
-fn fetch_session_messages(
+pub fn fetch_session_messages(
     conn: &Connection,
     composer_id: &str,
     options: &ExportOptions,
@@ -454,6 +1039,7 @@ fn fetch_session_messages(
                                     tool_call: None,
                                     model: None,
                                     tokens: None,
+                                    attachments: Vec::new(),
                                 });
                             }
                         }
@@ -471,15 +1057,28 @@ fn fetch_session_messages(
                             .unwrap_or("unknown")
                             .to_string();
 
-                        let params = tool_data
-                            .get("params")
-                            .and_then(|v| v.as_str())
-                            .map(|s| truncate_str(s, 500));
+                        let raw_params = tool_data.get("params").and_then(|v| v.as_str());
+                        let raw_result = tool_data.get("result").and_then(|v| v.as_str());
+
+                        // Decode before truncating, while the JSON is still intact
+                        let details = export_tools::decode_tool_call(
+                            &tool_name, raw_params, raw_result,
+                        )
+                        .map(|d| {
+                            d.truncated(
+                                options.tool_params_limit,
+                                options.tool_result_limit,
+                                truncate_field,
+                            )
+                        });
 
-                        let result = tool_data
-                            .get("result")
-                            .and_then(|v| v.as_str())
-                            .map(|s| truncate_str(s, 1000));
+                        let (params, params_truncation) = raw_params
+                            .map(|s| truncate_field(s, options.tool_params_limit))
+                            .unzip();
+
+                        let (result, result_truncation) = raw_result
+                            .map(|s| truncate_field(s, options.tool_result_limit))
+                            .unzip();
 
                         let status = tool_data
                             .get("status")
@@ -496,9 +1095,13 @@ fn fetch_session_messages(
                                 params,
                                 result,
                                 status,
+                                params_truncation: params_truncation.flatten(),
+                                result_truncation: result_truncation.flatten(),
+                                details,
                             }),
                             model: None,
                             tokens: None,
+                            attachments: Vec::new(),
                         });
 
                         continue; // Tool calls don't have regular text content
@@ -512,7 +1115,9 @@ fn fetch_session_messages(
                     .unwrap_or("")
                     .to_string();
 
-                if !text.is_empty() {
+                let attachments = export_attachments::extract_attachments(&bubble);
+
+                if !text.is_empty() || !attachments.is_empty() {
                     let role = match bubble_type {
                         1 => "user",
                         2 => "assistant",
@@ -552,6 +1157,7 @@ fn fetch_session_messages(
                         tool_call: None,
                         model,
                         tokens,
+                        attachments,
                     });
                 }
             }
@@ -569,14 +1175,35 @@ fn parse_iso_timestamp(s: &str) -> Option<i64> {
         .map(|dt| dt.timestamp())
 }
 
-/// Truncate string to max length (char-safe)
-fn truncate_str(s: &str, max_chars: usize) -> String {
+/// Truncate string to an optional max length (char-safe), reporting where it was cut
+fn truncate_field(s: &str, max_chars: Option<usize>) -> (String, Option<Truncation>) {
+    let Some(max_chars) = max_chars else {
+        return (s.to_string(), None);
+    };
+
     let char_count = s.chars().count();
     if char_count <= max_chars {
-        s.to_string()
+        (s.to_string(), None)
     } else {
         let truncated: String = s.chars().take(max_chars).collect();
-        format!("{}...[truncated]", truncated)
+        (
+            format!("{}...[truncated]", truncated),
+            Some(Truncation {
+                truncated_at: max_chars,
+                original_length: char_count,
+            }),
+        )
+    }
+}
+
+/// Parse a truncation limit: a character count, or `0`/`none`/`unlimited` for no limit
+pub fn parse_truncation_limit(s: &str) -> Result<Option<usize>> {
+    match s.trim().to_lowercase().as_str() {
+        "0" | "none" | "unlimited" => Ok(None),
+        other => other
+            .parse::<usize>()
+            .map(Some)
+            .with_context(|| format!("Invalid limit: {} (use a number or 'unlimited')", s)),
     }
 }
 
@@ -605,13 +1232,16 @@ fn format_message_as_markdown(msg: &ChatMessage, heading: &str) -> String {
                 }
                 md.push_str("\n\n");
 
-                if let Some(ref params) = tc.params {
+                if let Some(ref details) = tc.details {
+                    md.push_str(&details.to_markdown());
+                } else if let Some(ref params) = tc.params {
                     md.push_str("<details>\n<summary>Parameters</summary>\n\n```json\n");
                     md.push_str(params);
                     md.push_str("\n```\n\n</details>\n\n");
                 }
 
-                if let Some(ref result) = tc.result {
+                let show_result = tc.details.as_ref().is_none_or(|d| d.shows_result());
+                if let Some(result) = tc.result.as_ref().filter(|_| show_result) {
                     md.push_str("<details>\n<summary>Result</summary>\n\n```\n");
                     md.push_str(result);
                     md.push_str("\n```\n\n</details>\n\n");
@@ -641,8 +1271,13 @@ fn format_message_as_markdown(msg: &ChatMessage, heading: &str) -> String {
             }
 
             md.push_str("\n\n");
-            md.push_str(&msg.content);
-            md.push_str("\n\n");
+            if !msg.content.is_empty() {
+                md.push_str(&msg.content);
+                md.push_str("\n\n");
+            }
+            md.push_str(&export_attachments::attachments_to_markdown(
+                &msg.attachments,
+            ));
         }
     }
 
@@ -672,6 +1307,7 @@ fn format_as_markdown(export: &ChatExport) -> String {
         for msg in &session.messages {
             md.push_str(&format_message_as_markdown(msg, "###"));
         }
+        md.push_str(&format_children_as_markdown(&session.children, 3));
 
         md.push_str("---\n\n");
     }
@@ -679,22 +1315,36 @@ fn format_as_markdown(export: &ChatExport) -> String {
     md
 }
 
-/// Write sessions to separate files in a directory
+/// Write sessions to separate files in a directory, returning the file names
+///
+/// With `options.incremental`, files are named after the composer ID and a
+/// manifest in `output_dir` records when each was written, so only sessions
+/// that changed since the previous export are rewritten.
 fn write_split_output(
     sessions: &[ChatSession],
     output_dir: &str,
     format: ExportFormat,
     project_path: &str,
     exported_at: i64,
-) -> Result<()> {
+    options: &ExportOptions,
+) -> Result<Vec<String>> {
     // Create output directory
     fs::create_dir_all(output_dir)
         .with_context(|| format!("Failed to create directory: {}", output_dir))?;
 
-    let ext = match format {
-        ExportFormat::Markdown => "md",
-        ExportFormat::Json => "json",
-    };
+    if options.incremental {
+        return write_incremental_output(
+            sessions,
+            Path::new(output_dir),
+            format,
+            project_path,
+            exported_at,
+            options,
+        );
+    }
+
+    let ext = format.extension();
+    let mut files = Vec::with_capacity(sessions.len());
 
     for (i, session) in sessions.iter().enumerate() {
         let title = session.title.as_deref().unwrap_or("Untitled");
@@ -702,20 +1352,12 @@ fn write_split_output(
         let filename = format!("{:03}-{}.{}", i + 1, safe_title, ext);
         let file_path = Path::new(output_dir).join(&filename);
 
-        let content = match format {
-            ExportFormat::Markdown => format_single_session_as_markdown(session, i + 1),
-            ExportFormat::Json => {
-                let single_export = ChatExport {
-                    project_path: project_path.to_string(),
-                    exported_at,
-                    sessions: vec![session.clone()],
-                };
-                serde_json::to_string_pretty(&single_export)?
-            }
-        };
+        let content =
+            format_single_session(session, Some(i + 1), format, project_path, exported_at)?;
 
         fs::write(&file_path, &content)
             .with_context(|| format!("Failed to write: {}", file_path.display()))?;
+        files.push(filename);
     }
 
     println!(
@@ -724,7 +1366,170 @@ fn write_split_output(
         output_dir
     );
 
-    Ok(())
+    Ok(files)
+}
+
+/// Latest update time across a session and its nested children
+fn tree_updated_at_ms(session: &ChatSession) -> Option<i64> {
+    session
+        .children
+        .iter()
+        .map(tree_updated_at_ms)
+        .fold(session.updated_at_ms, Option::max)
+}
+
+/// Incremental variant of `write_split_output`
+fn write_incremental_output(
+    sessions: &[ChatSession],
+    output_dir: &Path,
+    format: ExportFormat,
+    project_path: &str,
+    exported_at: i64,
+    options: &ExportOptions,
+) -> Result<Vec<String>> {
+    let ext = format.extension();
+    let settings = options.content_settings();
+
+    let mut manifest = ExportManifest::read(output_dir)?;
+    let settings_changed = manifest.format != ext || manifest.settings != settings;
+
+    let mut files = Vec::with_capacity(sessions.len());
+    let mut seen = std::collections::HashSet::new();
+    let (mut written, mut unchanged, mut removed) = (0, 0, 0);
+
+    for session in sessions {
+        seen.insert(session.id.as_str());
+        let previous = manifest.sessions.get(&session.id).cloned();
+
+        // Keep the name a session was first exported under, even if its title changes
+        let filename = match previous.as_ref() {
+            Some(entry) => match entry.file.strip_suffix(&format!(".{}", manifest.format)) {
+                Some(stem) => format!("{}.{}", stem, ext),
+                None => entry.file.clone(),
+            },
+            None => stable_filename(session, ext),
+        };
+        let file_path = output_dir.join(&filename);
+        let tree_updated_at_ms = tree_updated_at_ms(session);
+
+        // Children are written into the parent's file, so their updates count too
+        let up_to_date = !settings_changed
+            && file_path.exists()
+            && previous.as_ref().is_some_and(|entry| {
+                entry.updated_at_ms == session.updated_at_ms
+                    && entry.tree_updated_at_ms == tree_updated_at_ms
+            });
+
+        if up_to_date {
+            unchanged += 1;
+        } else {
+            let content = format_single_session(session, None, format, project_path, exported_at)?;
+            fs::write(&file_path, &content)
+                .with_context(|| format!("Failed to write: {}", file_path.display()))?;
+            written += 1;
+
+            // A format change renames the file; drop the stale copy
+            if let Some(entry) = previous.filter(|entry| entry.file != filename) {
+                let _ = fs::remove_file(output_dir.join(&entry.file));
+            }
+        }
+
+        manifest.sessions.insert(
+            session.id.clone(),
+            ExportManifestEntry {
+                file: filename.clone(),
+                updated_at_ms: session.updated_at_ms,
+                tree_updated_at_ms,
+            },
+        );
+        files.push(filename);
+    }
+
+    if options.prune {
+        let stale: Vec<String> = manifest
+            .sessions
+            .keys()
+            .filter(|id| !seen.contains(id.as_str()))
+            .cloned()
+            .collect();
+        for id in stale {
+            if let Some(entry) = manifest.sessions.remove(&id) {
+                let file_path = output_dir.join(&entry.file);
+                if file_path.exists() {
+                    fs::remove_file(&file_path)
+                        .with_context(|| format!("Failed to remove: {}", file_path.display()))?;
+                }
+                removed += 1;
+            }
+        }
+    }
+
+    manifest.format = ext.to_string();
+    manifest.settings = settings;
+    manifest.write(output_dir)?;
+
+    println!(
+        "Exported {} sessions to directory: {} ({} written, {} unchanged, {} removed)",
+        sessions.len(),
+        output_dir.display(),
+        written,
+        unchanged,
+        removed
+    );
+
+    Ok(files)
+}
+
+/// File name for a session in incremental mode: `<composer-id>-<title>.<ext>`
+///
+/// The full composer ID keeps names unique; the title is only for readers.
+fn stable_filename(session: &ChatSession, ext: &str) -> String {
+    let title = session.title.as_deref().unwrap_or("Untitled");
+    format!(
+        "{}-{}.{}",
+        sanitize_id(&session.id),
+        sanitize_filename(title),
+        ext
+    )
+}
+
+/// Make a composer ID safe for a file name without shortening it
+fn sanitize_id(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Render a single session in the given format (for split output)
+///
+/// `index` numbers the session's heading; incremental output passes None so a
+/// file only changes when its own session does.
+fn format_single_session(
+    session: &ChatSession,
+    index: Option<usize>,
+    format: ExportFormat,
+    project_path: &str,
+    exported_at: i64,
+) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Markdown => format_single_session_as_markdown(session, index),
+        ExportFormat::Json => {
+            let single_export = ChatExport {
+                project_path: project_path.to_string(),
+                exported_at,
+                sessions: vec![session.clone()],
+            };
+            serde_json::to_string_pretty(&single_export)?
+        }
+        ExportFormat::Jsonl => format_as_jsonl(std::slice::from_ref(session), project_path)?,
+        ExportFormat::Html => export_html::format_single_session_as_html(session, index),
+    })
 }
 
 /// Sanitize a string for use as a filename
@@ -742,11 +1547,14 @@ fn sanitize_filename(s: &str) -> String {
 }
 
 /// Format a single session as markdown (for split output)
-fn format_single_session_as_markdown(session: &ChatSession, index: usize) -> String {
+fn format_single_session_as_markdown(session: &ChatSession, index: Option<usize>) -> String {
     let mut md = String::new();
 
     let title = session.title.as_deref().unwrap_or("Untitled Session");
-    md.push_str(&format!("# Session {}: {}\n\n", index, title));
+    match index {
+        Some(index) => md.push_str(&format!("# Session {}: {}\n\n", index, title)),
+        None => md.push_str(&format!("# {}\n\n", title)),
+    }
 
     if let Some(created) = session.created_at {
         md.push_str(&format!("_Created: {}_\n\n", format_timestamp(created)));
@@ -757,12 +1565,42 @@ fn format_single_session_as_markdown(session: &ChatSession, index: usize) -> Str
     for msg in &session.messages {
         md.push_str(&format_message_as_markdown(msg, "##"));
     }
+    md.push_str(&format_children_as_markdown(&session.children, 2));
+
+    md
+}
+
+/// Format child sessions as nested markdown sections
+///
+/// `level` is the heading level of each child; its messages sit one level
+/// deeper. Markdown stops at six levels, so deeper trees are flattened there.
+fn format_children_as_markdown(children: &[ChatSession], level: usize) -> String {
+    let mut md = String::new();
+    let heading = "#".repeat(level.min(6));
+    let message_heading = "#".repeat((level + 1).min(6));
+
+    for child in children {
+        let title = child.title.as_deref().unwrap_or("Untitled Session");
+        md.push_str(&format!("{} ↳ Sub-agent: {}\n\n", heading, title));
+
+        let mut meta = format!("_Session: `{}`", child.id);
+        if let Some(created) = child.created_at {
+            meta.push_str(&format!(" · Created: {}", format_timestamp(created)));
+        }
+        md.push_str(&meta);
+        md.push_str("_\n\n");
+
+        for msg in &child.messages {
+            md.push_str(&format_message_as_markdown(msg, &message_heading));
+        }
+        md.push_str(&format_children_as_markdown(&child.children, level + 1));
+    }
 
     md
 }
 
 /// Format unix timestamp as human-readable string
-fn format_timestamp(ts: i64) -> String {
+pub(crate) fn format_timestamp(ts: i64) -> String {
     chrono::DateTime::from_timestamp(ts, 0)
         .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
         .unwrap_or_else(|| ts.to_string())
@@ -780,6 +1618,8 @@ mod tests {
             Some(ExportFormat::Markdown)
         );
         assert_eq!(ExportFormat::from_str("json"), Some(ExportFormat::Json));
+        assert_eq!(ExportFormat::from_str("jsonl"), Some(ExportFormat::Jsonl));
+        assert_eq!(ExportFormat::from_str("html"), Some(ExportFormat::Html));
         assert_eq!(ExportFormat::from_str("xml"), None);
     }
 
@@ -794,29 +1634,29 @@ mod tests {
     }
 
     #[test]
-    fn test_truncate_str_short() {
+    fn test_truncate_field_short() {
         // String shorter than limit should be unchanged
-        assert_eq!(truncate_str("hello", 10), "hello");
+        assert_eq!(truncate_field("hello", Some(10)).0, "hello");
     }
 
     #[test]
-    fn test_truncate_str_exact() {
+    fn test_truncate_field_exact() {
         // String exactly at limit should be unchanged
-        assert_eq!(truncate_str("hello", 5), "hello");
+        assert_eq!(truncate_field("hello", Some(5)).0, "hello");
     }
 
     #[test]
-    fn test_truncate_str_long() {
+    fn test_truncate_field_long() {
         // String longer than limit should be truncated
-        let result = truncate_str("hello world", 5);
+        let result = truncate_field("hello world", Some(5)).0;
         assert!(result.starts_with("hello"));
         assert!(result.ends_with("...[truncated]"));
     }
 
     #[test]
-    fn test_truncate_str_unicode() {
+    fn test_truncate_field_unicode() {
         // Unicode characters should be handled correctly (char-safe)
-        let result = truncate_str("你好世界", 2);
+        let result = truncate_field("你好世界", Some(2)).0;
         assert!(result.starts_with("你好"));
         assert!(result.ends_with("...[truncated]"));
     }
@@ -870,6 +1710,7 @@ mod tests {
             tool_call: None,
             model: None,
             tokens: None,
+            attachments: Vec::new(),
         };
         let result = format_message_as_markdown(&msg, "##");
         assert!(result.contains("## **User**"));
@@ -889,6 +1730,7 @@ mod tests {
                 input: 100,
                 output: 50,
             }),
+            attachments: Vec::new(),
         };
         let result = format_message_as_markdown(&msg, "###");
         assert!(result.contains("### **Assistant**"));
@@ -907,6 +1749,7 @@ mod tests {
             tool_call: None,
             model: None,
             tokens: None,
+            attachments: Vec::new(),
         };
         let result = format_message_as_markdown(&msg, "##");
         assert!(result.contains("## 💭 **Thinking**"));
@@ -927,9 +1770,13 @@ mod tests {
                 params: Some(r#"{"path": "/test.rs"}"#.to_string()),
                 result: Some("file contents...".to_string()),
                 status: Some("completed".to_string()),
+                params_truncation: None,
+                result_truncation: None,
+                details: None,
             }),
             model: None,
             tokens: None,
+            attachments: Vec::new(),
         };
         let result = format_message_as_markdown(&msg, "###");
         assert!(result.contains("### 🔧 **Tool: read_file**"));
@@ -937,4 +1784,436 @@ mod tests {
         assert!(result.contains("Parameters"));
         assert!(result.contains("Result"));
     }
+
+    #[test]
+    fn test_write_jsonl_records() {
+        let session = ChatSession {
+            id: "s1".to_string(),
+            title: Some("Fix bug".to_string()),
+            messages: vec![
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: "line one\nline two".to_string(),
+                    timestamp: Some(1704067200),
+                    thinking_duration_ms: None,
+                    tool_call: None,
+                    model: None,
+                    tokens: None,
+                    attachments: Vec::new(),
+                },
+                ChatMessage {
+                    role: "assistant".to_string(),
+                    content: "Done.".to_string(),
+                    timestamp: None,
+                    thinking_duration_ms: None,
+                    tool_call: None,
+                    model: Some("gpt-4".to_string()),
+                    tokens: Some(TokenCount {
+                        input: 100,
+                        output: 50,
+                    }),
+                    attachments: Vec::new(),
+                },
+            ],
+            created_at: None,
+            updated_at: None,
+            updated_at_ms: None,
+            children: vec![],
+        };
+
+        let mut buf = Vec::new();
+        let count = write_jsonl_records(&mut buf, &session, "/tmp/project").unwrap();
+        assert_eq!(count, 2);
+
+        let output = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["session_id"], "s1");
+        assert_eq!(first["session_title"], "Fix bug");
+        assert_eq!(first["project_path"], "/tmp/project");
+        assert_eq!(first["role"], "user");
+        assert_eq!(first["content"], "line one\nline two");
+        assert_eq!(first["timestamp"], 1704067200);
+
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["model"], "gpt-4");
+        assert_eq!(second["tokens"]["input"], 100);
+        assert!(second.get("timestamp").is_none());
+    }
+
+    #[test]
+    fn test_project_slug() {
+        assert_eq!(
+            project_slug("/home/user/my project"),
+            "home-user-my-project"
+        );
+        assert_eq!(project_slug("ssh:box:/srv/app"), "ssh-box-srv-app");
+        assert_eq!(project_slug(r"C:\Users\dev\app.rs"), "C-Users-dev-app.rs");
+        assert_eq!(project_slug("///"), "project");
+    }
+
+    #[test]
+    fn test_project_slugs_do_not_depend_on_order() {
+        let project = |folder_id: &str, path: &str| list::Project {
+            folder_id: folder_id.to_string(),
+            path: PathBuf::from(path),
+            remote: None,
+            last_modified: None,
+            chat_count: None,
+            folders: Vec::new(),
+        };
+        let mut projects = vec![
+            project("aaaaaaaa1111", "/home/user/app"),
+            project("bbbbbbbb2222", "/home/user/app"),
+            project("cccccccc3333", "/home/user/web"),
+        ];
+        assert_eq!(
+            project_slugs(&projects),
+            vec![
+                "home-user-app-aaaaaaaa",
+                "home-user-app-bbbbbbbb",
+                "home-user-web"
+            ]
+        );
+
+        projects.reverse();
+        assert_eq!(
+            project_slugs(&projects),
+            vec![
+                "home-user-web",
+                "home-user-app-bbbbbbbb",
+                "home-user-app-aaaaaaaa"
+            ]
+        );
+
+        // Remote projects are always suffixed, even without a local twin
+        let mut remote = project("dddddddd4444", "/srv/app");
+        remote.remote = Some(list::RemoteInfo {
+            remote_type: list::RemoteType::SshRemote,
+            name: "box".to_string(),
+        });
+        let slugs = project_slugs(&[remote]);
+        assert!(slugs[0].ends_with("-dddddddd"), "{}", slugs[0]);
+    }
+
+    #[test]
+    fn test_format_index_as_markdown() {
+        let index = ExportIndex {
+            exported_at: 1704067200,
+            projects: vec![ExportIndexProject {
+                project_path: "/home/user/app".to_string(),
+                workspace_id: "abc123".to_string(),
+                directory: "home-user-app".to_string(),
+                sessions: vec![ExportIndexSession {
+                    id: "s1".to_string(),
+                    title: Some("Fix bug".to_string()),
+                    file: "001-Fix bug.md".to_string(),
+                }],
+            }],
+        };
+
+        let md = format_index_as_markdown(&index);
+        assert!(md.contains("## /home/user/app"));
+        assert!(md.contains("- [Fix bug](<home-user-app/001-Fix bug.md>)"));
+    }
+
+    fn split_session(id: &str, title: &str, updated_at_ms: i64) -> ChatSession {
+        ChatSession {
+            id: id.to_string(),
+            title: Some(title.to_string()),
+            messages: vec![],
+            created_at: None,
+            updated_at: Some(updated_at_ms / 1000),
+            updated_at_ms: Some(updated_at_ms),
+            children: vec![],
+        }
+    }
+
+    #[test]
+    fn test_child_sessions_are_nested_under_parent() {
+        let say = |content: &str| ChatMessage {
+            role: "assistant".to_string(),
+            content: content.to_string(),
+            timestamp: None,
+            thinking_duration_ms: None,
+            tool_call: None,
+            model: None,
+            tokens: None,
+            attachments: Vec::new(),
+        };
+
+        let mut grandchild = split_session("g1", "Grep logs", 1000);
+        grandchild.messages.push(say("no matches"));
+        let mut child = split_session("c1", "Explore schema", 1000);
+        child.messages.push(say("found 3 tables"));
+        child.children.push(grandchild);
+        let mut parent = split_session("p1", "Fix migration", 1000);
+        parent.messages.push(say("delegating"));
+        parent.children.push(child);
+
+        let md = format_single_session_as_markdown(&parent, Some(1));
+        assert!(md.contains("## ↳ Sub-agent: Explore schema\n\n_Session: `c1`_"));
+        assert!(md.contains("### **Assistant**\n\nfound 3 tables"));
+        assert!(md.contains("### ↳ Sub-agent: Grep logs"));
+        assert!(md.contains("#### **Assistant**\n\nno matches"));
+        assert!(md.find("delegating").unwrap() < md.find("found 3 tables").unwrap());
+
+        let json = serde_json::to_value(&parent).unwrap();
+        assert_eq!(json["children"][0]["id"], "c1");
+        assert_eq!(json["children"][0]["children"][0]["id"], "g1");
+        assert!(json["children"][0]["children"][0].get("children").is_none());
+
+        let mut buf = Vec::new();
+        assert_eq!(write_jsonl_records(&mut buf, &parent, "/p").unwrap(), 3);
+        let records: Vec<serde_json::Value> = String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(records[0].get("parent_session_id").is_none());
+        assert_eq!(records[1]["session_id"], "c1");
+        assert_eq!(records[1]["parent_session_id"], "p1");
+        assert_eq!(records[2]["parent_session_id"], "c1");
+    }
+
+    #[test]
+    fn test_incremental_split_output_rewrites_only_changed_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().to_str().unwrap();
+        let options = ExportOptions {
+            incremental: true,
+            ..ExportOptions::default()
+        };
+        let write = |sessions: &[ChatSession], options: &ExportOptions| {
+            write_split_output(sessions, output, ExportFormat::Markdown, "/p", 0, options).unwrap()
+        };
+
+        let files = write(
+            &[
+                split_session("aaaaaaaa-1111", "First", 1000),
+                split_session("bbbbbbbb-2222", "Second", 2000),
+            ],
+            &options,
+        );
+        assert_eq!(
+            files,
+            vec!["aaaaaaaa-1111-First.md", "bbbbbbbb-2222-Second.md"]
+        );
+        assert!(dir.path().join(EXPORT_MANIFEST_FILE).exists());
+
+        // Tamper with the unchanged file to detect whether it gets rewritten
+        fs::write(dir.path().join("aaaaaaaa-1111-First.md"), "untouched").unwrap();
+
+        // "Second" was updated and renamed; a new session appears in front
+        let files = write(
+            &[
+                split_session("cccccccc-3333", "Third", 3000),
+                split_session("aaaaaaaa-1111", "First", 1000),
+                split_session("bbbbbbbb-2222", "Renamed", 2500),
+            ],
+            &options,
+        );
+        assert_eq!(
+            files,
+            vec![
+                "cccccccc-3333-Third.md",
+                "aaaaaaaa-1111-First.md",
+                "bbbbbbbb-2222-Second.md"
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("aaaaaaaa-1111-First.md")).unwrap(),
+            "untouched"
+        );
+        // Headings carry no list position, which would shift with every new session
+        assert!(
+            fs::read_to_string(dir.path().join("bbbbbbbb-2222-Second.md"))
+                .unwrap()
+                .starts_with("# Renamed\n")
+        );
+
+        // Without --prune, deleted sessions keep their files
+        write(&[split_session("cccccccc-3333", "Third", 3000)], &options);
+        assert!(dir.path().join("aaaaaaaa-1111-First.md").exists());
+
+        let prune = ExportOptions {
+            prune: true,
+            ..options.clone()
+        };
+        write(&[split_session("cccccccc-3333", "Third", 3000)], &prune);
+        assert!(!dir.path().join("aaaaaaaa-1111-First.md").exists());
+        assert!(!dir.path().join("bbbbbbbb-2222-Second.md").exists());
+        assert!(dir.path().join("cccccccc-3333-Third.md").exists());
+    }
+
+    #[test]
+    fn test_incremental_split_output_rewrites_on_child_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().to_str().unwrap();
+        let options = ExportOptions {
+            incremental: true,
+            include_children: true,
+            ..ExportOptions::default()
+        };
+        let parent_with_child = |child_updated_at_ms: i64| {
+            let mut child = split_session("c1", "Explore", 500);
+            child
+                .children
+                .push(split_session("g1", "Grep", child_updated_at_ms));
+            let mut parent = split_session("aaaaaaaa-1111", "First", 1000);
+            parent.children.push(child);
+            parent
+        };
+        let path = dir.path().join("aaaaaaaa-1111-First.md");
+
+        let sessions = [parent_with_child(600)];
+        write_split_output(&sessions, output, ExportFormat::Markdown, "/p", 0, &options).unwrap();
+        fs::write(&path, "untouched").unwrap();
+
+        write_split_output(&sessions, output, ExportFormat::Markdown, "/p", 0, &options).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "untouched");
+
+        // Only the grandchild changed; the parent's own update time did not
+        let sessions = [parent_with_child(2000)];
+        write_split_output(&sessions, output, ExportFormat::Markdown, "/p", 0, &options).unwrap();
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("Sub-agent: Grep"));
+    }
+
+    #[test]
+    fn test_incremental_split_output_rewrites_on_format_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().to_str().unwrap();
+        let options = ExportOptions {
+            incremental: true,
+            ..ExportOptions::default()
+        };
+        let sessions = [split_session("aaaaaaaa-1111", "First", 1000)];
+
+        write_split_output(&sessions, output, ExportFormat::Markdown, "/p", 0, &options).unwrap();
+        let files =
+            write_split_output(&sessions, output, ExportFormat::Json, "/p", 0, &options).unwrap();
+
+        assert_eq!(files, vec!["aaaaaaaa-1111-First.json"]);
+        assert!(dir.path().join("aaaaaaaa-1111-First.json").exists());
+        assert!(!dir.path().join("aaaaaaaa-1111-First.md").exists());
+    }
+
+    #[test]
+    fn test_truncate_field_records_original_length() {
+        assert_eq!(
+            truncate_field("hello", Some(10)),
+            ("hello".to_string(), None)
+        );
+        assert_eq!(truncate_field("hello", None), ("hello".to_string(), None));
+
+        let (text, truncation) = truncate_field("你好世界", Some(2));
+        assert_eq!(text, "你好...[truncated]");
+        assert_eq!(
+            truncation,
+            Some(Truncation {
+                truncated_at: 2,
+                original_length: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_truncation_limit() {
+        assert_eq!(parse_truncation_limit("2000").unwrap(), Some(2000));
+        assert_eq!(parse_truncation_limit("unlimited").unwrap(), None);
+        assert_eq!(parse_truncation_limit("0").unwrap(), None);
+        assert!(parse_truncation_limit("lots").is_err());
+    }
+
+    #[test]
+    fn test_fetch_session_messages_tool_limits() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE cursorDiskKV (key TEXT PRIMARY KEY, value TEXT)",
+            [],
+        )
+        .unwrap();
+        let composer = serde_json::json!({
+            "fullConversationHeadersOnly": [{"bubbleId": "b1", "type": 2}]
+        });
+        let bubble = serde_json::json!({
+            "toolFormerData": {
+                "name": "run_terminal_cmd",
+                "params": "p".repeat(600),
+                "result": "r".repeat(50),
+            }
+        });
+        conn.execute(
+            "INSERT INTO cursorDiskKV (key, value) VALUES (?1, ?2), (?3, ?4)",
+            rusqlite::params![
+                "composerData:c1",
+                composer.to_string(),
+                "bubbleId:c1:b1",
+                bubble.to_string()
+            ],
+        )
+        .unwrap();
+
+        let options = ExportOptions {
+            with_tools: true,
+            ..ExportOptions::default()
+        };
+        let messages = fetch_session_messages(&conn, "c1", &options).unwrap();
+        let tool = messages[0].tool_call.as_ref().unwrap();
+        assert_eq!(
+            tool.params_truncation,
+            Some(Truncation {
+                truncated_at: DEFAULT_TOOL_PARAMS_LIMIT,
+                original_length: 600,
+            })
+        );
+        assert!(tool.result_truncation.is_none());
+
+        let json = serde_json::to_value(tool).unwrap();
+        assert_eq!(json["params_truncation"]["original_length"], 600);
+        assert!(json.get("result_truncation").is_none());
+
+        let unlimited = ExportOptions {
+            tool_params_limit: None,
+            ..options
+        };
+        let messages = fetch_session_messages(&conn, "c1", &unlimited).unwrap();
+        let tool = messages[0].tool_call.as_ref().unwrap();
+        assert_eq!(tool.params.as_deref().map(str::len), Some(600));
+        assert!(tool.params_truncation.is_none());
+    }
+
+    #[test]
+    fn test_format_message_tool_with_details() {
+        let msg = ChatMessage {
+            role: "tool".to_string(),
+            content: "[run_terminal_cmd]".to_string(),
+            timestamp: None,
+            thinking_duration_ms: None,
+            tool_call: Some(ToolCall {
+                name: "run_terminal_cmd".to_string(),
+                params: Some(r#"{"command":"ls"}"#.to_string()),
+                result: Some(r#"{"output":"a.txt","exitCode":0}"#.to_string()),
+                status: Some("completed".to_string()),
+                params_truncation: None,
+                result_truncation: None,
+                details: export_tools::decode_tool_call(
+                    "run_terminal_cmd",
+                    Some(r#"{"command":"ls"}"#),
+                    Some(r#"{"output":"a.txt","exitCode":0}"#),
+                ),
+            }),
+            model: None,
+            tokens: None,
+            attachments: Vec::new(),
+        };
+        let result = format_message_as_markdown(&msg, "###");
+        assert!(result.contains("```shell\n$ ls\n```"));
+        assert!(!result.contains("<summary>Parameters</summary>"));
+        assert!(!result.contains("<summary>Result</summary>"));
+    }
 }
@@ -0,0 +1,955 @@
+//! HTML rendering for chat exports
+//!
+//! Produces a single self-contained file: inline CSS, no scripts and no
+//! external assets, so transcripts can be shared and opened in any browser.
+
+use super::export_attachments::{self, Attachment};
+use super::export_chat::{format_timestamp, ChatExport, ChatMessage, ChatSession};
+use super::export_tools::{self, ToolDetails};
+
+const STYLE: &str = r#"
+:root { --bg: #ffffff; --fg: #1f2328; --muted: #656d76; --border: #d0d7de; --panel: #f6f8fa;
+  --user: #ddf4ff; --assistant: #ffffff; --accent: #0969da;
+  --kw: #cf222e; --str: #0a3069; --com: #6e7781; --num: #0550ae; }
+@media (prefers-color-scheme: dark) {
+  :root { --bg: #0d1117; --fg: #e6edf3; --muted: #8d96a0; --border: #30363d; --panel: #161b22;
+    --user: #12263a; --assistant: #0d1117; --accent: #4493f8;
+    --kw: #ff7b72; --str: #a5d6ff; --com: #8b949e; --num: #79c0ff; }
+}
+* { box-sizing: border-box; }
+body { margin: 0 auto; max-width: 960px; padding: 2rem 1rem; background: var(--bg); color: var(--fg);
+  font: 15px/1.6 -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif; }
+a { color: var(--accent); text-decoration: none; }
+a:hover { text-decoration: underline; }
+h1 { font-size: 1.6rem; margin: 0 0 .25rem; }
+h2 { font-size: 1.3rem; margin: 0 0 .25rem; }
+.meta { color: var(--muted); font-size: .85rem; margin: 0 0 1rem; }
+nav.toc { background: var(--panel); border: 1px solid var(--border); border-radius: 6px; padding: .75rem 1.25rem; margin-bottom: 2rem; }
+nav.toc ol { margin: .5rem 0 0; padding-left: 1.5rem; }
+nav.toc .meta { display: inline; margin-left: .5rem; }
+section.session { border-top: 1px solid var(--border); padding-top: 1.5rem; margin-bottom: 2.5rem; }
+.message { border: 1px solid var(--border); border-radius: 6px; margin: .75rem 0; padding: .5rem 1rem; }
+.message.user { background: var(--user); }
+.message.assistant { background: var(--assistant); }
+.message.thinking, .message.tool { background: var(--panel); }
+.role { font-weight: 600; font-size: .9rem; }
+.role time { font-weight: normal; color: var(--muted); margin-left: .5rem; }
+.badge { display: inline-block; font-weight: normal; font-size: .75rem; border: 1px solid var(--border);
+  border-radius: 999px; padding: 0 .5rem; margin-left: .4rem; color: var(--muted); }
+summary { cursor: pointer; font-weight: 600; font-size: .9rem; }
+details details { margin: .4rem 0; }
+details details summary { font-weight: normal; color: var(--muted); }
+.text { white-space: pre-wrap; overflow-wrap: anywhere; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: .85em;
+  background: var(--panel); border-radius: 4px; padding: .1em .3em; }
+pre { background: var(--panel); border: 1px solid var(--border); border-radius: 6px; padding: .75rem; overflow-x: auto; }
+pre code { background: none; padding: 0; white-space: pre; }
+.tok-kw { color: var(--kw); }
+.tok-str { color: var(--str); }
+.tok-com { color: var(--com); font-style: italic; }
+.tok-num { color: var(--num); }
+.diff-add { color: #1a7f37; }
+.diff-del { color: #cf222e; }
+.diff-hunk { color: var(--muted); }
+ul.attachments { margin: .25rem 0 .5rem; padding-left: 1.25rem; font-size: .9rem; }
+details.child-session { border-left: 3px solid var(--border); margin: 1rem 0; padding-left: 1rem; }
+details.child-session > summary .meta { display: inline; margin-left: .5rem; }
+"#;
+
+const KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "def",
+    "default",
+    "defer",
+    "del",
+    "do",
+    "elif",
+    "else",
+    "enum",
+    "except",
+    "export",
+    "extends",
+    "false",
+    "False",
+    "finally",
+    "fn",
+    "for",
+    "from",
+    "func",
+    "function",
+    "go",
+    "if",
+    "impl",
+    "import",
+    "in",
+    "interface",
+    "let",
+    "loop",
+    "match",
+    "mod",
+    "mut",
+    "new",
+    "nil",
+    "None",
+    "null",
+    "package",
+    "pass",
+    "pub",
+    "raise",
+    "return",
+    "self",
+    "Self",
+    "static",
+    "struct",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "trait",
+    "true",
+    "True",
+    "try",
+    "type",
+    "typeof",
+    "use",
+    "var",
+    "where",
+    "while",
+    "with",
+    "yield",
+    "fi",
+    "then",
+    "done",
+    "esac",
+];
+
+/// Format a full export as a standalone HTML document
+pub fn format_as_html(export: &ChatExport) -> String {
+    let mut body = String::new();
+
+    body.push_str(&format!(
+        "<header>\n<h1>Chat Export</h1>\n<p class=\"meta\">{} · Exported {}</p>\n</header>\n",
+        escape_html(&export.project_path),
+        escape_html(&format_timestamp(export.exported_at))
+    ));
+
+    body.push_str("<nav class=\"toc\">\n<strong>Sessions</strong>\n<ol>\n");
+    for (i, session) in export.sessions.iter().enumerate() {
+        body.push_str(&format!(
+            "<li><a href=\"#session-{}\">{}</a>{}</li>\n",
+            i + 1,
+            escape_html(session_title(session)),
+            toc_meta(session)
+        ));
+    }
+    body.push_str("</ol>\n</nav>\n<main>\n");
+
+    for (i, session) in export.sessions.iter().enumerate() {
+        body.push_str(&format_session(session, Some(i + 1), "h2"));
+    }
+    body.push_str("</main>\n");
+
+    wrap_document(&format!("Chat Export: {}", export.project_path), &body)
+}
+
+/// Format a single session as a standalone HTML document (for split output)
+pub fn format_single_session_as_html(session: &ChatSession, index: Option<usize>) -> String {
+    let body = format!("<main>\n{}</main>\n", format_session(session, index, "h1"));
+    wrap_document(session_title(session), &body)
+}
+
+fn wrap_document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        STYLE,
+        body
+    )
+}
+
+fn session_title(session: &ChatSession) -> &str {
+    session.title.as_deref().unwrap_or("Untitled Session")
+}
+
+fn toc_meta(session: &ChatSession) -> String {
+    let mut parts = Vec::new();
+    if let Some(created) = session.created_at {
+        parts.push(format_timestamp(created));
+    }
+    parts.push(format!("{} messages", session.messages.len()));
+    format!(
+        " <span class=\"meta\">{}</span>",
+        escape_html(&parts.join(" · "))
+    )
+}
+
+fn format_session(session: &ChatSession, index: Option<usize>, heading: &str) -> String {
+    let mut html = String::new();
+
+    let title = escape_html(session_title(session));
+    html.push_str(&match index {
+        Some(index) => format!(
+            "<section class=\"session\" id=\"session-{}\">\n<{heading}>Session {}: {}</{heading}>\n",
+            index, index, title
+        ),
+        None => format!("<section class=\"session\">\n<{heading}>{}</{heading}>\n", title),
+    });
+
+    if let Some(created) = session.created_at {
+        html.push_str(&format!(
+            "<p class=\"meta\">Created: {}</p>\n",
+            escape_html(&format_timestamp(created))
+        ));
+    }
+
+    for msg in &session.messages {
+        html.push_str(&format_message(msg));
+    }
+    html.push_str(&format_children(&session.children));
+
+    html.push_str("</section>\n");
+    html
+}
+
+/// Format child sessions as nested collapsible blocks
+fn format_children(children: &[ChatSession]) -> String {
+    let mut html = String::new();
+
+    for child in children {
+        html.push_str(&format!(
+            "<details class=\"child-session\" open>\n<summary>↳ Sub-agent: {}{}</summary>\n",
+            escape_html(session_title(child)),
+            toc_meta(child)
+        ));
+        for msg in &child.messages {
+            html.push_str(&format_message(msg));
+        }
+        html.push_str(&format_children(&child.children));
+        html.push_str("</details>\n");
+    }
+
+    html
+}
+
+/// Format a single message as HTML
+fn format_message(msg: &ChatMessage) -> String {
+    let time = msg
+        .timestamp
+        .map(|ts| format!("<time>{}</time>", escape_html(&format_timestamp(ts))))
+        .unwrap_or_default();
+
+    match msg.role.as_str() {
+        "thinking" => {
+            let duration = msg
+                .thinking_duration_ms
+                .map(|ms| badge(&format!("{:.1}s", ms as f64 / 1000.0)))
+                .unwrap_or_default();
+            format!(
+                "<details class=\"message thinking\">\n<summary>💭 Thinking{}</summary>\n{}</details>\n",
+                duration,
+                render_text(&msg.content)
+            )
+        }
+        "tool" => {
+            let Some(ref tc) = msg.tool_call else {
+                return String::new();
+            };
+
+            let mut html = format!(
+                "<div class=\"message tool\">\n<div class=\"role\">🔧 Tool: {}{}{}</div>\n",
+                escape_html(&tc.name),
+                tc.status.as_deref().map(badge).unwrap_or_default(),
+                time
+            );
+
+            if let Some(ref details) = tc.details {
+                html.push_str(&render_tool_details(details));
+            } else if let Some(ref params) = tc.params {
+                html.push_str(&format!(
+                    "<details>\n<summary>Parameters</summary>\n{}</details>\n",
+                    render_payload(params)
+                ));
+            }
+
+            let show_result = tc.details.as_ref().is_none_or(|d| d.shows_result());
+            if let Some(result) = tc.result.as_ref().filter(|_| show_result) {
+                html.push_str(&format!(
+                    "<details>\n<summary>Result</summary>\n{}</details>\n",
+                    render_payload(result)
+                ));
+            }
+
+            html.push_str("</div>\n");
+            html
+        }
+        role => {
+            let role_display = match role {
+                "user" => "User",
+                "assistant" => "Assistant",
+                "system" => "System",
+                other => other,
+            };
+
+            let mut badges = String::new();
+            if let Some(ref model) = msg.model {
+                badges.push_str(&badge(model));
+            }
+            if let Some(ref tokens) = msg.tokens {
+                if tokens.input > 0 || tokens.output > 0 {
+                    badges.push_str(&badge(&format!("{}↓ {}↑", tokens.input, tokens.output)));
+                }
+            }
+
+            format!(
+                "<article class=\"message {}\">\n<div class=\"role\">{}{}{}</div>\n{}{}</article>\n",
+                escape_html(role),
+                escape_html(role_display),
+                badges,
+                time,
+                render_text(&msg.content),
+                render_attachments(&msg.attachments)
+            )
+        }
+    }
+}
+
+/// Render a message's attachments as a list, with selected code in collapsible blocks
+fn render_attachments(attachments: &[Attachment]) -> String {
+    if attachments.is_empty() {
+        return String::new();
+    }
+
+    let mut html = String::from("<ul class=\"attachments\">\n");
+    for attachment in attachments {
+        let item = match attachment {
+            Attachment::File { path } => format!("📄 <code>{}</code>", escape_html(path)),
+            Attachment::Folder { path } => format!("📁 <code>{}</code>", escape_html(path)),
+            Attachment::Selection {
+                path,
+                start_line,
+                end_line,
+                text,
+            } => format!(
+                "<details>\n<summary>✂️ <code>{}</code>{}</summary>\n{}</details>",
+                escape_html(path.as_deref().unwrap_or("selection")),
+                export_tools::line_range(*start_line, *end_line),
+                render_code_block(text, export_attachments::language_for_path(path.as_deref()))
+            ),
+            Attachment::CodeBlock {
+                path,
+                language,
+                text,
+            } => format!(
+                "<details>\n<summary>🧩 Code block{}</summary>\n{}</details>",
+                path.as_deref()
+                    .map(|path| format!(" for <code>{}</code>", escape_html(path)))
+                    .unwrap_or_default(),
+                render_code_block(
+                    text,
+                    export_attachments::code_block_language(language, path)
+                )
+            ),
+            Attachment::Terminal { text } => format!(
+                "<details>\n<summary>💻 Terminal output</summary>\n{}</details>",
+                render_code_block(text, "")
+            ),
+            Attachment::Doc { name, url } => match url {
+                Some(url) if export_attachments::is_web_url(url) => format!(
+                    "📚 <a href=\"{}\">{}</a>",
+                    escape_html(url),
+                    escape_html(name)
+                ),
+                Some(url) => format!("📚 {} ({})", escape_html(name), escape_html(url)),
+                None => format!("📚 {}", escape_html(name)),
+            },
+            Attachment::Image {
+                path,
+                width,
+                height,
+            } => {
+                let mut item = match path {
+                    Some(path) => format!("🖼️ <code>{}</code>", escape_html(path)),
+                    None => "🖼️ image".to_string(),
+                };
+                if let (Some(w), Some(h)) = (width, height) {
+                    item.push_str(&format!(" ({}×{})", w, h));
+                }
+                item
+            }
+        };
+        html.push_str(&format!("<li>{}</li>\n", item));
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+/// Render the decoded view of a well-known tool call
+fn render_tool_details(details: &ToolDetails) -> String {
+    match details {
+        ToolDetails::ReadFile {
+            path,
+            start_line,
+            end_line,
+        } => {
+            let range = export_tools::line_range(*start_line, *end_line);
+            format!(
+                "<div class=\"text\"><code>{}</code>{}</div>\n",
+                escape_html(path),
+                range
+            )
+        }
+        ToolDetails::EditFile { path, diff, .. } => format!(
+            "<div class=\"text\"><code>{}</code></div>\n{}",
+            escape_html(path),
+            render_code_block(diff, "diff")
+        ),
+        ToolDetails::Terminal {
+            command,
+            cwd,
+            exit_code,
+            output,
+            ..
+        } => {
+            let mut html = String::new();
+            if let Some(cwd) = cwd {
+                html.push_str(&format!(
+                    "<div class=\"text\">in <code>{}</code></div>\n",
+                    escape_html(cwd)
+                ));
+            }
+            html.push_str(&render_code_block(&format!("$ {}", command), "shell"));
+            if let Some(output) = output.as_deref().filter(|o| !o.is_empty()) {
+                html.push_str(&format!(
+                    "<details>\n<summary>Output</summary>\n{}</details>\n",
+                    render_code_block(output, "")
+                ));
+            }
+            if let Some(code) = exit_code {
+                html.push_str(&format!("<div class=\"meta\">Exit code: {}</div>\n", code));
+            }
+            html
+        }
+        ToolDetails::Search {
+            query,
+            path,
+            include,
+        } => {
+            let mut html = format!(
+                "<div class=\"text\">Query: <code>{}</code>",
+                escape_html(query)
+            );
+            if let Some(path) = path {
+                html.push_str(&format!(" in <code>{}</code>", escape_html(path)));
+            }
+            if let Some(include) = include {
+                html.push_str(&format!(" (include <code>{}</code>)", escape_html(include)));
+            }
+            html.push_str("</div>\n");
+            html
+        }
+    }
+}
+
+fn badge(text: &str) -> String {
+    format!("<span class=\"badge\">{}</span>", escape_html(text))
+}
+
+/// Render a tool payload, pretty-printing and highlighting it when it is JSON
+fn render_payload(payload: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(payload) {
+        Ok(value) if value.is_object() || value.is_array() => {
+            let pretty = serde_json::to_string_pretty(&value).unwrap_or_else(|_| payload.into());
+            render_code_block(&pretty, "json")
+        }
+        _ => render_code_block(payload, ""),
+    }
+}
+
+/// Render message text, turning fenced code blocks into highlighted `<pre>` blocks
+fn render_text(text: &str) -> String {
+    let mut html = String::new();
+    let mut prose = Vec::new();
+    let mut code = Vec::new();
+    let mut fence: Option<(String, String)> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match fence {
+            None => {
+                if let Some(info) = trimmed.strip_prefix("```") {
+                    flush_prose(&mut html, &mut prose);
+                    let lang = info.split_whitespace().next().unwrap_or("").to_string();
+                    fence = Some(("```".to_string(), lang));
+                } else {
+                    prose.push(line);
+                }
+            }
+            Some((ref marker, ref lang)) => {
+                if trimmed.starts_with(marker.as_str()) && trimmed.trim_end() == marker {
+                    html.push_str(&render_code_block(&code.join("\n"), lang));
+                    code.clear();
+                    fence = None;
+                } else {
+                    code.push(line);
+                }
+            }
+        }
+    }
+
+    // Unterminated fences still render as code
+    if let Some((_, lang)) = fence {
+        html.push_str(&render_code_block(&code.join("\n"), &lang));
+    }
+    flush_prose(&mut html, &mut prose);
+
+    html
+}
+
+fn flush_prose(html: &mut String, prose: &mut Vec<&str>) {
+    let text = prose.join("\n");
+    prose.clear();
+
+    let text = text.trim_matches('\n');
+    if text.trim().is_empty() {
+        return;
+    }
+
+    html.push_str(&format!(
+        "<div class=\"text\">{}</div>\n",
+        render_inline_code(text)
+    ));
+}
+
+/// Escape prose and wrap `inline code` spans in `<code>`
+fn render_inline_code(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    let mut parts = text.split('`');
+    let count = text.matches('`').count();
+
+    // An odd number of backticks leaves one unmatched; keep it literal
+    let mut in_code = false;
+    let mut seen = 0;
+    if let Some(first) = parts.next() {
+        html.push_str(&escape_html(first));
+    }
+    for part in parts {
+        seen += 1;
+        if !in_code && seen == count && count % 2 == 1 {
+            html.push('`');
+            html.push_str(&escape_html(part));
+            continue;
+        }
+        in_code = !in_code;
+        if in_code {
+            html.push_str("<code>");
+            html.push_str(&escape_html(part));
+        } else {
+            html.push_str("</code>");
+            html.push_str(&escape_html(part));
+        }
+    }
+
+    html
+}
+
+fn render_code_block(code: &str, lang: &str) -> String {
+    let class = if lang.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"language-{}\"", escape_html(lang))
+    };
+    format!(
+        "<pre><code{}>{}</code></pre>\n",
+        class,
+        highlight_code(code, lang)
+    )
+}
+
+/// Lightweight syntax highlighting for common languages.
+///
+/// Recognizes comments, strings, numbers, and keywords; everything else is
+/// emitted as escaped text. Unlabeled code blocks are not highlighted.
+fn highlight_code(code: &str, lang: &str) -> String {
+    let lang = lang.to_ascii_lowercase();
+    if lang == "diff" || lang == "patch" {
+        return highlight_diff(code);
+    }
+    if lang.is_empty() || matches!(lang.as_str(), "text" | "plain" | "txt") {
+        return escape_html(code);
+    }
+
+    let hash_comments = matches!(
+        lang.as_str(),
+        "python"
+            | "py"
+            | "sh"
+            | "bash"
+            | "shell"
+            | "zsh"
+            | "ruby"
+            | "rb"
+            | "yaml"
+            | "yml"
+            | "toml"
+            | "perl"
+            | "r"
+            | "dockerfile"
+            | "makefile"
+            | "ini"
+    );
+    let dash_comments = matches!(lang.as_str(), "sql" | "lua" | "haskell" | "hs");
+    let slash_comments = !hash_comments && !dash_comments;
+
+    let chars: Vec<char> = code.chars().collect();
+    let mut html = String::with_capacity(code.len() * 2);
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let line_comment = (hash_comments && c == '#')
+            || (dash_comments && c == '-' && next == Some('-'))
+            || (slash_comments && c == '/' && next == Some('/'));
+        if line_comment {
+            let end = chars[i..]
+                .iter()
+                .position(|&ch| ch == '\n')
+                .map_or(chars.len(), |pos| i + pos);
+            push_token(&mut html, "tok-com", &chars[i..end]);
+            i = end;
+            continue;
+        }
+
+        if slash_comments && c == '/' && next == Some('*') {
+            let end = find_block_comment_end(&chars, i + 2);
+            push_token(&mut html, "tok-com", &chars[i..end]);
+            i = end;
+            continue;
+        }
+
+        if c == '"' || c == '\'' || c == '`' {
+            let end = find_string_end(&chars, i);
+            push_token(&mut html, "tok-str", &chars[i..end]);
+            i = end;
+            continue;
+        }
+
+        if c.is_ascii_digit() && !chars.get(i.wrapping_sub(1)).is_some_and(|p| is_ident(*p)) {
+            let end = chars[i..]
+                .iter()
+                .position(|&ch| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '_'))
+                .map_or(chars.len(), |pos| i + pos);
+            push_token(&mut html, "tok-num", &chars[i..end]);
+            i = end;
+            continue;
+        }
+
+        if is_ident(c) {
+            let end = chars[i..]
+                .iter()
+                .position(|&ch| !is_ident(ch))
+                .map_or(chars.len(), |pos| i + pos);
+            let word: String = chars[i..end].iter().collect();
+            if KEYWORDS.contains(&word.as_str()) {
+                push_token(&mut html, "tok-kw", &chars[i..end]);
+            } else {
+                html.push_str(&escape_html(&word));
+            }
+            i = end;
+            continue;
+        }
+
+        html.push_str(&escape_html(&c.to_string()));
+        i += 1;
+    }
+
+    html
+}
+
+/// Color added, removed, and hunk-header lines of a diff
+fn highlight_diff(code: &str) -> String {
+    code.split('\n')
+        .map(|line| {
+            let class = if line.starts_with("@@") {
+                Some("diff-hunk")
+            } else if line.starts_with('+') {
+                Some("diff-add")
+            } else if line.starts_with('-') {
+                Some("diff-del")
+            } else {
+                None
+            };
+            match class {
+                Some(class) => format!("<span class=\"{}\">{}</span>", class, escape_html(line)),
+                None => escape_html(line),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn find_block_comment_end(chars: &[char], from: usize) -> usize {
+    let mut i = from;
+    while i + 1 < chars.len() {
+        if chars[i] == '*' && chars[i + 1] == '/' {
+            return i + 2;
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+fn find_string_end(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '\n' if quote != '`' => return i,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+fn push_token(html: &mut String, class: &str, chars: &[char]) {
+    let text: String = chars.iter().collect();
+    html.push_str(&format!(
+        "<span class=\"{}\">{}</span>",
+        class,
+        escape_html(&text)
+    ));
+}
+
+/// Escape text for inclusion in HTML
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::export_chat::{TokenCount, ToolCall};
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: None,
+            thinking_duration_ms: None,
+            tool_call: None,
+            model: None,
+            tokens: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_render_text_code_fence() {
+        let html = render_text("Here:\n```rust\nfn main() {}\n```\nDone <b>");
+        assert!(html.contains("<div class=\"text\">Here:</div>"));
+        assert!(html.contains("<code class=\"language-rust\">"));
+        assert!(html.contains("<span class=\"tok-kw\">fn</span>"));
+        assert!(html.contains("Done &lt;b&gt;"));
+    }
+
+    #[test]
+    fn test_render_text_unterminated_fence() {
+        let html = render_text("```python\nprint('hi')");
+        assert!(html.contains("language-python"));
+        assert!(html.contains("<span class=\"tok-str\">&#39;hi&#39;</span>"));
+    }
+
+    #[test]
+    fn test_render_inline_code() {
+        assert_eq!(
+            render_inline_code("run `cargo test` now"),
+            "run <code>cargo test</code> now"
+        );
+        assert_eq!(render_inline_code("a ` b"), "a ` b");
+    }
+
+    #[test]
+    fn test_highlight_code_comments_and_numbers() {
+        let html = highlight_code("x = 42 # answer", "python");
+        assert!(html.contains("<span class=\"tok-num\">42</span>"));
+        assert!(html.contains("<span class=\"tok-com\"># answer</span>"));
+
+        let html = highlight_code("let v1 = 1; // note", "rust");
+        assert!(html.contains("<span class=\"tok-kw\">let</span>"));
+        assert!(html.contains(" v1 = <span class=\"tok-num\">1</span>;"));
+        assert!(html.contains("<span class=\"tok-com\">// note</span>"));
+    }
+
+    #[test]
+    fn test_highlight_code_unlabeled_is_plain() {
+        assert_eq!(highlight_code("let x = 1", ""), "let x = 1");
+    }
+
+    #[test]
+    fn test_format_message_thinking_is_collapsible() {
+        let mut msg = message("thinking", "Let me think...");
+        msg.thinking_duration_ms = Some(5000);
+        let html = format_message(&msg);
+        assert!(html.starts_with("<details class=\"message thinking\">"));
+        assert!(html.contains("<span class=\"badge\">5.0s</span>"));
+    }
+
+    #[test]
+    fn test_format_message_tool_and_badges() {
+        let mut tool = message("tool", "[read_file]");
+        tool.tool_call = Some(ToolCall {
+            name: "read_file".to_string(),
+            params: Some(r#"{"path":"/test.rs"}"#.to_string()),
+            result: Some("contents".to_string()),
+            status: Some("completed".to_string()),
+            params_truncation: None,
+            result_truncation: None,
+            details: None,
+        });
+        let html = format_message(&tool);
+        assert!(html.contains("Tool: read_file"));
+        assert!(html.contains("<summary>Parameters</summary>"));
+        assert!(html.contains("language-json"));
+        assert!(html.contains("<summary>Result</summary>"));
+
+        let mut assistant = message("assistant", "Hi");
+        assistant.model = Some("gpt-4".to_string());
+        assistant.tokens = Some(TokenCount {
+            input: 100,
+            output: 50,
+        });
+        let html = format_message(&assistant);
+        assert!(html.contains("<span class=\"badge\">gpt-4</span>"));
+        assert!(html.contains("100↓ 50↑"));
+    }
+
+    #[test]
+    fn test_format_as_html_has_toc_and_no_external_assets() {
+        let export = ChatExport {
+            project_path: "/tmp/project".to_string(),
+            exported_at: 0,
+            sessions: vec![ChatSession {
+                id: "s1".to_string(),
+                title: Some("First <session>".to_string()),
+                messages: vec![message("user", "Hello")],
+                created_at: Some(1704067200),
+                updated_at: None,
+                updated_at_ms: None,
+                children: vec![],
+            }],
+        };
+
+        let html = format_as_html(&export);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<a href=\"#session-1\">First &lt;session&gt;</a>"));
+        assert!(html.contains("id=\"session-1\""));
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("<link"));
+    }
+
+    #[test]
+    fn test_format_session_nests_children() {
+        let child = ChatSession {
+            id: "c1".to_string(),
+            title: Some("Explore <schema>".to_string()),
+            messages: vec![message("assistant", "found tables")],
+            created_at: None,
+            updated_at: None,
+            updated_at_ms: None,
+            children: vec![],
+        };
+        let parent = ChatSession {
+            id: "p1".to_string(),
+            title: None,
+            messages: vec![message("user", "Hello")],
+            created_at: None,
+            updated_at: None,
+            updated_at_ms: None,
+            children: vec![child],
+        };
+
+        let html = format_session(&parent, Some(1), "h2");
+        assert!(html.contains("<details class=\"child-session\" open>"));
+        assert!(html.contains("↳ Sub-agent: Explore &lt;schema&gt;"));
+        assert!(html.find("Hello").unwrap() < html.find("found tables").unwrap());
+        assert!(html.ends_with("</details>\n</section>\n"));
+    }
+
+    #[test]
+    fn test_highlight_diff() {
+        let html = highlight_code("@@ -1 +1 @@\n-old\n+new <x>", "diff");
+        assert!(html.contains("<span class=\"diff-hunk\">@@ -1 +1 @@</span>"));
+        assert!(html.contains("<span class=\"diff-del\">-old</span>"));
+        assert!(html.contains("<span class=\"diff-add\">+new &lt;x&gt;</span>"));
+    }
+
+    #[test]
+    fn test_render_attachments() {
+        assert_eq!(render_attachments(&[]), "");
+
+        let html = render_attachments(&[
+            Attachment::File {
+                path: "src/<db>.rs".to_string(),
+            },
+            Attachment::Selection {
+                path: Some("src/main.rs".to_string()),
+                start_line: Some(3),
+                end_line: Some(4),
+                text: "let x = 1;".to_string(),
+            },
+        ]);
+        assert!(html.contains("<li>📄 <code>src/&lt;db&gt;.rs</code></li>"));
+        assert!(html.contains("<summary>✂️ <code>src/main.rs</code> (lines 3–4)</summary>"));
+        assert!(html.contains("language-rust"));
+
+        let html = render_attachments(&[
+            Attachment::Doc {
+                name: "Rust".to_string(),
+                url: Some("https://doc.rust-lang.org/".to_string()),
+            },
+            Attachment::Doc {
+                name: "Evil".to_string(),
+                url: Some("javascript:alert(1)".to_string()),
+            },
+        ]);
+        assert!(html.contains("<li>📚 <a href=\"https://doc.rust-lang.org/\">Rust</a></li>"));
+        assert!(html.contains("<li>📚 Evil (javascript:alert(1))</li>"));
+        assert!(!html.contains("href=\"javascript"));
+    }
+}
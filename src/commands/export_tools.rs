@@ -0,0 +1,513 @@
+//! Structured decoding of well-known Cursor tool calls
+//!
+//! `toolFormerData` stores tool parameters and results as JSON strings whose
+//! shape depends on the tool. For the common ones (file reads, edits, terminal
+//! commands, searches) we pull out the interesting fields so exports can show
+//! a diff or a shell transcript instead of a raw JSON blob. Unknown tools, or
+//! payloads that don't parse, are left undecoded.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::export_chat::Truncation;
+
+/// Decoded fields of a recognised tool call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolDetails {
+    /// A file read
+    ReadFile {
+        /// Target file
+        path: String,
+        /// First line read (1-based), if a range was requested
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_line: Option<i64>,
+        /// Last line read (inclusive), if a range was requested
+        #[serde(skip_serializing_if = "Option::is_none")]
+        end_line: Option<i64>,
+    },
+    /// A file edit
+    EditFile {
+        /// Target file
+        path: String,
+        /// Unified diff hunks (without file headers)
+        diff: String,
+        /// Set when `diff` was truncated
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        diff_truncation: Option<Truncation>,
+    },
+    /// A terminal command
+    Terminal {
+        /// Command line
+        command: String,
+        /// Working directory, if given
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cwd: Option<String>,
+        /// Exit status, if the command finished
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i64>,
+        /// Captured output
+        #[serde(skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+        /// Set when `output` was truncated
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output_truncation: Option<Truncation>,
+    },
+    /// A codebase, text, or file-name search
+    Search {
+        /// Query or pattern
+        query: String,
+        /// Directory or file the search was limited to
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        /// Include glob, if given
+        #[serde(skip_serializing_if = "Option::is_none")]
+        include: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ToolKind {
+    ReadFile,
+    EditFile,
+    Terminal,
+    Search,
+}
+
+const PATH_KEYS: &[&str] = &[
+    "targetFile",
+    "target_file",
+    "relativeWorkspacePath",
+    "relative_workspace_path",
+    "filePath",
+    "file_path",
+    "path",
+];
+
+/// Classify a tool by name, ignoring version suffixes like `_v2`
+fn tool_kind(name: &str) -> Option<ToolKind> {
+    let name = name.to_ascii_lowercase();
+    let base = name
+        .rsplit_once("_v")
+        .filter(|(_, version)| !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()))
+        .map_or(name.as_str(), |(base, _)| base);
+
+    match base {
+        "read_file" | "read" => Some(ToolKind::ReadFile),
+        "edit_file" | "search_replace" | "write" | "write_file" | "edit" => {
+            Some(ToolKind::EditFile)
+        }
+        "run_terminal_cmd" | "run_terminal_command" | "run_command" | "shell" => {
+            Some(ToolKind::Terminal)
+        }
+        "codebase_search" | "grep_search" | "grep" | "ripgrep" | "file_search"
+        | "glob_file_search" | "semantic_search" => Some(ToolKind::Search),
+        _ => None,
+    }
+}
+
+/// Decode a tool call from its raw (untruncated) params and result strings
+pub fn decode_tool_call(
+    name: &str,
+    params: Option<&str>,
+    result: Option<&str>,
+) -> Option<ToolDetails> {
+    let kind = tool_kind(name)?;
+    let params: Value = serde_json::from_str(params?).ok()?;
+    let result = result.and_then(|r| serde_json::from_str::<Value>(r).ok());
+
+    match kind {
+        ToolKind::ReadFile => Some(ToolDetails::ReadFile {
+            path: first_str(&params, PATH_KEYS)?,
+            start_line: first_i64(&params, &["startLine", "start_line", "offset"]),
+            end_line: first_i64(&params, &["endLine", "end_line"]),
+        }),
+        ToolKind::EditFile => {
+            let path = first_str(&params, PATH_KEYS)?;
+            let diff = result
+                .as_ref()
+                .and_then(diff_from_result)
+                .or_else(|| diff_from_params(&params))?;
+            Some(ToolDetails::EditFile {
+                path,
+                diff,
+                diff_truncation: None,
+            })
+        }
+        ToolKind::Terminal => Some(ToolDetails::Terminal {
+            command: first_str(&params, &["command", "commandLine", "cmd"])?,
+            cwd: first_str(&params, &["cwd", "workingDirectory", "working_directory"]),
+            exit_code: result
+                .as_ref()
+                .and_then(|r| first_i64(r, &["exitCode", "exit_code", "exitCodeV2"])),
+            output: result
+                .as_ref()
+                .and_then(|r| first_str(r, &["output", "stdout", "outputRaw"])),
+            output_truncation: None,
+        }),
+        ToolKind::Search => Some(ToolDetails::Search {
+            query: first_str(
+                &params,
+                &["query", "pattern", "globPattern", "glob_pattern"],
+            )?,
+            path: first_str(&params, &["path", "targetDirectory", "target_directory"])
+                .or_else(|| first_array_str(&params, "targetDirectories")),
+            include: first_str(&params, &["includePattern", "include_pattern", "glob"]),
+        }),
+    }
+}
+
+impl ToolDetails {
+    /// Apply export truncation limits to the bulky fields
+    pub fn truncated(
+        self,
+        params_limit: Option<usize>,
+        result_limit: Option<usize>,
+        truncate: impl Fn(&str, Option<usize>) -> (String, Option<Truncation>),
+    ) -> Self {
+        match self {
+            Self::EditFile { path, diff, .. } => {
+                let (diff, diff_truncation) = truncate(&diff, params_limit);
+                Self::EditFile {
+                    path,
+                    diff,
+                    diff_truncation,
+                }
+            }
+            Self::Terminal {
+                command,
+                cwd,
+                exit_code,
+                output,
+                ..
+            } => {
+                let (output, output_truncation) =
+                    output.map(|o| truncate(&o, result_limit)).unzip();
+                Self::Terminal {
+                    command,
+                    cwd,
+                    exit_code,
+                    output,
+                    output_truncation: output_truncation.flatten(),
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// Render as markdown (used in place of the raw parameter/result blocks)
+    pub fn to_markdown(&self) -> String {
+        match self {
+            Self::ReadFile {
+                path,
+                start_line,
+                end_line,
+            } => format!("`{}`{}\n\n", path, line_range(*start_line, *end_line)),
+            Self::EditFile { path, diff, .. } => {
+                format!("`{}`\n\n{}\n", path, fenced(diff, "diff"))
+            }
+            Self::Terminal {
+                command,
+                cwd,
+                exit_code,
+                output,
+                ..
+            } => {
+                let mut md = String::new();
+                if let Some(cwd) = cwd {
+                    md.push_str(&format!("_in `{}`_\n\n", cwd));
+                }
+                md.push_str(&fenced(&format!("$ {}", command), "shell"));
+                md.push('\n');
+                if let Some(output) = output.as_deref().filter(|o| !o.is_empty()) {
+                    md.push_str("<details>\n<summary>Output</summary>\n\n");
+                    md.push_str(&fenced(output, ""));
+                    md.push_str("\n</details>\n\n");
+                }
+                if let Some(code) = exit_code {
+                    md.push_str(&format!("_Exit code: {}_\n\n", code));
+                }
+                md
+            }
+            Self::Search {
+                query,
+                path,
+                include,
+            } => {
+                let mut md = format!("Query: `{}`", query);
+                if let Some(path) = path {
+                    md.push_str(&format!(" in `{}`", path));
+                }
+                if let Some(include) = include {
+                    md.push_str(&format!(" (include `{}`)", include));
+                }
+                md.push_str("\n\n");
+                md
+            }
+        }
+    }
+
+    /// Whether the raw result is still worth showing next to the decoded view
+    ///
+    /// Edits and commands replace it with their diff or output, unless those
+    /// could not be decoded (e.g. a plain-text result).
+    pub fn shows_result(&self) -> bool {
+        match self {
+            Self::ReadFile { .. } | Self::Search { .. } => true,
+            Self::EditFile { diff, .. } => diff.is_empty(),
+            Self::Terminal { output, .. } => output.as_deref().is_none_or(str::is_empty),
+        }
+    }
+}
+
+/// Describe a line range, e.g. ` (lines 10–20)`
+pub(crate) fn line_range(start: Option<i64>, end: Option<i64>) -> String {
+    match (start, end) {
+        (Some(start), Some(end)) => format!(" (lines {}–{})", start, end),
+        (Some(start), None) => format!(" (from line {})", start),
+        (None, Some(end)) => format!(" (to line {})", end),
+        (None, None) => String::new(),
+    }
+}
+
+/// Wrap text in a code fence long enough not to clash with backticks inside it
+pub(crate) fn fenced(text: &str, lang: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        if c == '`' {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    let fence = "`".repeat((longest + 1).max(3));
+    format!("{fence}{lang}\n{}\n{fence}\n", text.trim_end_matches('\n'))
+}
+
+/// Cursor's edit results carry diff chunks: `{"diff": {"chunks": [{"diffString": ...}]}}`
+fn diff_from_result(result: &Value) -> Option<String> {
+    let chunks = result.get("diff")?.get("chunks")?.as_array()?;
+
+    let mut diff = String::new();
+    for chunk in chunks {
+        let Some(body) = chunk.get("diffString").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let start = |key: &str| chunk.get(key).and_then(|v| v.as_i64());
+        if let (Some(old_start), Some(new_start)) = (start("oldStart"), start("newStart")) {
+            diff.push_str(&format!(
+                "@@ -{},{} +{},{} @@\n",
+                old_start,
+                start("oldLines").unwrap_or(0),
+                new_start,
+                start("newLines").unwrap_or(0)
+            ));
+        }
+        diff.push_str(body.trim_end_matches('\n'));
+        diff.push('\n');
+    }
+
+    (!diff.is_empty()).then(|| diff.trim_end_matches('\n').to_string())
+}
+
+/// Build a diff from the edit parameters when the result has none
+fn diff_from_params(params: &Value) -> Option<String> {
+    let old = first_str(params, &["old_string", "oldString"]);
+    let new = first_str(params, &["new_string", "newString"]);
+
+    let (old, new) = match (old, new) {
+        (Some(old), Some(new)) => (old, new),
+        _ => {
+            // Whole-file writes and sketch-style edits only have the new content
+            let content = first_str(params, &["code_edit", "codeEdit", "contents", "content"])?;
+            (String::new(), content)
+        }
+    };
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    // The edit's position in the file is unknown, so there is no hunk header
+    let diff: Vec<String> = old_lines
+        .iter()
+        .map(|line| format!("-{}", line))
+        .chain(new_lines.iter().map(|line| format!("+{}", line)))
+        .collect();
+    Some(diff.join("\n"))
+}
+
+fn first_str(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| value.get(*key))
+        .find_map(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+fn first_i64(value: &Value, keys: &[&str]) -> Option<i64> {
+    keys.iter()
+        .filter_map(|key| value.get(*key))
+        .find_map(|v| v.as_i64())
+}
+
+fn first_array_str(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)?
+        .as_array()?
+        .iter()
+        .find_map(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_kind_ignores_version_suffix() {
+        assert_eq!(tool_kind("read_file_v2"), Some(ToolKind::ReadFile));
+        assert_eq!(tool_kind("edit_file"), Some(ToolKind::EditFile));
+        assert_eq!(
+            tool_kind("run_terminal_command_v2"),
+            Some(ToolKind::Terminal)
+        );
+        assert_eq!(tool_kind("grep_search"), Some(ToolKind::Search));
+        assert_eq!(tool_kind("mcp_custom_tool"), None);
+    }
+
+    #[test]
+    fn test_decode_read_file() {
+        let details = decode_tool_call(
+            "read_file_v2",
+            Some(r#"{"targetFile":"src/main.rs","startLine":10,"endLine":20}"#),
+            Some("fn main() {}"),
+        );
+        assert_eq!(
+            details,
+            Some(ToolDetails::ReadFile {
+                path: "src/main.rs".to_string(),
+                start_line: Some(10),
+                end_line: Some(20),
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_terminal_command() {
+        let details = decode_tool_call(
+            "run_terminal_cmd",
+            Some(r#"{"command":"cargo test","is_background":false}"#),
+            Some(r#"{"output":"test result: ok","exitCode":0}"#),
+        )
+        .unwrap();
+        assert_eq!(
+            details,
+            ToolDetails::Terminal {
+                command: "cargo test".to_string(),
+                cwd: None,
+                exit_code: Some(0),
+                output: Some("test result: ok".to_string()),
+                output_truncation: None,
+            }
+        );
+
+        let md = details.to_markdown();
+        assert!(md.contains("```shell\n$ cargo test\n```"));
+        assert!(md.contains("_Exit code: 0_"));
+        assert!(!details.shows_result());
+
+        // A plain-text result leaves no decoded output, so the raw one is kept
+        let details = decode_tool_call(
+            "run_terminal_cmd",
+            Some(r#"{"command":"cargo test"}"#),
+            Some("test result: ok"),
+        )
+        .unwrap();
+        assert!(details.shows_result());
+    }
+
+    #[test]
+    fn test_decode_search_replace_builds_diff() {
+        let details = decode_tool_call(
+            "search_replace",
+            Some(r#"{"file_path":"a.rs","old_string":"let x = 1;","new_string":"let x = 2;\nlet y = 3;"}"#),
+            Some("ok"),
+        )
+        .unwrap();
+        let ToolDetails::EditFile { path, diff, .. } = &details else {
+            panic!("expected edit");
+        };
+        assert_eq!(path, "a.rs");
+        // The edit's line numbers are unknown, so none are made up
+        assert_eq!(diff, "-let x = 1;\n+let x = 2;\n+let y = 3;");
+        assert!(details.to_markdown().contains("```diff\n-let x = 1;"));
+
+        let details = details.truncated(Some(5), None, |s, limit| {
+            let kept: String = s.chars().take(limit.unwrap()).collect();
+            (
+                kept,
+                Some(Truncation {
+                    truncated_at: 5,
+                    original_length: s.chars().count(),
+                }),
+            )
+        });
+        let ToolDetails::EditFile {
+            diff_truncation, ..
+        } = &details
+        else {
+            panic!("expected edit");
+        };
+        assert_eq!(
+            *diff_truncation,
+            Some(Truncation {
+                truncated_at: 5,
+                original_length: 35,
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_edit_prefers_result_chunks() {
+        let result = r#"{"diff":{"chunks":[{"diffString":"-a\n+b","oldStart":3,"newStart":3,"oldLines":1,"newLines":1}]}}"#;
+        let details = decode_tool_call(
+            "edit_file",
+            Some(r#"{"target_file":"b.rs","code_edit":"b"}"#),
+            Some(result),
+        );
+        assert_eq!(
+            details,
+            Some(ToolDetails::EditFile {
+                path: "b.rs".to_string(),
+                diff: "@@ -3,1 +3,1 @@\n-a\n+b".to_string(),
+                diff_truncation: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_search_and_unknown() {
+        let details = decode_tool_call(
+            "codebase_search",
+            Some(r#"{"query":"where is auth","targetDirectories":["src/"]}"#),
+            None,
+        );
+        assert_eq!(
+            details,
+            Some(ToolDetails::Search {
+                query: "where is auth".to_string(),
+                path: Some("src/".to_string()),
+                include: None,
+            })
+        );
+
+        assert_eq!(decode_tool_call("todo_write", Some("{}"), None), None);
+        assert_eq!(decode_tool_call("read_file", Some("not json"), None), None);
+    }
+
+    #[test]
+    fn test_fenced_avoids_backtick_collisions() {
+        assert_eq!(fenced("a ``` b", "diff"), "````diff\na ``` b\n````\n");
+    }
+}
@@ -0,0 +1,51 @@
+//! Index command - Build or refresh the persistent chat history index
+
+use anyhow::{bail, Context, Result};
+use owo_colors::OwoColorize;
+use std::fs;
+
+use super::utils;
+use crate::cursor::chat_sessions;
+use crate::cursor::search_index::{self, SearchIndex};
+
+/// Execute the index command
+pub fn execute(rebuild: bool, remove: bool) -> Result<()> {
+    let index_path = search_index::index_db_path()?;
+
+    if remove {
+        if index_path.exists() {
+            fs::remove_file(&index_path)
+                .with_context(|| format!("Failed to remove: {}", index_path.display()))?;
+            println!("{} {}", "Removed:".green(), index_path.display());
+        } else {
+            println!("No search index found at: {}", index_path.display());
+        }
+        return Ok(());
+    }
+
+    let Some(global_conn) = chat_sessions::open_global_state_db()? else {
+        bail!("No global state database found; nothing to index");
+    };
+
+    let mut index = SearchIndex::open(&index_path)?;
+    if rebuild {
+        index.clear()?;
+    }
+
+    println!("Indexing chat history...");
+    let stats = index.refresh(&global_conn)?;
+
+    println!(
+        "  {} updated, {} removed, {} unchanged",
+        stats.updated, stats.removed, stats.unchanged
+    );
+    println!(
+        "{} {} ({} sessions, {})",
+        "Index:".green(),
+        index_path.display(),
+        index.session_count()?,
+        utils::format_size(fs::metadata(&index_path)?.len())
+    );
+
+    Ok(())
+}
@@ -3,13 +3,15 @@
 use anyhow::{Context, Result};
 use comfy_table::{presets::UTF8_FULL_CONDENSED, Cell, ContentArrangement, Table};
 use percent_encoding::percent_decode_str;
+use serde::Serialize;
 use std::fs;
 use std::path::PathBuf;
 use std::time::SystemTime;
 use url::Url;
 
-use super::utils;
+use super::utils::{self, ReportFormat};
 use crate::config;
+use crate::cursor::code_workspace::{self, CodeWorkspace};
 
 #[derive(Debug)]
 struct ProjectLoadWarning {
@@ -78,6 +80,25 @@ pub struct Project {
 
     /// Number of chat sessions found, if discovery succeeded
     pub chat_count: Option<usize>,
+
+    /// Member folders of a multi-root workspace (`path` is then the
+    /// `.code-workspace` file); empty for single-folder projects
+    pub folders: Vec<PathBuf>,
+}
+
+impl Project {
+    /// Path shown to users, prefixed with the remote (e.g. `ssh:box:/srv/app`)
+    pub fn display_path(&self) -> String {
+        match &self.remote {
+            Some(remote) => format!(
+                "{}:{}:{}",
+                remote.remote_type,
+                remote.name,
+                self.path.display()
+            ),
+            None => self.path.display().to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -133,13 +154,12 @@ fn list(workspace_storage_dir: PathBuf) -> Result<(Vec<Project>, ListWarnings)>
         // workspace.json can have either:
         // - "folder": single-folder project
         // - "workspace": multi-root .code-workspace file
-        let folder_url = match workspace.get("folder").and_then(|v| v.as_str()) {
-            Some(url) => url,
-            None => {
-                // Multi-root workspace - skip for now (would need to parse .code-workspace)
-                // Could also use workspace.get("workspace") to show the workspace file
-                continue;
-            }
+        let Some(folder_url) = workspace
+            .get("folder")
+            .or_else(|| workspace.get("workspace"))
+            .and_then(|v| v.as_str())
+        else {
+            continue;
         };
 
         // Parse folder URL
@@ -175,12 +195,27 @@ fn list(workspace_storage_dir: PathBuf) -> Result<(Vec<Project>, ListWarnings)>
             }
         };
 
+        let folders = match workspace.get("workspace").and_then(|v| v.as_str()) {
+            Some(uri) => match code_workspace::config_path_from_uri(uri) {
+                Some(config_path) => match CodeWorkspace::read(&config_path) {
+                    Ok(ws) => ws.folders.into_iter().map(|f| f.path).collect(),
+                    Err(err) => {
+                        warnings.push(folder_id.clone(), parsed.path.clone(), err.to_string());
+                        vec![]
+                    }
+                },
+                None => vec![],
+            },
+            None => vec![],
+        };
+
         projects.push(Project {
             folder_id,
             path: parsed.path,
             remote: parsed.remote,
             last_modified,
             chat_count,
+            folders,
         });
     }
 
@@ -194,6 +229,15 @@ fn list(workspace_storage_dir: PathBuf) -> Result<(Vec<Project>, ListWarnings)>
     Ok((projects, warnings))
 }
 
+/// Discover all projects (local and remote) in Cursor's workspace storage
+pub fn discover_projects() -> Result<Vec<Project>> {
+    let workspace_storage_dir = config::workspace_storage_dir()
+        .context("Failed to determine workspace storage directory")?;
+
+    let (projects, _warnings) = list(workspace_storage_dir)?;
+    Ok(projects)
+}
+
 /// Options for the list command
 pub struct ListOptions {
     /// Show workspace ID for each project
@@ -206,6 +250,89 @@ pub struct ListOptions {
     pub filter: Option<String>,
     /// Limit number of results
     pub limit: Option<usize>,
+    /// Output format
+    pub format: ReportFormat,
+}
+
+/// Machine-readable view of a project (`list --format json|csv`)
+///
+/// Field names and order are part of the output schema; add new fields at
+/// the end.
+#[derive(Debug, Serialize)]
+pub struct ProjectRecord {
+    /// Workspace storage folder ID (use with `export-chat --workspace-id`)
+    pub folder_id: String,
+    /// Project path (the remote path for remote projects)
+    pub path: String,
+    /// Remote type (`ssh`, `tunnel`, `container`, `wsl`, ...), null if local
+    pub remote_type: Option<String>,
+    /// Remote host or name, null if local
+    pub remote_name: Option<String>,
+    /// Last modified time of the workspace storage (RFC 3339, UTC)
+    pub last_modified: Option<String>,
+    /// Number of chat sessions, null if discovery failed
+    pub chat_count: Option<usize>,
+    /// Size of the workspace storage folder in bytes
+    pub workspace_size: u64,
+    /// Member folders of a multi-root workspace (empty otherwise)
+    pub folders: Vec<String>,
+}
+
+impl ProjectRecord {
+    const CSV_HEADER: [&'static str; 8] = [
+        "folder_id",
+        "path",
+        "remote_type",
+        "remote_name",
+        "last_modified",
+        "chat_count",
+        "workspace_size",
+        "folders",
+    ];
+
+    fn new(project: &Project, workspace_storage_dir: &std::path::Path) -> Self {
+        Self {
+            folder_id: project.folder_id.clone(),
+            path: project.path.to_string_lossy().to_string(),
+            remote_type: project.remote.as_ref().map(|r| r.remote_type.to_string()),
+            remote_name: project.remote.as_ref().map(|r| r.name.clone()),
+            last_modified: project.last_modified.map(|t| {
+                chrono::DateTime::<chrono::Utc>::from(t)
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+            }),
+            chat_count: project.chat_count,
+            workspace_size: utils::calculate_dir_size(
+                &workspace_storage_dir.join(&project.folder_id),
+            )
+            .unwrap_or(0),
+            folders: project
+                .folders
+                .iter()
+                .map(|f| f.to_string_lossy().to_string())
+                .collect(),
+        }
+    }
+
+    /// CSV fields; multi-root folders are joined with `;`
+    fn csv_fields(&self) -> [String; 8] {
+        [
+            self.folder_id.clone(),
+            self.path.clone(),
+            self.remote_type.clone().unwrap_or_default(),
+            self.remote_name.clone().unwrap_or_default(),
+            self.last_modified.clone().unwrap_or_default(),
+            self.chat_count.map(|c| c.to_string()).unwrap_or_default(),
+            self.workspace_size.to_string(),
+            self.folders.join(";"),
+        ]
+    }
+}
+
+/// Format project records as CSV with a header row
+fn format_csv(records: &[ProjectRecord]) -> String {
+    let mut lines = vec![utils::csv_row(&ProjectRecord::CSV_HEADER)];
+    lines.extend(records.iter().map(|r| utils::csv_row(&r.csv_fields())));
+    lines.join("\n")
 }
 
 /// Execute the list command and return formatted output
@@ -213,7 +340,7 @@ pub fn execute(options: ListOptions) -> Result<(String, Option<String>)> {
     let workspace_storage_dir = config::workspace_storage_dir()
         .context("Failed to determine workspace storage directory")?;
 
-    let (mut projects, warnings) = list(workspace_storage_dir)?;
+    let (mut projects, warnings) = list(workspace_storage_dir.clone())?;
 
     // Apply filter
     if let Some(ref filter_str) = options.filter {
@@ -255,7 +382,48 @@ pub fn execute(options: ListOptions) -> Result<(String, Option<String>)> {
         projects.truncate(n);
     }
 
-    // Build table
+    let output = match options.format {
+        ReportFormat::Table => format_table(&projects, options.with_id, total_count),
+        ReportFormat::Json | ReportFormat::Csv => {
+            let records: Vec<ProjectRecord> = projects
+                .iter()
+                .map(|project| ProjectRecord::new(project, &workspace_storage_dir))
+                .collect();
+            if options.format == ReportFormat::Json {
+                serde_json::to_string_pretty(&records)
+                    .context("Failed to serialize project list")?
+            } else {
+                format_csv(&records)
+            }
+        }
+    };
+
+    let displayed_folder_ids: std::collections::HashSet<&str> = projects
+        .iter()
+        .map(|project| project.folder_id.as_str())
+        .collect();
+    let filtered_warning_entries = warnings
+        .entries
+        .into_iter()
+        .filter(|warning| displayed_folder_ids.contains(warning.folder_id.as_str()))
+        .collect::<Vec<_>>();
+    let warning_output = if filtered_warning_entries.is_empty() {
+        None
+    } else {
+        Some(
+            filtered_warning_entries
+                .iter()
+                .map(|warning| format!("- {}: {}", warning.display_path.display(), warning.message))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    };
+
+    Ok((output, warning_output))
+}
+
+/// Format projects as a table, followed by a count summary
+fn format_table(projects: &[Project], with_id: bool, total_count: usize) -> String {
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL_CONDENSED)
@@ -263,7 +431,7 @@ pub fn execute(options: ListOptions) -> Result<(String, Option<String>)> {
 
     // Build header
     let mut header = vec![];
-    if options.with_id {
+    if with_id {
         header.push(Cell::new("ID"));
     }
     header.push(Cell::new("Remote"));
@@ -272,8 +440,11 @@ pub fn execute(options: ListOptions) -> Result<(String, Option<String>)> {
     header.push(Cell::new("Modified"));
     table.set_header(header);
 
-    for project in &projects {
-        let path_str = project.path.to_string_lossy().to_string();
+    for project in projects {
+        let mut path_str = project.path.to_string_lossy().to_string();
+        for folder in &project.folders {
+            path_str.push_str(&format!("\n  • {}", folder.display()));
+        }
         let chat_str = project
             .chat_count
             .map(|count| count.to_string())
@@ -293,7 +464,7 @@ pub fn execute(options: ListOptions) -> Result<(String, Option<String>)> {
             .unwrap_or_else(|| "-".to_string());
 
         let mut row = vec![];
-        if options.with_id {
+        if with_id {
             row.push(Cell::new(&project.folder_id));
         }
         row.push(Cell::new(remote_str));
@@ -303,7 +474,6 @@ pub fn execute(options: ListOptions) -> Result<(String, Option<String>)> {
         table.add_row(row);
     }
 
-    // Build output
     let mut output = table.to_string();
     if projects.len() < total_count {
         output.push_str(&format!(
@@ -315,38 +485,17 @@ pub fn execute(options: ListOptions) -> Result<(String, Option<String>)> {
         output.push_str(&format!("\n\n{} projects found", total_count));
     }
 
-    let displayed_folder_ids: std::collections::HashSet<&str> = projects
-        .iter()
-        .map(|project| project.folder_id.as_str())
-        .collect();
-    let filtered_warning_entries = warnings
-        .entries
-        .into_iter()
-        .filter(|warning| displayed_folder_ids.contains(warning.folder_id.as_str()))
-        .collect::<Vec<_>>();
-    let warning_output = if filtered_warning_entries.is_empty() {
-        None
-    } else {
-        Some(
-            filtered_warning_entries
-                .iter()
-                .map(|warning| format!("- {}: {}", warning.display_path.display(), warning.message))
-                .collect::<Vec<_>>()
-                .join("\n"),
-        )
-    };
-
-    Ok((output, warning_output))
+    output
 }
 
 /// Parsed URL result containing path and optional remote info
-struct ParsedUrl {
-    path: PathBuf,
-    remote: Option<RemoteInfo>,
+pub struct ParsedUrl {
+    pub path: PathBuf,
+    pub remote: Option<RemoteInfo>,
 }
 
 /// Convert a file:// or vscode-remote:// URL to a PathBuf with optional remote info
-fn parse_folder_url(url_str: &str) -> Option<ParsedUrl> {
+pub fn parse_folder_url(url_str: &str) -> Option<ParsedUrl> {
     let url = Url::parse(url_str).ok()?;
 
     match url.scheme() {
@@ -396,6 +545,33 @@ fn parse_folder_url(url_str: &str) -> Option<ParsedUrl> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_project_record_csv_and_json() {
+        let project = Project {
+            folder_id: "abc123".to_string(),
+            path: PathBuf::from("/home/user/my,app"),
+            remote: Some(RemoteInfo {
+                remote_type: RemoteType::SshRemote,
+                name: "box".to_string(),
+            }),
+            last_modified: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(86400)),
+            chat_count: None,
+            folders: vec![],
+        };
+        let record = ProjectRecord::new(&project, std::path::Path::new("/nonexistent"));
+
+        assert_eq!(
+            format_csv(&[record]),
+            "folder_id,path,remote_type,remote_name,last_modified,chat_count,workspace_size,folders\n\
+             abc123,\"/home/user/my,app\",ssh,box,1970-01-02T00:00:00Z,,0,"
+        );
+
+        let record = ProjectRecord::new(&project, std::path::Path::new("/nonexistent"));
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["remote_type"], "ssh");
+        assert!(json["chat_count"].is_null());
+    }
+
     #[cfg(not(windows))]
     #[test]
     fn test_parse_local_url() {
@@ -531,6 +707,7 @@ mod tests {
             }),
             last_modified: None,
             chat_count: Some(5),
+            folders: vec![],
         };
         assert_eq!(project.folder_id, "abc123");
         assert_eq!(project.chat_count, Some(5));
@@ -545,6 +722,7 @@ mod tests {
             remote: None,
             last_modified: None,
             chat_count: None,
+            folders: vec![],
         };
 
         let chat_str = project
@@ -557,13 +735,14 @@ mod tests {
 
     #[test]
     fn test_sort_by_chats_places_unknown_last() {
-        let mut projects = vec![
+        let mut projects = [
             Project {
                 folder_id: "a".to_string(),
                 path: PathBuf::from("/a"),
                 remote: None,
                 last_modified: None,
                 chat_count: None,
+                folders: vec![],
             },
             Project {
                 folder_id: "b".to_string(),
@@ -571,6 +750,7 @@ mod tests {
                 remote: None,
                 last_modified: None,
                 chat_count: Some(2),
+                folders: vec![],
             },
         ];
 
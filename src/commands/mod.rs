@@ -1,11 +1,23 @@
 //! CLI commands
 
 pub mod backup;
+pub mod backup_inspect;
 pub mod clean;
 pub mod clone;
+pub mod export_attachments;
 pub mod export_chat;
+pub mod export_html;
+pub mod export_tools;
+pub mod index;
 pub mod list;
+pub mod relink;
 pub mod rename;
+pub mod rename_batch;
+pub mod rename_journal;
 pub mod restore;
+pub mod safety_backups;
+pub mod search;
 pub mod stats;
+pub mod undo;
+pub mod usage;
 pub mod utils;
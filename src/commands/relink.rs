@@ -0,0 +1,201 @@
+//! Relink command - move Cursor metadata after a folder was moved outside the tool
+//!
+//! The folder is already at its new location, so the filesystem move is skipped
+//! and only the metadata steps of a rename run: the workspaceStorage hash move,
+//! composer index sync, storage.json and the global state DB.
+
+use anyhow::{bail, Result};
+use owo_colors::OwoColorize;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use super::clean::{self, OrphanedWorkspace};
+use super::rename::{self, Locations};
+use super::rename_journal::{Operation, RenameJournal};
+use super::safety_backups;
+use crate::cursor::workspace;
+
+/// Execute the relink command
+pub fn execute(old_path: &str, new_path: &str, dry_run: bool) -> Result<()> {
+    let new_path = rename::normalize_path(new_path)?;
+    let old_path = PathBuf::from(old_path);
+    let old_path = rename::clean_path(&if old_path.is_absolute() {
+        old_path
+    } else {
+        std::env::current_dir()?.join(old_path)
+    });
+
+    if old_path.exists() {
+        bail!(
+            "Old path still exists: {}. Use `rename` to move it",
+            old_path.display()
+        );
+    }
+
+    // Check if Cursor is running (skip in dry-run)
+    if !dry_run && rename::is_cursor_running() {
+        bail!("Cursor is running. Please close it completely before running this script.");
+    }
+
+    let locations = Locations::from_config()?;
+    let orphaned = if locations.workspace_storage_dir.exists() {
+        clean::find_orphaned_workspaces(&locations.workspace_storage_dir)?
+    } else {
+        Vec::new()
+    };
+    if find_orphaned(&orphaned, &old_path)?.is_none() {
+        bail!("{}", not_found_message(&orphaned, &old_path, &new_path));
+    }
+
+    let mut plan = rename::prepare_plan(old_path, new_path, false, false, &locations)?;
+    let new_workspace_hash = workspace::compute_workspace_hash(&plan.new_path)?;
+    plan.new_workspace_dir_existed = new_workspace_hash != plan.old_workspace_hash
+        && locations
+            .workspace_storage_dir
+            .join(&new_workspace_hash)
+            .exists();
+    plan.new_workspace_hash = Some(new_workspace_hash);
+    plan.folder_moved = true;
+
+    let old_projects_dir = locations.projects_dir.join(&plan.old_folder_id);
+    let old_workspace_dir = locations
+        .workspace_storage_dir
+        .join(&plan.old_workspace_hash);
+
+    // Print summary
+    println!();
+    println!("{}", "=== Cursor Project RELINK Tool ===".green());
+    println!();
+    println!("Old path: {} (gone)", plan.old_path.display());
+    println!("New path: {}", plan.new_path.display());
+    println!();
+    println!("Old folder ID: {}", plan.old_folder_id);
+    println!("Old workspace hash: {}", plan.old_workspace_hash);
+    println!(
+        "New workspace hash: {}",
+        plan.new_workspace_hash.as_deref().unwrap_or_default()
+    );
+    println!();
+
+    rename::print_exists_status("Cursor projects dir", &old_projects_dir);
+    rename::print_exists_status("Workspace storage dir", &old_workspace_dir);
+    for ws in &plan.multi_root {
+        println!(
+            "{} Multi-root workspace: {} ({})",
+            "Found:".green(),
+            ws.config_path.display(),
+            ws.hash
+        );
+    }
+    if plan.new_workspace_dir_existed {
+        println!(
+            "{} Cursor already created a workspace for the new path; it will be replaced \
+             (a copy is kept in the safety backup)",
+            "Warning:".yellow()
+        );
+    }
+    println!();
+
+    // Confirm (skip in dry-run)
+    if !dry_run && !rename::confirm("Proceed with relink?")? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    // Step 0: Create backups for rollback safety
+    let backup_dir = if dry_run {
+        println!("{}", "Step 0: Skipping backups in dry-run mode.".yellow());
+        None
+    } else {
+        println!("{}", "Step 0: Creating safety backup...".green());
+        let backup_dir = rename::create_rename_backup(&plan, &locations, None, dry_run)?;
+        if let Some(backup_dir) = &backup_dir {
+            println!("  Backup created at: {}", backup_dir.display());
+        }
+        backup_dir
+    };
+
+    println!("New folder ID: {}", plan.new_folder_id);
+
+    let mut journal = RenameJournal::new(Operation::Rename, plan, backup_dir)?;
+    if dry_run {
+        rename::run_steps(&mut journal, &locations, None, dry_run)?;
+    } else {
+        rename::run_journaled(&mut journal, &locations)?;
+    }
+
+    rename::print_completion(&journal.plan, dry_run);
+    if let Some(backup_dir) = &journal.backup_dir {
+        safety_backups::apply_retention_policy(std::slice::from_ref(backup_dir));
+    }
+    Ok(())
+}
+
+/// Find the orphaned workspace whose `workspace.json` points at `old_path`
+fn find_orphaned<'a>(
+    orphaned: &'a [OrphanedWorkspace],
+    old_path: &Path,
+) -> Result<Option<&'a OrphanedWorkspace>> {
+    let old_uri = Url::from_file_path(old_path)
+        .map_err(|_| anyhow::anyhow!("Invalid path: {}", old_path.display()))?
+        .to_string();
+    let old_uri = old_uri.trim_end_matches('/');
+
+    Ok(orphaned
+        .iter()
+        .find(|o| o.folder_url.trim_end_matches('/') == old_uri))
+}
+
+/// Explain a missing workspace, suggesting orphans with the same folder name
+fn not_found_message(orphaned: &[OrphanedWorkspace], old_path: &Path, new_path: &Path) -> String {
+    let mut message = format!("No orphaned workspace found for {}", old_path.display());
+
+    let candidates: Vec<PathBuf> = orphaned
+        .iter()
+        .filter_map(|o| Url::parse(&o.folder_url).ok()?.to_file_path().ok())
+        .filter(|path| path.file_name().is_some() && path.file_name() == new_path.file_name())
+        .collect();
+
+    if candidates.is_empty() {
+        message.push_str("\nRun `cursor-helper clean --dry-run` to list orphaned workspaces");
+    } else {
+        message.push_str("\nOrphaned workspaces for a folder with the same name:");
+        for path in candidates {
+            message.push_str(&format!("\n  {}", path.display()));
+        }
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orphan(folder_url: &str) -> OrphanedWorkspace {
+        OrphanedWorkspace {
+            storage_path: PathBuf::from("/storage/hash"),
+            folder_url: folder_url.to_string(),
+            size_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn test_find_orphaned() {
+        let orphaned = vec![
+            orphan("file:///old/other"),
+            orphan("file:///old/project/"),
+            orphan("vscode-remote://ssh-remote%2Bbox/old/project"),
+        ];
+
+        let found = find_orphaned(&orphaned, Path::new("/old/project")).unwrap();
+        assert_eq!(found.unwrap().folder_url, "file:///old/project/");
+        assert!(find_orphaned(&orphaned, Path::new("/old/missing"))
+            .unwrap()
+            .is_none());
+
+        let message =
+            not_found_message(&orphaned, Path::new("/old/typo"), Path::new("/new/project"));
+        assert!(message.contains("same name:\n  /old/project"));
+        assert!(!message.contains("/old/other"));
+    }
+}
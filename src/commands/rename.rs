@@ -5,11 +5,13 @@
 //! - Workspace settings
 //! - MCP cache
 //! - Terminal info
+//! - Multi-root workspaces (`.code-workspace` files) that include the project
 
 use anyhow::{bail, Context, Result};
 use fs_extra::dir::{self, CopyOptions};
 use owo_colors::OwoColorize;
 use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
@@ -17,10 +19,61 @@ use std::process::Command;
 use std::time::UNIX_EPOCH;
 use url::Url;
 
+use super::rename_batch;
+use super::rename_journal::{self, JournalStatus, Operation, RenameJournal, RenameStep};
+use super::safety_backups;
 use super::utils;
 use crate::config;
+use crate::cursor::code_workspace::{self, CodeWorkspace};
 use crate::cursor::{folder_id, storage, workspace};
 
+/// Everything needed to run (or resume) the steps of a rename
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenamePlan {
+    pub old_path: PathBuf,
+    pub new_path: PathBuf,
+    pub copy_mode: bool,
+    pub force_index: bool,
+    pub old_folder_id: String,
+    pub new_folder_id: String,
+    pub old_workspace_hash: String,
+    /// Known once the destination exists (Step 1)
+    pub new_workspace_hash: Option<String>,
+    /// Whether `~/.cursor/projects/<new folder id>` existed before the rename
+    pub new_projects_dir_existed: bool,
+    /// Whether `workspaceStorage/<new hash>` existed before Step 3
+    pub new_workspace_dir_existed: bool,
+    /// Merge into existing destination data without asking (a batch
+    /// confirms this up front)
+    #[serde(default)]
+    pub merge_existing: bool,
+    /// The project folder was already moved outside the tool (`relink`):
+    /// Step 1 leaves it alone, and so does rolling back
+    #[serde(default)]
+    pub folder_moved: bool,
+    pub multi_root: Vec<MultiRootWorkspace>,
+}
+
+/// Cursor data locations touched by a rename
+pub struct Locations {
+    pub projects_dir: PathBuf,
+    pub workspace_storage_dir: PathBuf,
+    pub storage_json_path: PathBuf,
+    pub global_state_db_path: PathBuf,
+}
+
+impl Locations {
+    pub fn from_config() -> Result<Self> {
+        let global_storage_dir = config::global_storage_dir()?;
+        Ok(Self {
+            projects_dir: config::cursor_projects_dir()?,
+            workspace_storage_dir: config::workspace_storage_dir()?,
+            storage_json_path: global_storage_dir.join("storage.json"),
+            global_state_db_path: global_storage_dir.join("state.vscdb"),
+        })
+    }
+}
+
 /// Execute the rename command
 pub fn execute(
     old_path: &str,
@@ -45,42 +98,17 @@ pub fn execute(
         bail!("Cursor is running. Please close it completely before running this script.");
     }
 
-    // Try to find existing workspace storage
-    // This handles symlink issues (e.g., /tmp vs /private/tmp on macOS)
-    let (cursor_old_path, old_workspace_hash) = match find_existing_workspace(&old_path)? {
-        Some((cursor_path, hash)) => {
-            if cursor_path != old_path.to_string_lossy() {
-                println!(
-                    "{} Cursor recorded path as: {}",
-                    "Note:".yellow(),
-                    cursor_path
-                );
-            }
-            (PathBuf::from(&cursor_path), hash)
-        }
-        None => {
-            // No existing workspace found, compute from user path
-            let hash = workspace::compute_workspace_hash(&old_path)?;
-            (old_path.clone(), hash)
-        }
-    };
-
-    // Compute folder ID from the path Cursor actually used
-    let old_folder_id = folder_id::path_to_folder_id(&cursor_old_path);
-
     // Get directories
-    let cursor_projects_dir = config::cursor_projects_dir()?;
-    let workspace_storage_dir = config::workspace_storage_dir()?;
-    let global_storage_dir = config::global_storage_dir()?;
+    let locations = Locations::from_config()?;
+    let plan = prepare_plan(old_path, new_path, copy_mode, force_index, &locations)?;
 
-    let old_projects_dir = cursor_projects_dir.join(&old_folder_id);
-    let old_workspace_dir = workspace_storage_dir.join(&old_workspace_hash);
-    let storage_json_path = global_storage_dir.join("storage.json");
-    let global_state_db_path = global_storage_dir.join("state.vscdb");
+    let old_projects_dir = locations.projects_dir.join(&plan.old_folder_id);
+    let old_workspace_dir = locations
+        .workspace_storage_dir
+        .join(&plan.old_workspace_hash);
 
     // Mode description
     let mode = if copy_mode { "COPY" } else { "MOVE" };
-    let action = if copy_mode { "Copying" } else { "Moving" };
 
     // Print summary
     println!();
@@ -89,279 +117,817 @@ pub fn execute(
         format!("=== Cursor Project {} Tool ===", mode).green()
     );
     println!();
-    println!("Old path: {}", old_path.display());
-    println!("New path: {}", new_path.display());
+    println!("Old path: {}", plan.old_path.display());
+    println!("New path: {}", plan.new_path.display());
     println!("Mode: {}", mode);
     println!("Force index: {}", if force_index { "on" } else { "off" });
     println!();
-    println!("Old folder ID: {}", old_folder_id);
-    println!("Old workspace hash: {}", old_workspace_hash);
+    println!("Old folder ID: {}", plan.old_folder_id);
+    println!("Old workspace hash: {}", plan.old_workspace_hash);
     println!();
 
     // Check if old data exists
     print_exists_status("Cursor projects dir", &old_projects_dir);
     print_exists_status("Workspace storage dir", &old_workspace_dir);
+    for ws in &plan.multi_root {
+        println!(
+            "{} Multi-root workspace: {} ({})",
+            "Found:".green(),
+            ws.config_path.display(),
+            ws.hash
+        );
+    }
     println!();
 
     // Confirm (skip in dry-run)
-    if !dry_run {
-        print!("Proceed with {}? (y/N) ", mode.to_lowercase());
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-
-        if !input.trim().eq_ignore_ascii_case("y") {
-            println!("Aborted.");
-            return Ok(());
-        }
+    if !dry_run && !confirm(&format!("Proceed with {}?", mode.to_lowercase()))? {
+        println!("Aborted.");
+        return Ok(());
     }
 
     // Step 0: Create backups for rollback safety
-    if dry_run {
+    let backup_dir = if dry_run {
         println!("{}", "Step 0: Skipping backups in dry-run mode.".yellow());
+        None
     } else {
         println!("{}", "Step 0: Creating safety backup...".green());
-        if let Some(backup_dir) = create_rename_backup(
-            &old_projects_dir,
-            &old_workspace_dir,
-            &storage_json_path,
-            &global_state_db_path,
-            dry_run,
-        )? {
+        let backup_dir = create_rename_backup(&plan, &locations, None, dry_run)?;
+        if let Some(backup_dir) = &backup_dir {
             println!("  Backup created at: {}", backup_dir.display());
         }
-    }
+        backup_dir
+    };
 
-    let old_uri = path_to_file_uri(&old_path)?;
-    let new_uri = path_to_file_uri(&new_path)?;
-    let old_path_raw = old_path.to_string_lossy().to_string();
-    let new_path_raw = new_path.to_string_lossy().to_string();
-    let estimated_move_hash = if !copy_mode {
-        Some(estimate_hash_after_move(&old_path, &new_path)?)
+    println!("New folder ID: {}", plan.new_folder_id);
+
+    let mut journal = RenameJournal::new(Operation::Rename, plan, backup_dir)?;
+    if dry_run {
+        run_steps(&mut journal, &locations, None, dry_run)?;
     } else {
-        None
+        run_journaled(&mut journal, &locations)?;
+    }
+
+    print_completion(&journal.plan, dry_run);
+    if let Some(backup_dir) = &journal.backup_dir {
+        safety_backups::apply_retention_policy(std::slice::from_ref(backup_dir));
+    }
+    Ok(())
+}
+
+/// Work out what renaming `old_path` to `new_path` involves, before anything changes
+pub fn prepare_plan(
+    old_path: PathBuf,
+    new_path: PathBuf,
+    copy_mode: bool,
+    force_index: bool,
+    locations: &Locations,
+) -> Result<RenamePlan> {
+    // Try to find existing workspace storage
+    // This handles symlink issues (e.g., /tmp vs /private/tmp on macOS)
+    let (cursor_old_path, old_workspace_hash) = match find_existing_workspace(&old_path)? {
+        Some((cursor_path, hash)) => {
+            if cursor_path != old_path.to_string_lossy() {
+                println!(
+                    "{} Cursor recorded path as: {}",
+                    "Note:".yellow(),
+                    cursor_path
+                );
+            }
+            (PathBuf::from(&cursor_path), hash)
+        }
+        None => {
+            // No existing workspace found, compute from user path
+            let hash = workspace::compute_workspace_hash(&old_path)?;
+            (old_path.clone(), hash)
+        }
     };
 
-    // Compute new folder ID (before creating destination)
+    // Compute folder IDs from the path Cursor actually used
+    let old_folder_id = folder_id::path_to_folder_id(&cursor_old_path);
     let new_folder_id = folder_id::path_to_folder_id(&new_path);
-    println!("New folder ID: {}", new_folder_id);
 
-    // Step 1: Copy/Move the project folder
+    // Multi-root workspaces must be found before the move, while their
+    // .code-workspace files are still where workspaceStorage says they are
+    let multi_root =
+        find_multi_root_workspaces(&locations.workspace_storage_dir, &old_path, &new_path)?;
+
+    Ok(RenamePlan {
+        new_projects_dir_existed: locations.projects_dir.join(&new_folder_id).exists(),
+        old_path,
+        new_path,
+        copy_mode,
+        force_index,
+        old_folder_id,
+        new_folder_id,
+        old_workspace_hash,
+        new_workspace_hash: None,
+        new_workspace_dir_existed: false,
+        merge_existing: false,
+        folder_moved: false,
+        multi_root,
+    })
+}
+
+/// Resume the most recent interrupted rename
+pub fn resume() -> Result<()> {
+    let backups_dir = rename_journal::backups_dir()?;
+    let mut journal = rename_journal::find_interrupted(&backups_dir)?
+        .context("No interrupted rename to resume")?;
+
+    if is_cursor_running() {
+        bail!("Cursor is running. Please close it completely before running this script.");
+    }
+    if let Some(batch) = &journal.batch {
+        return rename_batch::resume(batch);
+    }
+
+    print_journal_summary(&journal);
+    if !confirm("Resume?")? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let locations = Locations::from_config()?;
+    run_journaled(&mut journal, &locations)?;
+
+    print_completion(&journal.plan, false);
+    if let Some(backup_dir) = &journal.backup_dir {
+        safety_backups::apply_retention_policy(std::slice::from_ref(backup_dir));
+    }
+    Ok(())
+}
+
+/// Roll back the most recent interrupted rename
+pub fn rollback() -> Result<()> {
+    let backups_dir = rename_journal::backups_dir()?;
+    let mut journal = rename_journal::find_interrupted(&backups_dir)?
+        .context("No interrupted rename to roll back")?;
+
+    if is_cursor_running() {
+        bail!("Cursor is running. Please close it completely before running this script.");
+    }
+    if let Some(batch) = &journal.batch {
+        return rename_batch::rollback(batch);
+    }
+
+    print_journal_summary(&journal);
+    if !confirm("Roll back?")? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let locations = Locations::from_config()?;
+    println!("{}", "Rolling back...".yellow());
+    roll_back(&mut journal, &locations, true)?;
+
+    println!();
+    println!("{}", "=== Rollback complete! ===".green());
+    println!();
     println!(
-        "{}",
-        format!("Step 1: {} project folder...", action).green()
+        "{} is back at {}.",
+        journal.plan.new_path.display(),
+        journal.plan.old_path.display()
     );
-    println!("  {} -> {}", old_path.display(), new_path.display());
-    copy_or_move(&old_path, &new_path, copy_mode, dry_run)?;
+    Ok(())
+}
 
-    // Compute new workspace hash after destination exists
-    let new_workspace_hash = if dry_run {
-        if copy_mode {
-            println!(
-                "  {}",
-                "(New folder would get new birthtime, hash computed at runtime)".yellow()
-            );
-            "<computed-at-runtime>".to_string()
-        } else {
-            estimated_move_hash
-                .clone()
-                .context("Failed to estimate moved workspace hash")?
-        }
-    } else {
-        workspace::compute_workspace_hash(&new_path)?
-    };
+/// Ask a yes/no question on stdin
+pub fn confirm(question: &str) -> Result<bool> {
+    print!("{} (y/N) ", question);
+    io::stdout().flush()?;
 
-    println!("New workspace hash: {}", new_workspace_hash);
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
 
-    let mut source_composer_db: Option<PathBuf> = None;
-    if copy_mode && !dry_run && old_workspace_dir.exists() {
-        source_composer_db = Some(old_workspace_dir.join("state.vscdb"));
-    }
+/// Print what an interrupted rename was doing and how far it got
+fn print_journal_summary(journal: &RenameJournal) {
+    let plan = &journal.plan;
+    let mode = if plan.copy_mode { "COPY" } else { "MOVE" };
 
-    // Step 2: Copy/Move ~/.cursor/projects/
-    let new_projects_dir = cursor_projects_dir.join(&new_folder_id);
-    if old_projects_dir.exists() {
-        println!(
-            "{}",
-            format!("Step 2: {} cursor projects data...", action).green()
-        );
+    println!();
+    println!("Interrupted {} started at {}", mode, journal.started_at);
+    println!("Old path: {}", plan.old_path.display());
+    println!("New path: {}", plan.new_path.display());
+    if let Some(backup_dir) = &journal.backup_dir {
+        println!("Backup: {}", backup_dir.display());
+    }
+    println!();
+    for step in RenameStep::ALL {
+        let state = match journal.entries.iter().find(|e| e.step == step) {
+            Some(entry) if entry.done => "done".green().to_string(),
+            Some(_) => "interrupted".yellow().to_string(),
+            None => "pending".to_string(),
+        };
         println!(
-            "  {} -> {}",
-            old_projects_dir.display(),
-            new_projects_dir.display()
+            "  Step {}: {} ({})",
+            step.number(),
+            step.description(),
+            state
         );
-        copy_or_move(&old_projects_dir, &new_projects_dir, copy_mode, dry_run)?;
-        println!("  -> {}", new_projects_dir.display());
-    } else {
-        println!("{}", "Step 2: No cursor projects data to migrate".yellow());
     }
+    println!();
+}
 
-    // Step 3: Copy/Move workspaceStorage
-    let new_workspace_dir = workspace_storage_dir.join(&new_workspace_hash);
-    if old_workspace_dir.exists() {
-        println!(
-            "{}",
-            format!("Step 3: {} workspaceStorage...", action).green()
-        );
-        println!(
-            "  {} -> {}",
-            old_workspace_dir.display(),
-            new_workspace_dir.display()
-        );
-        copy_or_move(&old_workspace_dir, &new_workspace_dir, copy_mode, dry_run)?;
-        println!("  -> {}", new_workspace_dir.display());
+/// Run the remaining steps, rolling back everything on failure
+pub fn run_journaled(journal: &mut RenameJournal, locations: &Locations) -> Result<()> {
+    let Err(err) = run_steps(journal, locations, None, false) else {
+        return Ok(());
+    };
 
-        // Step 4: Update workspace.json
-        let workspace_json_path = new_workspace_dir.join("workspace.json");
-        println!("{}", "Step 4: Updating workspace.json...".green());
+    println!();
+    println!("{} {:#}", "Failed:".red(), err);
+    println!("{}", "Rolling back...".yellow());
+
+    let backup_dir = journal
+        .backup_dir
+        .as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    match roll_back(journal, locations, true) {
+        Ok(()) => Err(err.context("Rename failed; all changes were rolled back")),
+        Err(rollback_err) => Err(err.context(format!(
+            "Rename failed and could not be rolled back ({:#}). \
+             Fix the problem, then run `cursor-helper rename --resume` or `--rollback`. \
+             Backup: {}",
+            rollback_err, backup_dir
+        ))),
+    }
+}
 
-        if dry_run {
+/// Run the steps not yet recorded as done in the journal
+///
+/// `global_db` is an open connection to the global state DB to use instead
+/// of opening it for each update.
+pub fn run_steps(
+    journal: &mut RenameJournal,
+    locations: &Locations,
+    global_db: Option<&Connection>,
+    dry_run: bool,
+) -> Result<()> {
+    for step in RenameStep::ALL {
+        if journal.is_done(step) {
+            continue;
+        }
+        journal.start(step)?;
+        run_step(step, &mut journal.plan, locations, global_db, dry_run)?;
+        journal.finish(step)?;
+    }
+
+    journal.set_status(JournalStatus::Completed)
+}
+
+/// Run a single step
+fn run_step(
+    step: RenameStep,
+    plan: &mut RenamePlan,
+    locations: &Locations,
+    global_db: Option<&Connection>,
+    dry_run: bool,
+) -> Result<()> {
+    let action = if plan.copy_mode { "Copying" } else { "Moving" };
+    let old_uri = path_to_file_uri(&plan.old_path)?;
+    let new_uri = path_to_file_uri(&plan.new_path)?;
+    let old_path_raw = plan.old_path.to_string_lossy().to_string();
+    let new_path_raw = plan.new_path.to_string_lossy().to_string();
+    let old_projects_dir = locations.projects_dir.join(&plan.old_folder_id);
+    let new_projects_dir = locations.projects_dir.join(&plan.new_folder_id);
+    let old_workspace_dir = locations
+        .workspace_storage_dir
+        .join(&plan.old_workspace_hash);
+    let new_workspace_hash = plan.new_workspace_hash.clone().unwrap_or_default();
+    let new_workspace_dir = locations.workspace_storage_dir.join(&new_workspace_hash);
+
+    match step {
+        // Step 1: Copy/Move the project folder
+        RenameStep::ProjectFolder => {
+            if plan.folder_moved {
+                println!("{}", "Step 1: Project folder already moved".green());
+            } else {
+                println!(
+                    "{}",
+                    format!("Step 1: {} project folder...", action).green()
+                );
+            }
             println!(
-                "  {} Write to {}:",
-                "[DRY-RUN]".blue(),
-                workspace_json_path.display()
+                "  {} -> {}",
+                plan.old_path.display(),
+                plan.new_path.display()
             );
-            println!("  {} folder: {}", "[DRY-RUN]".blue(), new_uri);
-        } else {
-            let ws = workspace::WorkspaceJson::new(&new_path)?;
-            ws.write(&workspace_json_path)?;
+            if plan.folder_moved || (!dry_run && !plan.old_path.exists() && plan.new_path.exists())
+            {
+                // Resumed after the move itself finished
+                println!("  -> Already at {}", plan.new_path.display());
+            } else {
+                copy_or_move(
+                    &plan.old_path,
+                    &plan.new_path,
+                    plan.copy_mode,
+                    false,
+                    dry_run,
+                )?;
+            }
+
+            // Compute new workspace hash after destination exists
+            let new_workspace_hash = if dry_run && !plan.folder_moved {
+                if plan.copy_mode {
+                    println!(
+                        "  {}",
+                        "(New folder would get new birthtime, hash computed at runtime)".yellow()
+                    );
+                    "<computed-at-runtime>".to_string()
+                } else {
+                    estimate_hash_after_move(&plan.old_path, &plan.new_path)?
+                }
+            } else {
+                workspace::compute_workspace_hash(&plan.new_path)?
+            };
+
+            println!("New workspace hash: {}", new_workspace_hash);
+            plan.new_workspace_dir_existed = new_workspace_hash != plan.old_workspace_hash
+                && locations
+                    .workspace_storage_dir
+                    .join(&new_workspace_hash)
+                    .exists();
+            plan.new_workspace_hash = Some(new_workspace_hash);
         }
-        println!("  -> folder URI: {}", new_uri);
-    } else {
-        println!("{}", "Step 3: No workspaceStorage data to migrate".yellow());
-    }
-
-    // Step 5: Ensure composer index in workspace state DB
-    if !dry_run && new_workspace_dir.exists() {
-        let new_workspace_db = new_workspace_dir.join("state.vscdb");
-        if new_workspace_db.exists() {
-            println!("{}", "Step 5: Synchronizing composer index...".green());
-            let updated = sync_workspace_composer_index(
-                source_composer_db.as_deref(),
-                &new_workspace_db,
-                &old_uri,
-                &new_uri,
-                &old_path_raw,
-                &new_path_raw,
-                &old_workspace_hash,
-                &new_workspace_hash,
-                force_index,
-                dry_run,
-            )?;
-            if updated {
-                println!("  -> Composer index synchronized");
+
+        // Step 2: Copy/Move ~/.cursor/projects/
+        RenameStep::ProjectsData => {
+            if old_projects_dir.exists() {
+                println!(
+                    "{}",
+                    format!("Step 2: {} cursor projects data...", action).green()
+                );
+                println!(
+                    "  {} -> {}",
+                    old_projects_dir.display(),
+                    new_projects_dir.display()
+                );
+                copy_or_move(
+                    &old_projects_dir,
+                    &new_projects_dir,
+                    plan.copy_mode,
+                    plan.merge_existing,
+                    dry_run,
+                )?;
+                println!("  -> {}", new_projects_dir.display());
             } else {
-                println!("  -> Composer index already complete");
+                println!("{}", "Step 2: No cursor projects data to migrate".yellow());
             }
-        } else {
-            println!("  -> No workspace state DB found; skipping composer index sync");
         }
-    } else if dry_run {
-        println!(
-            "{}",
-            "Step 5: Composer index sync skipped in dry-run".yellow()
-        );
-    } else {
-        println!(
-            "{}",
-            "Step 5: No workspace state DB available for sync".yellow()
-        );
-    }
 
-    // Step 6: Update storage.json
-    if storage_json_path.exists() {
-        println!(
-            "{}",
-            "Step 6: Updating globalStorage/storage.json...".green()
-        );
+        // Step 3: Copy/Move workspaceStorage
+        RenameStep::WorkspaceStorage => {
+            if old_workspace_dir.exists() {
+                println!(
+                    "{}",
+                    format!("Step 3: {} workspaceStorage...", action).green()
+                );
+                println!(
+                    "  {} -> {}",
+                    old_workspace_dir.display(),
+                    new_workspace_dir.display()
+                );
+                // Cursor made a fresh workspace when the moved folder was
+                // opened; the old one replaces it (both are in the backup)
+                let replace = plan.folder_moved
+                    && plan.new_workspace_dir_existed
+                    && new_workspace_dir.exists();
+                if replace {
+                    println!(
+                        "  Replacing the workspace Cursor created for the new path: {}",
+                        new_workspace_dir.display()
+                    );
+                    if !dry_run {
+                        fs::remove_dir_all(&new_workspace_dir).with_context(|| {
+                            format!(
+                                "Failed to remove directory: {}",
+                                new_workspace_dir.display()
+                            )
+                        })?;
+                    }
+                }
+                if !(replace && dry_run) {
+                    copy_or_move(
+                        &old_workspace_dir,
+                        &new_workspace_dir,
+                        plan.copy_mode,
+                        plan.merge_existing,
+                        dry_run,
+                    )?;
+                }
+                println!("  -> {}", new_workspace_dir.display());
+            } else {
+                println!("{}", "Step 3: No workspaceStorage data to migrate".yellow());
+            }
+        }
 
-        if dry_run {
-            println!("  {} Update {} -> {}", "[DRY-RUN]".blue(), old_uri, new_uri);
+        // Step 4: Update workspace.json
+        RenameStep::WorkspaceJson => {
+            if new_workspace_dir.exists() || (dry_run && old_workspace_dir.exists()) {
+                let workspace_json_path = new_workspace_dir.join("workspace.json");
+                println!("{}", "Step 4: Updating workspace.json...".green());
+
+                if dry_run {
+                    println!(
+                        "  {} Write to {}:",
+                        "[DRY-RUN]".blue(),
+                        workspace_json_path.display()
+                    );
+                    println!("  {} folder: {}", "[DRY-RUN]".blue(), new_uri);
+                } else {
+                    let ws = workspace::WorkspaceJson::new(&plan.new_path)?;
+                    ws.write(&workspace_json_path)?;
+                }
+                println!("  -> folder URI: {}", new_uri);
+            }
         }
 
-        let modified =
-            storage::update_storage_json(&storage_json_path, &old_uri, &new_uri, dry_run)?;
+        // Step 5: Ensure composer index in workspace state DB
+        RenameStep::ComposerIndex => {
+            if !dry_run && new_workspace_dir.exists() {
+                let new_workspace_db = new_workspace_dir.join("state.vscdb");
+                if new_workspace_db.exists() {
+                    println!("{}", "Step 5: Synchronizing composer index...".green());
+                    let source_composer_db = (plan.copy_mode && old_workspace_dir.exists())
+                        .then(|| old_workspace_dir.join("state.vscdb"));
+                    let updated = sync_workspace_composer_index(
+                        source_composer_db.as_deref(),
+                        &new_workspace_db,
+                        &old_uri,
+                        &new_uri,
+                        &old_path_raw,
+                        &new_path_raw,
+                        &plan.old_workspace_hash,
+                        &new_workspace_hash,
+                        plan.force_index,
+                        dry_run,
+                    )?;
+                    if updated {
+                        println!("  -> Composer index synchronized");
+                    } else {
+                        println!("  -> Composer index already complete");
+                    }
+                } else {
+                    println!("  -> No workspace state DB found; skipping composer index sync");
+                }
+            } else if dry_run {
+                println!(
+                    "{}",
+                    "Step 5: Composer index sync skipped in dry-run".yellow()
+                );
+            } else {
+                println!(
+                    "{}",
+                    "Step 5: No workspace state DB available for sync".yellow()
+                );
+            }
+        }
 
-        let hash_modified = old_workspace_hash != new_workspace_hash;
-        if hash_modified && !dry_run {
-            println!(
-                "  Note: storage.json hash migration skipped (format may be unsupported): {} -> {}",
-                old_workspace_hash, new_workspace_hash
-            );
-        } else if dry_run {
-            println!(
-                "  {} Hash migration in storage.json would run if needed",
-                "[DRY-RUN]".blue()
-            );
+        // Step 6: Update storage.json
+        RenameStep::StorageJson => {
+            let storage_json_path = &locations.storage_json_path;
+            if storage_json_path.exists() {
+                println!(
+                    "{}",
+                    "Step 6: Updating globalStorage/storage.json...".green()
+                );
+
+                if dry_run {
+                    println!("  {} Update {} -> {}", "[DRY-RUN]".blue(), old_uri, new_uri);
+                }
+
+                let modified =
+                    storage::update_storage_json(storage_json_path, &old_uri, &new_uri, dry_run)?;
+
+                let hash_modified = plan.old_workspace_hash != new_workspace_hash;
+                if hash_modified && !dry_run {
+                    println!(
+                        "  Note: storage.json hash migration skipped (format may be unsupported): {} -> {}",
+                        plan.old_workspace_hash, new_workspace_hash
+                    );
+                } else if dry_run {
+                    println!(
+                        "  {} Hash migration in storage.json would run if needed",
+                        "[DRY-RUN]".blue()
+                    );
+                }
+
+                if !modified && !hash_modified {
+                    println!("  -> No matching storage.json updates applied");
+                }
+            } else {
+                println!("{}", "Step 6: No storage.json file found".yellow());
+            }
         }
 
-        if !modified && !hash_modified {
-            println!("  -> No matching storage.json updates applied");
+        // Step 7: Update global state DB
+        RenameStep::GlobalStateDb => {
+            let global_state_db_path = &locations.global_state_db_path;
+            if global_state_db_path.exists() {
+                println!(
+                    "{}",
+                    "Step 7: Updating globalStorage/state.vscdb...".green()
+                );
+
+                if dry_run {
+                    println!("  {} Update {} -> {}", "[DRY-RUN]".blue(), old_uri, new_uri);
+                    println!(
+                        "  {} Update workspace hash {} -> {}",
+                        "[DRY-RUN]".blue(),
+                        plan.old_workspace_hash,
+                        new_workspace_hash
+                    );
+                }
+
+                let global_modified = update_global_refs(
+                    global_db,
+                    global_state_db_path,
+                    &old_path_raw,
+                    &new_path_raw,
+                    &old_uri,
+                    &new_uri,
+                    &plan.old_workspace_hash,
+                    &new_workspace_hash,
+                    dry_run,
+                )?;
+
+                if global_modified {
+                    println!("  -> Updated global state references");
+                } else {
+                    println!("  -> No matching global state references found");
+                }
+            } else {
+                println!("{}", "Step 7: No global state DB found".yellow());
+            }
+        }
+
+        // Step 8: Update multi-root workspaces
+        RenameStep::MultiRoot => {
+            if plan.multi_root.is_empty() {
+                println!(
+                    "{}",
+                    "Step 8: No multi-root workspaces reference the old path".yellow()
+                );
+            } else if plan.copy_mode {
+                println!(
+                    "{}",
+                    "Step 8: Multi-root workspaces keep referencing the original (copy mode)"
+                        .yellow()
+                );
+            } else {
+                println!("{}", "Step 8: Updating multi-root workspaces...".green());
+                for ws in &plan.multi_root {
+                    update_multi_root_workspace(
+                        ws,
+                        &locations.storage_json_path,
+                        &locations.global_state_db_path,
+                        global_db,
+                        &old_uri,
+                        &new_uri,
+                        &old_path_raw,
+                        &new_path_raw,
+                        plan.force_index,
+                        dry_run,
+                    )?;
+                }
+            }
+        }
+
+        // Step 9: Clear stale cache directories
+        RenameStep::ClearCache => {
+            println!("{}", "Step 9: Clearing stale cache data...".green());
+            if !dry_run {
+                if new_workspace_dir.exists() {
+                    clear_path(
+                        &new_workspace_dir.join("anysphere.cursor-retrieval"),
+                        dry_run,
+                    )?;
+                } else {
+                    println!("  -> No workspace cache directory for new path");
+                }
+
+                for cache_dir in config::cursor_cache_dirs()? {
+                    if cache_dir.exists() {
+                        clear_path(&cache_dir, dry_run)?;
+                    }
+                }
+            } else {
+                println!("  -> Cache clear skipped in dry-run");
+            }
         }
-    } else {
-        println!("{}", "Step 6: No storage.json file found".yellow());
     }
 
-    // Step 7: Update global state DB
-    if global_state_db_path.exists() {
+    Ok(())
+}
+
+/// Undo the started steps of a journaled rename in reverse order, restoring
+/// metadata from the safety backup
+///
+/// A copied project folder is removed only with `remove_copy`: right after
+/// a failure it is ours alone, but once the operation completed it may hold
+/// new work.
+pub fn roll_back(
+    journal: &mut RenameJournal,
+    locations: &Locations,
+    remove_copy: bool,
+) -> Result<()> {
+    let backup_dir = journal
+        .backup_dir
+        .clone()
+        .context("Rename journal has no backup directory")?;
+    let plan = &journal.plan;
+
+    for entry in journal.entries.iter().rev() {
         println!(
-            "{}",
-            "Step 7: Updating globalStorage/state.vscdb...".green()
+            "  <- Step {}: {}",
+            entry.step.number(),
+            entry.step.description()
         );
+        undo_step(entry.step, plan, locations, &backup_dir, remove_copy)?;
+    }
 
-        if dry_run {
-            println!("  {} Update {} -> {}", "[DRY-RUN]".blue(), old_uri, new_uri);
-            println!(
-                "  {} Update workspace hash {} -> {}",
-                "[DRY-RUN]".blue(),
-                old_workspace_hash,
-                new_workspace_hash
-            );
+    let status = match journal.status {
+        JournalStatus::Completed => JournalStatus::Undone,
+        _ => JournalStatus::RolledBack,
+    };
+    journal.set_status(status)
+}
+
+/// Undo a single step (safe to call on a step that only partially ran)
+fn undo_step(
+    step: RenameStep,
+    plan: &RenamePlan,
+    locations: &Locations,
+    backup_dir: &Path,
+    remove_copy: bool,
+) -> Result<()> {
+    match step {
+        RenameStep::ProjectFolder => {
+            if plan.folder_moved || !plan.new_path.exists() {
+                return Ok(());
+            }
+            if plan.copy_mode && !remove_copy {
+                println!(
+                    "  {} Keeping copied project folder: {}",
+                    "Note:".yellow(),
+                    plan.new_path.display()
+                );
+            } else if plan.copy_mode {
+                // The destination did not exist before, so it is entirely ours
+                fs::remove_dir_all(&plan.new_path).with_context(|| {
+                    format!("Failed to remove directory: {}", plan.new_path.display())
+                })?;
+            } else if plan.old_path.exists() {
+                bail!(
+                    "Both {} and {} exist; move the project back by hand",
+                    plan.old_path.display(),
+                    plan.new_path.display()
+                );
+            } else {
+                copy_or_move(&plan.new_path, &plan.old_path, false, false, false)?;
+            }
+        }
+        RenameStep::ProjectsData => undo_dir_migration(
+            &backup_dir.join("old_projects"),
+            &locations.projects_dir.join(&plan.old_folder_id),
+            &locations.projects_dir.join(&plan.new_folder_id),
+            plan.new_projects_dir_existed,
+            plan.copy_mode,
+        )?,
+        RenameStep::WorkspaceStorage => {
+            let old_dir = locations
+                .workspace_storage_dir
+                .join(&plan.old_workspace_hash);
+            let new_dir = match &plan.new_workspace_hash {
+                Some(hash) => locations.workspace_storage_dir.join(hash),
+                None => old_dir.clone(),
+            };
+            let old_snapshot = backup_dir.join("old_workspace");
+            let new_snapshot = backup_dir.join("new_workspace");
+            if new_snapshot.exists() {
+                // Relink replaced the workspace Cursor created for the new path
+                if old_snapshot.exists() {
+                    restore_dir(&old_snapshot, &old_dir)?;
+                }
+                restore_dir(&new_snapshot, &new_dir)?;
+            } else {
+                undo_dir_migration(
+                    &old_snapshot,
+                    &old_dir,
+                    &new_dir,
+                    plan.new_workspace_dir_existed,
+                    plan.copy_mode,
+                )?;
+            }
+        }
+        // Edits inside the new workspace directory, undone with Step 3
+        RenameStep::WorkspaceJson | RenameStep::ComposerIndex => {}
+        RenameStep::StorageJson => {
+            restore_file(
+                &backup_dir.join("storage.json"),
+                &locations.storage_json_path,
+            )?;
+        }
+        RenameStep::GlobalStateDb => {
+            if let Some(snapshot) = safety_backups::global_db_snapshot(backup_dir) {
+                restore_file(&snapshot, &locations.global_state_db_path)?;
+            }
         }
+        RenameStep::MultiRoot => {
+            if plan.copy_mode {
+                return Ok(());
+            }
+            for ws in &plan.multi_root {
+                let snapshot = backup_dir.join("multi_root").join(&ws.hash);
+                if ws.new_dir != ws.dir && ws.new_dir.exists() {
+                    fs::remove_dir_all(&ws.new_dir).with_context(|| {
+                        format!("Failed to remove directory: {}", ws.new_dir.display())
+                    })?;
+                }
+                if snapshot.exists() {
+                    restore_dir(&snapshot, &ws.dir)?;
+                }
+                // The project folder is moved back afterwards, taking a
+                // .code-workspace file inside it along
+                restore_file(
+                    &snapshot.with_extension("code-workspace"),
+                    &ws.new_config_path,
+                )?;
+            }
+        }
+        // Caches are rebuilt by Cursor
+        RenameStep::ClearCache => {}
+    }
 
-        let global_modified = storage::update_global_state_db(
-            &global_state_db_path,
-            &old_path_raw,
-            &new_path_raw,
-            &old_uri,
-            &new_uri,
-            &old_workspace_hash,
-            &new_workspace_hash,
-            dry_run,
-        )?;
+    Ok(())
+}
 
-        if global_modified {
-            println!("  -> Updated global state references");
+/// Undo a copied/moved metadata directory from its backup snapshot
+fn undo_dir_migration(
+    snapshot: &Path,
+    old_dir: &Path,
+    new_dir: &Path,
+    new_dir_existed: bool,
+    copy_mode: bool,
+) -> Result<()> {
+    if new_dir != old_dir && new_dir.exists() {
+        if new_dir_existed {
+            println!(
+                "  {} Leaving merged directory in place: {}",
+                "Note:".yellow(),
+                new_dir.display()
+            );
         } else {
-            println!("  -> No matching global state references found");
+            fs::remove_dir_all(new_dir)
+                .with_context(|| format!("Failed to remove directory: {}", new_dir.display()))?;
         }
-    } else {
-        println!("{}", "Step 7: No global state DB found".yellow());
     }
 
-    // Step 8: Clear stale cache directories
-    println!("{}", "Step 8: Clearing stale cache data...".green());
-    if !dry_run {
-        if new_workspace_dir.exists() {
-            clear_path(
-                &new_workspace_dir.join("anysphere.cursor-retrieval"),
-                dry_run,
-            )?;
-        } else {
-            println!("  -> No workspace cache directory for new path");
-        }
+    // Without a snapshot there was nothing to migrate
+    if snapshot.exists() && (!copy_mode || new_dir == old_dir) {
+        restore_dir(snapshot, old_dir)?;
+    }
 
-        for cache_dir in config::cursor_cache_dirs()? {
-            if cache_dir.exists() {
-                clear_path(&cache_dir, dry_run)?;
-            }
-        }
-    } else {
-        println!("  -> Cache clear skipped in dry-run");
+    Ok(())
+}
+
+/// Replace `target` with a copy of the `snapshot` directory
+fn restore_dir(snapshot: &Path, target: &Path) -> Result<()> {
+    if target.exists() {
+        fs::remove_dir_all(target)
+            .with_context(|| format!("Failed to remove directory: {}", target.display()))?;
+    }
+    utils::copy_dir(snapshot, target)
+}
+
+/// Replace `target` with `snapshot`, if there is a snapshot
+fn restore_file(snapshot: &Path, target: &Path) -> Result<()> {
+    if !snapshot.exists() {
+        return Ok(());
     }
+    fs::copy(snapshot, target).with_context(|| {
+        format!(
+            "Failed to restore {} from {}",
+            target.display(),
+            snapshot.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Print the closing summary of a rename
+pub fn print_completion(plan: &RenamePlan, dry_run: bool) {
+    let mode = if plan.copy_mode {
+        "COPY"
+    } else if plan.folder_moved {
+        "RELINK"
+    } else {
+        "MOVE"
+    };
 
-    // Done!
     println!();
     println!("{}", format!("=== {} complete! ===", mode).green());
     println!();
@@ -370,23 +936,21 @@ pub fn execute(
         println!("This was a dry-run. No changes were made.");
         println!("Run without --dry-run to apply changes.");
     } else {
-        println!("You can now open {} in Cursor.", new_path.display());
+        println!("You can now open {} in Cursor.", plan.new_path.display());
         println!("Your chat history and workspace settings should be preserved.");
-        if copy_mode {
+        if plan.copy_mode {
             println!();
             println!(
                 "Original project at {} was kept intact.",
-                old_path.display()
+                plan.old_path.display()
             );
         }
     }
-
-    Ok(())
 }
 
 /// Update composer index in workspace state DB when copied from an existing workspace
 #[allow(clippy::too_many_arguments)]
-fn sync_workspace_composer_index(
+pub fn sync_workspace_composer_index(
     source_db_path: Option<&Path>,
     target_db_path: &Path,
     old_uri: &str,
@@ -596,42 +1160,234 @@ fn is_composer_value_suffix_terminator(suffix: Option<char>) -> bool {
     }
 }
 
-/// Create backup snapshots before mutating Cursor metadata.
-fn create_rename_backup(
-    old_projects_dir: &Path,
-    old_workspace_dir: &Path,
+/// A multi-root workspace that includes the renamed path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiRootWorkspace {
+    /// workspaceStorage hash before the rename
+    pub hash: String,
+    /// workspaceStorage hash after the rename
+    new_hash: String,
+    /// workspaceStorage directory before the rename
+    dir: PathBuf,
+    /// workspaceStorage directory after the rename
+    new_dir: PathBuf,
+    /// `.code-workspace` file before the rename
+    pub config_path: PathBuf,
+    /// `.code-workspace` file after the rename
+    new_config_path: PathBuf,
+    /// Rewritten `.code-workspace` content, if any folder entry changes
+    content: Option<String>,
+}
+
+/// Find multi-root workspaces whose `.code-workspace` file or member folders
+/// are at or below `old_path`
+fn find_multi_root_workspaces(
+    workspace_storage_dir: &Path,
+    old_path: &Path,
+    new_path: &Path,
+) -> Result<Vec<MultiRootWorkspace>> {
+    if !workspace_storage_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let remap = |path: &Path| {
+        path.strip_prefix(old_path).ok().map(|rest| {
+            if rest.as_os_str().is_empty() {
+                new_path.to_path_buf()
+            } else {
+                new_path.join(rest)
+            }
+        })
+    };
+
+    let mut found = Vec::new();
+    for entry in fs::read_dir(workspace_storage_dir)?.filter_map(Result::ok) {
+        let Ok(content) = fs::read_to_string(entry.path().join("workspace.json")) else {
+            continue;
+        };
+        let Ok(ws) = serde_json::from_str::<serde_json::Value>(&content) else {
+            continue;
+        };
+        let Some(config_path) = ws
+            .get("workspace")
+            .and_then(|v| v.as_str())
+            .and_then(code_workspace::config_path_from_uri)
+        else {
+            continue;
+        };
+        // After a move outside the tool the file is only at its new location
+        let Some(raw) = fs::read_to_string(&config_path)
+            .ok()
+            .or_else(|| remap(&config_path).and_then(|p| fs::read_to_string(p).ok()))
+        else {
+            continue;
+        };
+        let parsed = match CodeWorkspace::parse(&raw, &config_path) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("Warning: Skipping {}: {}", config_path.display(), e);
+                continue;
+            }
+        };
+
+        let affected = config_path.starts_with(old_path)
+            || parsed
+                .folders
+                .iter()
+                .any(|f| f.uri.is_none() && f.path.starts_with(old_path));
+        if !affected {
+            continue;
+        }
+
+        let hash = entry.file_name().to_string_lossy().to_string();
+        let (new_config_path, new_hash) = match remap(&config_path) {
+            Some(moved) => {
+                let new_hash = workspace::compute_multi_root_hash(&moved);
+                (moved, new_hash)
+            }
+            None => (config_path.clone(), hash.clone()),
+        };
+
+        found.push(MultiRootWorkspace {
+            content: code_workspace::rewrite_folders(&raw, &config_path, &new_config_path, remap),
+            dir: entry.path(),
+            new_dir: workspace_storage_dir.join(&new_hash),
+            hash,
+            new_hash,
+            config_path,
+            new_config_path,
+        });
+    }
+
+    Ok(found)
+}
+
+/// Rewrite a multi-root workspace's folder entries and migrate its
+/// workspaceStorage entry and global references
+#[allow(clippy::too_many_arguments)]
+fn update_multi_root_workspace(
+    ws: &MultiRootWorkspace,
     storage_json_path: &Path,
     global_state_db_path: &Path,
+    global_db: Option<&Connection>,
+    old_uri: &str,
+    new_uri: &str,
+    old_path: &str,
+    new_path: &str,
+    force_index: bool,
+    dry_run: bool,
+) -> Result<()> {
+    println!("  {}", ws.new_config_path.display());
+
+    if let Some(content) = &ws.content {
+        if dry_run {
+            println!("  {} Rewrite folder entries", "[DRY-RUN]".blue());
+        } else {
+            fs::write(&ws.new_config_path, content)
+                .with_context(|| format!("Failed to write: {}", ws.new_config_path.display()))?;
+        }
+        println!("  -> Updated folder entries");
+    }
+
+    if ws.hash != ws.new_hash {
+        let old_config_uri = path_to_file_uri(&ws.config_path)?;
+        let new_config_uri = path_to_file_uri(&ws.new_config_path)?;
+
+        println!("  {} -> {}", ws.dir.display(), ws.new_dir.display());
+        copy_or_move(&ws.dir, &ws.new_dir, false, false, dry_run)?;
+
+        let workspace_json_path = ws.new_dir.join("workspace.json");
+        if dry_run {
+            println!("  {} workspace: {}", "[DRY-RUN]".blue(), new_config_uri);
+        } else {
+            let content =
+                serde_json::to_string_pretty(&serde_json::json!({ "workspace": new_config_uri }))?;
+            fs::write(&workspace_json_path, content)
+                .with_context(|| format!("Failed to write: {}", workspace_json_path.display()))?;
+        }
+
+        storage::update_storage_json(storage_json_path, &old_config_uri, &new_config_uri, dry_run)?;
+        update_global_refs(
+            global_db,
+            global_state_db_path,
+            &ws.config_path.to_string_lossy(),
+            &ws.new_config_path.to_string_lossy(),
+            &old_config_uri,
+            &new_config_uri,
+            &ws.hash,
+            &ws.new_hash,
+            dry_run,
+        )?;
+        println!("  -> workspace hash: {} -> {}", ws.hash, ws.new_hash);
+    }
+
+    let workspace_db = ws.new_dir.join("state.vscdb");
+    if !dry_run && workspace_db.exists() {
+        sync_workspace_composer_index(
+            None,
+            &workspace_db,
+            old_uri,
+            new_uri,
+            old_path,
+            new_path,
+            &ws.hash,
+            &ws.new_hash,
+            force_index,
+            dry_run,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Create backup snapshots before mutating Cursor metadata.
+pub fn create_rename_backup(
+    plan: &RenamePlan,
+    locations: &Locations,
+    shared_global_db: Option<&Path>,
     dry_run: bool,
 ) -> Result<Option<PathBuf>> {
     if dry_run {
         return Ok(None);
     }
 
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_nanos())
-        .unwrap_or(0);
-    let backup_root = std::env::temp_dir().join(format!("cursor-helper-rename-backup-{timestamp}"));
-    fs::create_dir_all(&backup_root).with_context(|| {
-        format!(
-            "Failed to create backup directory: {}",
-            backup_root.display()
-        )
-    })?;
+    let old_projects_dir = locations.projects_dir.join(&plan.old_folder_id);
+    let old_workspace_dir = locations
+        .workspace_storage_dir
+        .join(&plan.old_workspace_hash);
+    let storage_json_path = &locations.storage_json_path;
+    let global_state_db_path = &locations.global_state_db_path;
+    let backup_root = rename_journal::create_backup_dir("rename")?;
 
     if old_projects_dir.exists() {
         let backup_projects = backup_root.join("old_projects");
-        utils::copy_dir(old_projects_dir, &backup_projects)?;
+        utils::copy_dir(&old_projects_dir, &backup_projects)?;
         println!("  Backup projects: {}", backup_projects.display());
     }
 
     if old_workspace_dir.exists() {
         let backup_workspace = backup_root.join("old_workspace");
-        utils::copy_dir(old_workspace_dir, &backup_workspace)?;
+        utils::copy_dir(&old_workspace_dir, &backup_workspace)?;
         println!("  Backup workspaceStorage: {}", backup_workspace.display());
     }
 
+    // Known up front only when the folder was already moved (relink)
+    if let Some(hash) = plan
+        .new_workspace_hash
+        .as_ref()
+        .filter(|_| plan.new_workspace_dir_existed)
+    {
+        let backup_workspace = backup_root.join("new_workspace");
+        utils::copy_dir(
+            &locations.workspace_storage_dir.join(hash),
+            &backup_workspace,
+        )?;
+        println!(
+            "  Backup new path's workspaceStorage: {}",
+            backup_workspace.display()
+        );
+    }
+
     if storage_json_path.exists() {
         let target = backup_root.join("storage.json");
         fs::copy(storage_json_path, &target).with_context(|| {
@@ -644,21 +1400,79 @@ fn create_rename_backup(
         println!("  Backup storage.json: {}", target.display());
     }
 
-    if global_state_db_path.exists() {
-        let target = backup_root.join("state.vscdb");
-        fs::copy(global_state_db_path, &target).with_context(|| {
+    if let Some(snapshot) = shared_global_db {
+        safety_backups::share_global_db(&backup_root, snapshot)?;
+        println!("  Backup global state DB: {} (shared)", snapshot.display());
+    } else if global_state_db_path.exists() {
+        let (snapshot, reused) =
+            safety_backups::snapshot_global_db(&backup_root, global_state_db_path)?;
+        let note = if reused { " (unchanged, shared)" } else { "" };
+        println!("  Backup global state DB: {}{}", snapshot.display(), note);
+    }
+
+    for ws in &plan.multi_root {
+        let backup_workspace = backup_root.join("multi_root").join(&ws.hash);
+        utils::copy_dir(&ws.dir, &backup_workspace)?;
+        let target = backup_workspace.with_extension("code-workspace");
+        let source = if ws.config_path.exists() {
+            &ws.config_path
+        } else {
+            &ws.new_config_path
+        };
+        fs::copy(source, &target).with_context(|| {
             format!(
                 "Failed to backup {} to {}",
-                global_state_db_path.display(),
+                source.display(),
                 target.display()
             )
         })?;
-        println!("  Backup global state DB: {}", target.display());
+        println!(
+            "  Backup multi-root workspace: {}",
+            backup_workspace.display()
+        );
     }
 
     Ok(Some(backup_root))
 }
 
+/// Update global state DB references through `global_db` if given, or by
+/// opening the database at `global_state_db_path`
+#[allow(clippy::too_many_arguments)]
+fn update_global_refs(
+    global_db: Option<&Connection>,
+    global_state_db_path: &Path,
+    old_path: &str,
+    new_path: &str,
+    old_uri: &str,
+    new_uri: &str,
+    old_workspace_hash: &str,
+    new_workspace_hash: &str,
+    dry_run: bool,
+) -> Result<bool> {
+    match global_db {
+        Some(conn) => storage::update_global_state_refs(
+            conn,
+            old_path,
+            new_path,
+            old_uri,
+            new_uri,
+            old_workspace_hash,
+            new_workspace_hash,
+            dry_run,
+        ),
+        None => storage::update_global_state_db(
+            global_state_db_path,
+            old_path,
+            new_path,
+            old_uri,
+            new_uri,
+            old_workspace_hash,
+            new_workspace_hash,
+            dry_run,
+        ),
+    }
+}
+
 /// Clear stale cache files or directories.
 fn clear_path(path: &Path, dry_run: bool) -> Result<()> {
     if !path.exists() {
@@ -723,7 +1537,7 @@ fn find_existing_workspace(path: &Path) -> Result<Option<(String, String)>> {
 
 /// Normalize an existing path - make absolute and resolve . and .. but NOT symlinks
 /// Cursor uses paths as-is (without symlink resolution)
-fn normalize_path(path: &str) -> Result<PathBuf> {
+pub fn normalize_path(path: &str) -> Result<PathBuf> {
     let path = PathBuf::from(path);
 
     if !path.exists() {
@@ -764,7 +1578,7 @@ fn normalize_new_path(path: &str) -> Result<PathBuf> {
 }
 
 /// Clean a path by resolving . and .. components without following symlinks
-fn clean_path(path: &Path) -> PathBuf {
+pub fn clean_path(path: &Path) -> PathBuf {
     use std::path::Component;
 
     let mut result = PathBuf::new();
@@ -781,7 +1595,7 @@ fn clean_path(path: &Path) -> PathBuf {
 }
 
 /// Check if Cursor is running
-fn is_cursor_running() -> bool {
+pub fn is_cursor_running() -> bool {
     #[cfg(target_os = "macos")]
     {
         Command::new("pgrep")
@@ -811,7 +1625,7 @@ fn is_cursor_running() -> bool {
 }
 
 /// Print whether a path exists
-fn print_exists_status(label: &str, path: &Path) {
+pub fn print_exists_status(label: &str, path: &Path) {
     if path.exists() {
         println!("{} {}: {}", "Found:".green(), label, path.display());
     } else {
@@ -820,12 +1634,22 @@ fn print_exists_status(label: &str, path: &Path) {
 }
 
 /// Copy or move a directory, with optional merge if target exists
-fn copy_or_move(src: &Path, dst: &Path, copy_mode: bool, dry_run: bool) -> Result<()> {
+///
+/// Merging asks for confirmation unless `merge_confirmed`.
+fn copy_or_move(
+    src: &Path,
+    dst: &Path,
+    copy_mode: bool,
+    merge_confirmed: bool,
+    dry_run: bool,
+) -> Result<()> {
     let merge = dst.exists();
     if merge {
         println!("  {} {}", "Target exists:".yellow(), dst.display());
 
-        if dry_run {
+        if merge_confirmed {
+            println!("  Merging into existing directory");
+        } else if dry_run {
             println!(
                 "  {} Would prompt for merge confirmation",
                 "[DRY-RUN]".blue()
@@ -948,6 +1772,221 @@ mod tests {
         assert!(uri.contains("Users"));
     }
 
+    #[test]
+    fn test_roll_back_restores_moved_project_and_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let locations = Locations {
+            projects_dir: root.join("projects"),
+            workspace_storage_dir: root.join("workspaceStorage"),
+            storage_json_path: root.join("storage.json"),
+            global_state_db_path: root.join("state.vscdb"),
+        };
+        let backup_dir = root.join("backup");
+
+        // State after Steps 1-6 of a move, with the pre-rename backup
+        fs::create_dir_all(root.join("new/project")).unwrap();
+        fs::write(root.join("new/project/main.rs"), "fn main() {}").unwrap();
+        fs::create_dir_all(locations.workspace_storage_dir.join("hash_new")).unwrap();
+        fs::write(
+            locations
+                .workspace_storage_dir
+                .join("hash_new/workspace.json"),
+            "new",
+        )
+        .unwrap();
+        fs::write(&locations.storage_json_path, "new").unwrap();
+        fs::create_dir_all(backup_dir.join("old_workspace")).unwrap();
+        fs::write(backup_dir.join("old_workspace/workspace.json"), "old").unwrap();
+        fs::write(backup_dir.join("storage.json"), "old").unwrap();
+
+        let plan = RenamePlan {
+            old_path: root.join("old/project"),
+            new_path: root.join("new/project"),
+            copy_mode: false,
+            force_index: false,
+            old_folder_id: "old-project".to_string(),
+            new_folder_id: "new-project".to_string(),
+            old_workspace_hash: "hash_old".to_string(),
+            new_workspace_hash: Some("hash_new".to_string()),
+            new_projects_dir_existed: false,
+            new_workspace_dir_existed: false,
+            merge_existing: false,
+            folder_moved: false,
+            multi_root: Vec::new(),
+        };
+        fs::create_dir_all(root.join("old")).unwrap();
+        let mut journal = RenameJournal::new(Operation::Rename, plan, Some(backup_dir)).unwrap();
+        for step in &RenameStep::ALL[..6] {
+            journal.start(*step).unwrap();
+            journal.finish(*step).unwrap();
+        }
+        journal.start(RenameStep::GlobalStateDb).unwrap();
+
+        roll_back(&mut journal, &locations, true).unwrap();
+
+        assert_eq!(journal.status, JournalStatus::RolledBack);
+        assert!(!root.join("new/project").exists());
+        assert!(root.join("old/project/main.rs").exists());
+        assert!(!locations.workspace_storage_dir.join("hash_new").exists());
+        assert_eq!(
+            fs::read_to_string(
+                locations
+                    .workspace_storage_dir
+                    .join("hash_old/workspace.json")
+            )
+            .unwrap(),
+            "old"
+        );
+        assert_eq!(
+            fs::read_to_string(&locations.storage_json_path).unwrap(),
+            "old"
+        );
+        // No global DB existed before the rename, so there is nothing to restore
+        assert!(!locations.global_state_db_path.exists());
+    }
+
+    #[test]
+    fn test_roll_back_relink_restores_both_workspaces() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let locations = Locations {
+            projects_dir: root.join("projects"),
+            workspace_storage_dir: root.join("workspaceStorage"),
+            storage_json_path: root.join("storage.json"),
+            global_state_db_path: root.join("state.vscdb"),
+        };
+        let backup_dir = root.join("backup");
+
+        // The folder was moved by hand and Cursor already opened it there
+        let new_path = root.join("new/project");
+        fs::create_dir_all(&new_path).unwrap();
+        fs::write(new_path.join("main.rs"), "fn main() {}").unwrap();
+        let new_hash = workspace::compute_workspace_hash(&new_path).unwrap();
+        let write_workspace = |dir: &Path, content: &str| {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join("workspace.json"), content).unwrap();
+        };
+        write_workspace(&locations.workspace_storage_dir.join("hash_old"), "old");
+        write_workspace(&locations.workspace_storage_dir.join(&new_hash), "fresh");
+        write_workspace(&backup_dir.join("old_workspace"), "old");
+        write_workspace(&backup_dir.join("new_workspace"), "fresh");
+
+        let plan = RenamePlan {
+            old_path: root.join("old/project"),
+            new_path: new_path.clone(),
+            copy_mode: false,
+            force_index: false,
+            old_folder_id: "old-project".to_string(),
+            new_folder_id: "new-project".to_string(),
+            old_workspace_hash: "hash_old".to_string(),
+            new_workspace_hash: Some(new_hash.clone()),
+            new_projects_dir_existed: false,
+            new_workspace_dir_existed: true,
+            merge_existing: false,
+            folder_moved: true,
+            multi_root: Vec::new(),
+        };
+        let mut journal = RenameJournal::new(Operation::Rename, plan, Some(backup_dir)).unwrap();
+        for step in &RenameStep::ALL[..3] {
+            journal.start(*step).unwrap();
+            run_step(*step, &mut journal.plan, &locations, None, false).unwrap();
+            journal.finish(*step).unwrap();
+        }
+
+        // Step 1 left the folder alone and Step 3 replaced Cursor's fresh workspace
+        assert!(new_path.join("main.rs").exists());
+        assert!(!locations.workspace_storage_dir.join("hash_old").exists());
+        let workspace_json = |hash: &str| {
+            fs::read_to_string(
+                locations
+                    .workspace_storage_dir
+                    .join(hash)
+                    .join("workspace.json"),
+            )
+            .unwrap()
+        };
+        assert_eq!(workspace_json(&new_hash), "old");
+
+        roll_back(&mut journal, &locations, true).unwrap();
+
+        assert_eq!(journal.status, JournalStatus::RolledBack);
+        // The folder was never moved by the tool, so it stays where it is
+        assert!(new_path.join("main.rs").exists());
+        assert!(!root.join("old/project").exists());
+        assert_eq!(workspace_json("hash_old"), "old");
+        assert_eq!(workspace_json(&new_hash), "fresh");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_find_multi_root_workspaces() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let storage_dir = root.join("workspaceStorage");
+        let config_path = root.join("mono/mono.code-workspace");
+        fs::create_dir_all(root.join("mono")).unwrap();
+        fs::write(
+            &config_path,
+            r#"{"folders": [{"path": "api"}, {"path": "../shared"}]}"#,
+        )
+        .unwrap();
+
+        for (hash, key, uri) in [
+            (
+                "multi",
+                "workspace",
+                path_to_file_uri(&config_path).unwrap(),
+            ),
+            (
+                "single",
+                "folder",
+                path_to_file_uri(&root.join("mono/api")).unwrap(),
+            ),
+        ] {
+            fs::create_dir_all(storage_dir.join(hash)).unwrap();
+            fs::write(
+                storage_dir.join(hash).join("workspace.json"),
+                serde_json::json!({ key: uri }).to_string(),
+            )
+            .unwrap();
+        }
+
+        // A member folder moves: the file is rewritten, the hash is kept
+        let found =
+            find_multi_root_workspaces(&storage_dir, &root.join("shared"), &root.join("common"))
+                .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].hash, "multi");
+        assert_eq!(found[0].new_hash, "multi");
+        assert!(found[0]
+            .content
+            .as_ref()
+            .unwrap()
+            .contains(r#""../common""#));
+
+        // The directory holding the file moves: relative entries still resolve
+        let found =
+            find_multi_root_workspaces(&storage_dir, &root.join("mono"), &root.join("mono2"))
+                .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].new_config_path,
+            root.join("mono2/mono.code-workspace")
+        );
+        assert_eq!(
+            found[0].new_hash,
+            workspace::compute_multi_root_hash(root.join("mono2/mono.code-workspace"))
+        );
+        assert_eq!(found[0].content, None);
+
+        assert!(
+            find_multi_root_workspaces(&storage_dir, &root.join("other"), &root.join("x"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_sync_workspace_composer_index_normalizes_when_all_composers_present() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,795 @@
+//! Batch rename - Move or copy many projects from a mapping file
+//!
+//! Each line of the mapping file is either an `OLD<TAB>NEW` pair or an
+//! `OLD_ROOT=NEW_ROOT` prefix rewrite, which applies to every known Cursor
+//! project under `OLD_ROOT`. All pairs are validated before anything is
+//! touched, and the renames then run one after another against a single
+//! connection to the global `state.vscdb`. Projects nested inside another
+//! project of the same prefix rewrite move along with the outer folder, and
+//! are then relinked.
+//!
+//! Every rename keeps its own journal, tagged with the batch ID. Their safety
+//! backups are all taken up front and share one global DB snapshot, so a
+//! batch fails, resumes, rolls back and is undone as a whole.
+
+use anyhow::{bail, Context, Result};
+use owo_colors::OwoColorize;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use super::rename::{self, Locations, RenamePlan};
+use super::rename_journal::{self, JournalStatus, Operation, RenameJournal};
+use super::safety_backups;
+use crate::cursor::workspace::WorkspaceJson;
+
+/// One entry of a mapping file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MappingRule {
+    /// Rename a single project
+    Pair { old: PathBuf, new: PathBuf },
+    /// Rename every known project under `old` to the same place under `new`
+    Prefix { old: PathBuf, new: PathBuf },
+}
+
+/// Parse a mapping file
+///
+/// Blank lines and lines starting with `#` are skipped. Relative paths are
+/// resolved against the current directory and a leading `~` against the
+/// home directory.
+pub fn parse_mapping(content: &str) -> Result<Vec<MappingRule>> {
+    let mut rules = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let (separator, is_pair) = if line.contains('\t') {
+            ('\t', true)
+        } else if line.contains('=') {
+            ('=', false)
+        } else {
+            bail!(
+                "Line {}: expected OLD<TAB>NEW or OLD_ROOT=NEW_ROOT: {}",
+                index + 1,
+                line
+            );
+        };
+
+        let (old, new) = line.split_once(separator).expect("separator is present");
+        let (old, new) = (old.trim(), new.trim());
+        if old.is_empty() || new.is_empty() {
+            bail!("Line {}: missing path: {}", index + 1, line);
+        }
+
+        let (old, new) = (resolve_path(old)?, resolve_path(new)?);
+        rules.push(if is_pair {
+            MappingRule::Pair { old, new }
+        } else {
+            MappingRule::Prefix { old, new }
+        });
+    }
+
+    if rules.is_empty() {
+        bail!("Mapping file has no entries");
+    }
+    Ok(rules)
+}
+
+/// Make a mapping file path absolute, expanding `~` and cleaning `.`/`..`
+fn resolve_path(path: &str) -> Result<PathBuf> {
+    let path = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = dirs::home_dir().context("Could not determine home directory")?;
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    };
+
+    let path = if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    Ok(rename::clean_path(&path))
+}
+
+/// An old -> new pair of a batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BatchPair {
+    old: PathBuf,
+    new: PathBuf,
+    /// Old path of the outermost project of the same prefix rewrite that
+    /// contains this one; moving that folder carries this one along
+    moved_with: Option<PathBuf>,
+}
+
+impl BatchPair {
+    /// The pair whose folder move brings this project to its new path
+    fn moving_root(&self) -> &Path {
+        self.moved_with.as_deref().unwrap_or(&self.old)
+    }
+}
+
+/// Local project folders Cursor has a workspace for and that still exist
+fn known_projects(workspace_storage_dir: &Path) -> Vec<PathBuf> {
+    let mut projects: Vec<PathBuf> = fs::read_dir(workspace_storage_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| WorkspaceJson::read(entry.path().join("workspace.json")).ok())
+        .filter_map(|ws| Url::parse(&ws.folder).ok())
+        .filter(|url| url.scheme() == "file")
+        .filter_map(|url| url.to_file_path().ok())
+        .filter(|path| path.is_dir())
+        .collect();
+    projects.sort();
+    projects.dedup();
+    projects
+}
+
+/// Turn mapping rules into old -> new pairs, expanding prefix rewrites
+///
+/// `projects` is sorted, so a prefix rewrite lists an outer project before
+/// the projects nested inside it.
+fn expand_rules(
+    rules: &[MappingRule],
+    projects: &[PathBuf],
+    problems: &mut Vec<String>,
+) -> Vec<BatchPair> {
+    let mut pairs = Vec::new();
+
+    for rule in rules {
+        match rule {
+            MappingRule::Pair { old, new } => pairs.push(BatchPair {
+                old: old.clone(),
+                new: new.clone(),
+                moved_with: None,
+            }),
+            MappingRule::Prefix { old, new } => {
+                let matched: Vec<&PathBuf> = projects
+                    .iter()
+                    .filter(|project| project.starts_with(old))
+                    .collect();
+                if matched.is_empty() {
+                    problems.push(format!("No Cursor projects under {}", old.display()));
+                }
+
+                for project in &matched {
+                    let rest = project.strip_prefix(old).expect("project is under old");
+                    // `join("")` would leave a trailing separator
+                    let new_path = if rest.as_os_str().is_empty() {
+                        new.clone()
+                    } else {
+                        new.join(rest)
+                    };
+                    let moved_with = matched
+                        .iter()
+                        .find(|outer| outer != &project && project.starts_with(outer))
+                        .map(|outer| outer.to_path_buf());
+                    pairs.push(BatchPair {
+                        old: project.to_path_buf(),
+                        new: new_path,
+                        moved_with,
+                    });
+                }
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Pairs that cannot be renamed together
+///
+/// A folder may appear in only one pair, and no old or new path may be
+/// inside another pair's: those renames depend on each other's order. The
+/// exception is projects a single folder move carries along.
+fn find_overlaps(pairs: &[BatchPair]) -> Vec<String> {
+    let nested = |a: &Path, b: &Path| a.starts_with(b) || b.starts_with(a);
+    let mut problems = Vec::new();
+
+    for (i, pair_a) in pairs.iter().enumerate() {
+        for pair_b in &pairs[i + 1..] {
+            let (old_a, new_a, old_b, new_b) = (&pair_a.old, &pair_a.new, &pair_b.old, &pair_b.new);
+            if old_a == old_b {
+                problems.push(format!("{} is listed more than once", old_a.display()));
+            } else if new_a == new_b {
+                problems.push(format!(
+                    "{} and {} would both move to {}",
+                    old_a.display(),
+                    old_b.display(),
+                    new_a.display()
+                ));
+            } else if pair_a.moving_root() == pair_b.moving_root() {
+                // Moved together by the outermost folder's rename
+            } else if let Some((a, b)) = [
+                (old_a, old_b),
+                (new_a, new_b),
+                (old_a, new_b),
+                (new_a, old_b),
+            ]
+            .into_iter()
+            .find(|(a, b)| nested(a, b))
+            {
+                problems.push(format!(
+                    "{} overlaps {}; rename nested or chained folders in separate runs",
+                    a.display(),
+                    b.display()
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// Problems with a single pair on disk
+fn check_pair(pair: &BatchPair) -> Option<String> {
+    let (old, new) = (&pair.old, &pair.new);
+    let problem = if !old.is_dir() {
+        "old path does not exist".to_string()
+    } else if new.exists() {
+        "new path already exists".to_string()
+    } else if new.starts_with(old) {
+        "new path is inside the old one".to_string()
+    } else if pair.moved_with.is_some() {
+        // Its parent appears once the outer folder has moved
+        return None;
+    } else {
+        match new.parent() {
+            Some(parent) if parent.is_dir() => return None,
+            Some(parent) => format!("parent directory does not exist: {}", parent.display()),
+            None => "new path has no parent directory".to_string(),
+        }
+    };
+    Some(format!(
+        "{} -> {}: {}",
+        old.display(),
+        new.display(),
+        problem
+    ))
+}
+
+/// Execute `rename --batch`
+pub fn execute(
+    mapping_file: &str,
+    dry_run: bool,
+    copy_mode: bool,
+    force_index: bool,
+) -> Result<()> {
+    let content = fs::read_to_string(mapping_file)
+        .with_context(|| format!("Failed to read mapping file: {}", mapping_file))?;
+    let rules = parse_mapping(&content)?;
+    let locations = Locations::from_config()?;
+
+    let plans = plan_batch(&rules, copy_mode, force_index, &locations)?;
+    if !dry_run && rename::is_cursor_running() {
+        bail!("Cursor is running. Please close it completely before running this script.");
+    }
+
+    let mode = if copy_mode { "COPY" } else { "MOVE" };
+    print_plan(&plans, &locations, mode);
+
+    if dry_run {
+        println!("This was a dry-run. No changes were made.");
+        println!("Run without --dry-run to apply changes.");
+        return Ok(());
+    }
+
+    if !rename::confirm(&format!(
+        "Proceed with {} of {} project(s)?",
+        mode.to_lowercase(),
+        plans.len()
+    ))? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    println!("{}", "Step 0: Creating safety backups...".green());
+    let mut journals = create_journals(plans, &locations)?;
+
+    run_batch(&mut journals, &locations)?;
+
+    println!();
+    println!("{}", format!("=== Batch {} complete! ===", mode).green());
+    println!();
+    for journal in &journals {
+        println!(
+            "  {} -> {}",
+            journal.plan.old_path.display(),
+            journal.plan.new_path.display()
+        );
+    }
+    println!();
+    println!("Your chat history and workspace settings should be preserved.");
+
+    let backups: Vec<PathBuf> = journals
+        .iter()
+        .filter_map(|j| j.backup_dir.clone())
+        .collect();
+    safety_backups::apply_retention_policy(&backups);
+    Ok(())
+}
+
+/// Validate every pair and plan its rename, before anything is touched
+fn plan_batch(
+    rules: &[MappingRule],
+    copy_mode: bool,
+    force_index: bool,
+    locations: &Locations,
+) -> Result<Vec<RenamePlan>> {
+    let mut problems = Vec::new();
+    let projects = known_projects(&locations.workspace_storage_dir);
+    let pairs = expand_rules(rules, &projects, &mut problems);
+    problems.extend(pairs.iter().filter_map(check_pair));
+    problems.extend(find_overlaps(&pairs));
+
+    let mut plans = Vec::new();
+    if problems.is_empty() {
+        for pair in pairs {
+            let mut plan =
+                rename::prepare_plan(pair.old, pair.new, copy_mode, force_index, locations)?;
+            plan.merge_existing = true;
+            // Relinked once the outer folder's rename has moved it
+            plan.folder_moved = pair.moved_with.is_some();
+            plans.push(plan);
+        }
+        problems.extend(find_shared_multi_root(&plans));
+    }
+
+    if !problems.is_empty() {
+        eprintln!("{}", "Mapping file has problems:".red());
+        for problem in &problems {
+            eprintln!("  - {}", problem);
+        }
+        bail!("No changes made ({} problem(s))", problems.len());
+    }
+    Ok(plans)
+}
+
+/// Multi-root workspaces that include folders from more than one pair
+///
+/// Their `.code-workspace` rewrite is planned per pair, so each would undo
+/// the other's changes.
+fn find_shared_multi_root(plans: &[RenamePlan]) -> Vec<String> {
+    let mut seen: HashMap<&str, &RenamePlan> = HashMap::new();
+    let mut problems = Vec::new();
+
+    for plan in plans {
+        for ws in &plan.multi_root {
+            if let Some(other) = seen.insert(&ws.hash, plan) {
+                problems.push(format!(
+                    "Multi-root workspace {} includes both {} and {}; rename them in separate runs",
+                    ws.config_path.display(),
+                    other.old_path.display(),
+                    plan.old_path.display()
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// Print the combined plan
+fn print_plan(plans: &[RenamePlan], locations: &Locations, mode: &str) {
+    println!();
+    println!(
+        "{}",
+        format!("=== Cursor Project Batch {} ===", mode).green()
+    );
+    println!();
+
+    for (i, plan) in plans.iter().enumerate() {
+        println!(
+            "{:>3}. {} -> {}",
+            i + 1,
+            plan.old_path.display(),
+            plan.new_path.display()
+        );
+
+        if plan.folder_moved {
+            println!("     Moves along with its enclosing project");
+        }
+
+        let mut found = Vec::new();
+        if locations
+            .workspace_storage_dir
+            .join(&plan.old_workspace_hash)
+            .exists()
+        {
+            found.push(format!("workspace {}", plan.old_workspace_hash));
+        }
+        if locations.projects_dir.join(&plan.old_folder_id).exists() {
+            found.push("projects data".to_string());
+        }
+        if !plan.multi_root.is_empty() {
+            found.push(format!("{} multi-root workspace(s)", plan.multi_root.len()));
+        }
+        if found.is_empty() {
+            println!("     {}", "No Cursor data found".yellow());
+        } else {
+            println!("     {}", found.join(", "));
+        }
+
+        if plan.new_projects_dir_existed {
+            println!(
+                "     {} into existing {}",
+                "Merges".yellow(),
+                locations.projects_dir.join(&plan.new_folder_id).display()
+            );
+        }
+    }
+
+    println!();
+}
+
+/// Take every safety backup before the first rename, sharing one global DB snapshot
+fn create_journals(plans: Vec<RenamePlan>, locations: &Locations) -> Result<Vec<RenameJournal>> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let batch = format!("batch-{timestamp}");
+
+    let mut journals: Vec<RenameJournal> = Vec::new();
+    let mut shared_global_db: Option<PathBuf> = None;
+
+    for plan in plans {
+        let result = (|| -> Result<RenameJournal> {
+            let backup_dir =
+                rename::create_rename_backup(&plan, locations, shared_global_db.as_deref(), false)?
+                    .context("Safety backup was not created")?;
+            if shared_global_db.is_none() {
+                shared_global_db = safety_backups::global_db_snapshot(&backup_dir);
+            }
+            let mut journal = RenameJournal::new(Operation::Rename, plan, Some(backup_dir))?;
+            journal.batch = Some(batch.clone());
+            journal.save()?;
+            Ok(journal)
+        })();
+
+        match result {
+            Ok(journal) => journals.push(journal),
+            Err(err) => {
+                // Nothing has been renamed yet
+                for journal in &mut journals {
+                    journal.set_status(JournalStatus::RolledBack)?;
+                }
+                return Err(err.context("Failed to create safety backups; no changes made"));
+            }
+        }
+    }
+
+    println!("  Batch ID: {}", batch);
+    Ok(journals)
+}
+
+/// Run the unfinished renames of a batch, rolling back the whole batch on failure
+fn run_batch(journals: &mut [RenameJournal], locations: &Locations) -> Result<()> {
+    let Err(err) = run_pending(journals, locations) else {
+        return Ok(());
+    };
+
+    println!();
+    println!("{} {:#}", "Failed:".red(), err);
+    println!("{}", "Rolling back the whole batch...".yellow());
+
+    match roll_back_batch(journals, locations) {
+        Ok(()) => Err(err.context("Batch rename failed; all changes were rolled back")),
+        Err(rollback_err) => Err(err.context(format!(
+            "Batch rename failed and could not be rolled back ({:#}). \
+             Fix the problem, then run `cursor-helper rename --resume` or `--rollback`",
+            rollback_err
+        ))),
+    }
+}
+
+/// Run every rename not yet completed, sharing one global DB connection
+fn run_pending(journals: &mut [RenameJournal], locations: &Locations) -> Result<()> {
+    let global_db = if locations.global_state_db_path.exists() {
+        Some(
+            Connection::open(&locations.global_state_db_path).with_context(|| {
+                format!(
+                    "Failed to open global state DB: {}",
+                    locations.global_state_db_path.display()
+                )
+            })?,
+        )
+    } else {
+        None
+    };
+
+    let total = journals.len();
+    for (i, journal) in journals.iter_mut().enumerate() {
+        if journal.status != JournalStatus::InProgress {
+            continue;
+        }
+        println!();
+        println!(
+            "{}",
+            format!(
+                "[{}/{}] {} -> {}",
+                i + 1,
+                total,
+                journal.plan.old_path.display(),
+                journal.plan.new_path.display()
+            )
+            .bold()
+        );
+        rename::run_steps(journal, locations, global_db.as_ref(), false)?;
+    }
+
+    Ok(())
+}
+
+/// Roll back every rename of a batch, newest first
+fn roll_back_batch(journals: &mut [RenameJournal], locations: &Locations) -> Result<()> {
+    for journal in journals.iter_mut().rev() {
+        match journal.status {
+            // Never started: nothing to undo
+            JournalStatus::InProgress if journal.entries.is_empty() => {
+                journal.set_status(JournalStatus::RolledBack)?;
+            }
+            JournalStatus::InProgress | JournalStatus::Completed => {
+                println!(
+                    "  {} -> {}",
+                    journal.plan.new_path.display(),
+                    journal.plan.old_path.display()
+                );
+                rename::roll_back(journal, locations, true)?;
+                journal.set_status(JournalStatus::RolledBack)?;
+            }
+            JournalStatus::RolledBack | JournalStatus::Undone => {}
+        }
+    }
+    Ok(())
+}
+
+/// All journals of a batch, in execution order
+pub fn batch_journals(batch: &str) -> Result<Vec<RenameJournal>> {
+    Ok(
+        rename_journal::list_journals(&rename_journal::backups_dir()?)?
+            .into_iter()
+            .filter(|j| j.batch.as_deref() == Some(batch))
+            .collect(),
+    )
+}
+
+/// Print an interrupted batch and how far each rename got
+fn print_batch_summary(batch: &str, journals: &[RenameJournal]) {
+    println!();
+    println!("Interrupted batch {} ({} renames)", batch, journals.len());
+    for journal in journals {
+        let state = match journal.status {
+            JournalStatus::Completed => "done".green().to_string(),
+            JournalStatus::InProgress if !journal.entries.is_empty() => {
+                "interrupted".yellow().to_string()
+            }
+            JournalStatus::InProgress => "pending".to_string(),
+            status => status.label().to_string(),
+        };
+        println!(
+            "  {} -> {} ({})",
+            journal.plan.old_path.display(),
+            journal.plan.new_path.display(),
+            state
+        );
+    }
+    println!();
+}
+
+/// Resume an interrupted batch
+pub fn resume(batch: &str) -> Result<()> {
+    let mut journals = batch_journals(batch)?;
+    print_batch_summary(batch, &journals);
+    if !rename::confirm("Resume?")? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let locations = Locations::from_config()?;
+    run_batch(&mut journals, &locations)?;
+
+    println!();
+    println!("{}", "=== Batch complete! ===".green());
+    Ok(())
+}
+
+/// Roll back an interrupted batch
+pub fn rollback(batch: &str) -> Result<()> {
+    let mut journals = batch_journals(batch)?;
+    print_batch_summary(batch, &journals);
+    if !rename::confirm("Roll back?")? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let locations = Locations::from_config()?;
+    println!("{}", "Rolling back...".yellow());
+    roll_back_batch(&mut journals, &locations)?;
+
+    println!();
+    println!("{}", "=== Rollback complete! ===".green());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(old: &str, new: &str) -> BatchPair {
+        BatchPair {
+            old: PathBuf::from(old),
+            new: PathBuf::from(new),
+            moved_with: None,
+        }
+    }
+
+    fn nested_pair(old: &str, new: &str, moved_with: &str) -> BatchPair {
+        BatchPair {
+            moved_with: Some(PathBuf::from(moved_with)),
+            ..pair(old, new)
+        }
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_parse_mapping() {
+        let rules = parse_mapping(
+            "# reorganised ~/src\n\n/src/app\t/work/app\n/src/team = /work/team\n/src/a=b\t/src/c\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rules,
+            vec![
+                MappingRule::Pair {
+                    old: PathBuf::from("/src/app"),
+                    new: PathBuf::from("/work/app"),
+                },
+                MappingRule::Prefix {
+                    old: PathBuf::from("/src/team"),
+                    new: PathBuf::from("/work/team"),
+                },
+                // A tab wins over '=' inside a path
+                MappingRule::Pair {
+                    old: PathBuf::from("/src/a=b"),
+                    new: PathBuf::from("/src/c"),
+                },
+            ]
+        );
+
+        assert!(parse_mapping("/src/app /work/app\n").is_err());
+        assert!(parse_mapping("/src/app\t\n").is_err());
+        assert!(parse_mapping("# nothing\n").is_err());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_expand_rules_rewrites_prefixes() {
+        let projects = [
+            PathBuf::from("/src/team"),
+            PathBuf::from("/src/team/api"),
+            PathBuf::from("/src/team/web"),
+            PathBuf::from("/src/teamwork"),
+        ];
+        let rules = [
+            MappingRule::Prefix {
+                old: PathBuf::from("/src/team"),
+                new: PathBuf::from("/work/team"),
+            },
+            MappingRule::Prefix {
+                old: PathBuf::from("/src/gone"),
+                new: PathBuf::from("/work/gone"),
+            },
+        ];
+
+        let mut problems = Vec::new();
+        let pairs = expand_rules(&rules, &projects, &mut problems);
+        assert_eq!(
+            pairs,
+            vec![
+                pair("/src/team", "/work/team"),
+                nested_pair("/src/team/api", "/work/team/api", "/src/team"),
+                nested_pair("/src/team/web", "/work/team/web", "/src/team"),
+            ]
+        );
+        // Path equality ignores a trailing separator, the raw string does not
+        assert_eq!(pairs[0].new.as_os_str(), "/work/team");
+        assert_eq!(problems, vec!["No Cursor projects under /src/gone"]);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_find_overlaps() {
+        assert!(find_overlaps(&[pair("/src/a", "/work/a"), pair("/src/b", "/work/b")]).is_empty());
+
+        let problems = find_overlaps(&[
+            pair("/src/a", "/work/a"),
+            pair("/src/a", "/work/b"),
+            pair("/src/c", "/work/a"),
+            pair("/src/d", "/src/c/d"),
+        ]);
+        assert_eq!(
+            problems,
+            vec![
+                "/src/a is listed more than once",
+                "/src/a and /src/c would both move to /work/a",
+                "/src/c overlaps /src/c/d; rename nested or chained folders in separate runs",
+            ]
+        );
+
+        // Projects one prefix rewrite moves together are fine, unless
+        // another entry touches them too
+        let problems = find_overlaps(&[
+            pair("/src/team", "/work/team"),
+            nested_pair("/src/team/api", "/work/team/api", "/src/team"),
+            nested_pair("/src/team/api/v2", "/work/team/api/v2", "/src/team"),
+            pair("/src/team/web", "/work/web"),
+        ]);
+        assert_eq!(
+            problems,
+            vec![
+                "/src/team overlaps /src/team/web; rename nested or chained folders in separate runs",
+            ]
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_plan_batch_moves_nested_projects_with_their_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let locations = Locations {
+            projects_dir: root.join("projects"),
+            workspace_storage_dir: root.join("workspaceStorage"),
+            storage_json_path: root.join("storage.json"),
+            global_state_db_path: root.join("state.vscdb"),
+        };
+        let (team, api) = (root.join("src/team"), root.join("src/team/api"));
+        fs::create_dir_all(&api).unwrap();
+        fs::create_dir_all(root.join("work")).unwrap();
+        for (hash, folder) in [("hash-team", &team), ("hash-api", &api)] {
+            let ws_dir = locations.workspace_storage_dir.join(hash);
+            fs::create_dir_all(&ws_dir).unwrap();
+            WorkspaceJson::new(folder)
+                .unwrap()
+                .write(ws_dir.join("workspace.json"))
+                .unwrap();
+        }
+
+        let rules = [MappingRule::Prefix {
+            old: team.clone(),
+            new: root.join("work/team"),
+        }];
+        let plans = plan_batch(&rules, false, false, &locations).unwrap();
+
+        let summary: Vec<(&Path, &Path, bool)> = plans
+            .iter()
+            .map(|plan| (&*plan.old_path, &*plan.new_path, plan.folder_moved))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (&*team, &*root.join("work/team"), false),
+                (&*api, &*root.join("work/team/api"), true),
+            ]
+        );
+
+        // Listing the nested project separately still conflicts
+        let rules = [
+            rules[0].clone(),
+            MappingRule::Pair {
+                old: api.clone(),
+                new: root.join("work/api"),
+            },
+        ];
+        assert!(plan_batch(&rules, false, false, &locations).is_err());
+    }
+}
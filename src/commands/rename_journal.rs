@@ -0,0 +1,346 @@
+//! Rename journal
+//!
+//! Every rename writes a journal next to its safety backup. Each step is
+//! recorded when it starts and when it finishes, so a failed run can be rolled
+//! back in reverse order and an interrupted one (process killed, machine
+//! crashed) resumed or rolled back later with `rename --resume` / `--rollback`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::rename::RenamePlan;
+use crate::config;
+
+/// Journal file name inside a backup directory
+pub const JOURNAL_FILE: &str = "journal.json";
+
+/// Current journal format version
+const JOURNAL_VERSION: u32 = 1;
+
+/// Steps of a rename after the safety backup, in execution order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameStep {
+    ProjectFolder,
+    ProjectsData,
+    WorkspaceStorage,
+    WorkspaceJson,
+    ComposerIndex,
+    StorageJson,
+    GlobalStateDb,
+    MultiRoot,
+    ClearCache,
+}
+
+impl RenameStep {
+    pub const ALL: [RenameStep; 9] = [
+        RenameStep::ProjectFolder,
+        RenameStep::ProjectsData,
+        RenameStep::WorkspaceStorage,
+        RenameStep::WorkspaceJson,
+        RenameStep::ComposerIndex,
+        RenameStep::StorageJson,
+        RenameStep::GlobalStateDb,
+        RenameStep::MultiRoot,
+        RenameStep::ClearCache,
+    ];
+
+    /// Step number as printed in progress output (Step 0 is the backup)
+    pub fn number(self) -> usize {
+        Self::ALL.iter().position(|s| *s == self).unwrap_or(0) + 1
+    }
+
+    /// What the step migrates
+    pub fn description(self) -> &'static str {
+        match self {
+            RenameStep::ProjectFolder => "project folder",
+            RenameStep::ProjectsData => "cursor projects data",
+            RenameStep::WorkspaceStorage => "workspaceStorage",
+            RenameStep::WorkspaceJson => "workspace.json",
+            RenameStep::ComposerIndex => "composer index",
+            RenameStep::StorageJson => "globalStorage/storage.json",
+            RenameStep::GlobalStateDb => "globalStorage/state.vscdb",
+            RenameStep::MultiRoot => "multi-root workspaces",
+            RenameStep::ClearCache => "stale cache data",
+        }
+    }
+}
+
+/// Kind of operation a journal records
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    /// `rename` (a move, or a copy with `--copy`)
+    #[default]
+    Rename,
+    /// `clone`
+    Clone,
+}
+
+/// Outcome of a journaled operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalStatus {
+    InProgress,
+    Completed,
+    RolledBack,
+    /// Completed, then reverted with `undo`
+    Undone,
+}
+
+impl JournalStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            JournalStatus::InProgress => "interrupted",
+            JournalStatus::Completed => "completed",
+            JournalStatus::RolledBack => "rolled back",
+            JournalStatus::Undone => "undone",
+        }
+    }
+}
+
+/// A step that was started, and possibly finished
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub step: RenameStep,
+    pub done: bool,
+}
+
+/// Operation log of a single rename or clone
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenameJournal {
+    /// Version of the journal format
+    pub version: u32,
+    #[serde(default)]
+    pub operation: Operation,
+    /// When the operation started (RFC 3339)
+    pub started_at: String,
+    /// When the operation completed, was rolled back or was undone (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+    pub status: JournalStatus,
+    /// Batch the operation belongs to (`rename --batch`); a batch is
+    /// resumed, rolled back and undone as a whole
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<String>,
+    pub plan: RenamePlan,
+    /// Started steps, in order
+    pub entries: Vec<JournalEntry>,
+    /// Backup directory the journal lives in (None for dry runs, which are
+    /// never persisted)
+    #[serde(skip)]
+    pub backup_dir: Option<PathBuf>,
+}
+
+impl RenameJournal {
+    /// Start a journal for `plan`, saved in `backup_dir` if given
+    pub fn new(
+        operation: Operation,
+        plan: RenamePlan,
+        backup_dir: Option<PathBuf>,
+    ) -> Result<Self> {
+        let journal = Self {
+            version: JOURNAL_VERSION,
+            operation,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+            status: JournalStatus::InProgress,
+            batch: None,
+            plan,
+            entries: Vec::new(),
+            backup_dir,
+        };
+        journal.save()?;
+        Ok(journal)
+    }
+
+    /// Read the journal stored in `backup_dir`
+    pub fn read(backup_dir: &Path) -> Result<Self> {
+        let path = backup_dir.join(JOURNAL_FILE);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read: {}", path.display()))?;
+        let mut journal: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse: {}", path.display()))?;
+        journal.backup_dir = Some(backup_dir.to_path_buf());
+        Ok(journal)
+    }
+
+    /// Write the journal (atomically, so a crash never leaves it truncated)
+    pub fn save(&self) -> Result<()> {
+        let Some(backup_dir) = &self.backup_dir else {
+            return Ok(());
+        };
+
+        let path = backup_dir.join(JOURNAL_FILE);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write: {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("Failed to write: {}", path.display()))
+    }
+
+    /// Whether `step` finished
+    pub fn is_done(&self, step: RenameStep) -> bool {
+        self.entries.iter().any(|e| e.step == step && e.done)
+    }
+
+    /// Record that `step` is about to run
+    pub fn start(&mut self, step: RenameStep) -> Result<()> {
+        if !self.entries.iter().any(|e| e.step == step) {
+            self.entries.push(JournalEntry { step, done: false });
+        }
+        self.save()
+    }
+
+    /// Record that `step` finished
+    pub fn finish(&mut self, step: RenameStep) -> Result<()> {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.step == step) {
+            entry.done = true;
+        }
+        self.save()
+    }
+
+    /// Record the final outcome
+    pub fn set_status(&mut self, status: JournalStatus) -> Result<()> {
+        if matches!(
+            status,
+            JournalStatus::Completed | JournalStatus::RolledBack | JournalStatus::Undone
+        ) {
+            self.finished_at = Some(chrono::Utc::now().to_rfc3339());
+        }
+        self.status = status;
+        self.save()
+    }
+
+    /// ID of the operation (its backup directory name)
+    pub fn id(&self) -> String {
+        self.backup_dir
+            .as_ref()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Short description of the operation: move, copy or clone
+    pub fn kind(&self) -> &'static str {
+        match (self.operation, self.plan.copy_mode) {
+            (Operation::Clone, _) => "clone",
+            (Operation::Rename, true) => "copy",
+            (Operation::Rename, false) if self.plan.folder_moved => "relink",
+            (Operation::Rename, false) => "move",
+        }
+    }
+}
+
+/// Directory holding rename safety backups and their journals
+pub fn backups_dir() -> Result<PathBuf> {
+    Ok(config::cursor_helper_data_dir()?.join("rename-backups"))
+}
+
+/// Create a new, uniquely named backup directory for an operation
+pub fn create_backup_dir(prefix: &str) -> Result<PathBuf> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let backup_dir = backups_dir()?.join(format!("{prefix}-{timestamp}"));
+    fs::create_dir_all(&backup_dir).with_context(|| {
+        format!(
+            "Failed to create backup directory: {}",
+            backup_dir.display()
+        )
+    })?;
+    Ok(backup_dir)
+}
+
+/// Journals of all renames and clones, oldest first (unreadable journals are skipped)
+pub fn list_journals(backups_dir: &Path) -> Result<Vec<RenameJournal>> {
+    if !backups_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut journals: Vec<RenameJournal> = fs::read_dir(backups_dir)
+        .with_context(|| format!("Failed to read: {}", backups_dir.display()))?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().join(JOURNAL_FILE).exists())
+        .filter_map(|entry| RenameJournal::read(&entry.path()).ok())
+        .collect();
+    journals.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(journals)
+}
+
+/// The most recent rename that neither completed nor was rolled back
+pub fn find_interrupted(backups_dir: &Path) -> Result<Option<RenameJournal>> {
+    Ok(list_journals(backups_dir)?
+        .into_iter()
+        .rfind(|j| j.status == JournalStatus::InProgress))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn plan() -> RenamePlan {
+        RenamePlan {
+            old_path: PathBuf::from("/old/project"),
+            new_path: PathBuf::from("/new/project"),
+            copy_mode: false,
+            force_index: false,
+            old_folder_id: "old-project".to_string(),
+            new_folder_id: "new-project".to_string(),
+            old_workspace_hash: "hash_old".to_string(),
+            new_workspace_hash: None,
+            new_projects_dir_existed: false,
+            new_workspace_dir_existed: false,
+            merge_existing: false,
+            folder_moved: false,
+            multi_root: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_journal_records_steps_and_round_trips() {
+        let temp_dir = TempDir::new().unwrap();
+        let backup_dir = temp_dir.path().join("rename-1");
+        fs::create_dir_all(&backup_dir).unwrap();
+
+        let mut journal =
+            RenameJournal::new(Operation::Rename, plan(), Some(backup_dir.clone())).unwrap();
+        journal.start(RenameStep::ProjectFolder).unwrap();
+        journal.finish(RenameStep::ProjectFolder).unwrap();
+        journal.start(RenameStep::ProjectsData).unwrap();
+
+        let read = RenameJournal::read(&backup_dir).unwrap();
+        assert!(read.is_done(RenameStep::ProjectFolder));
+        assert!(!read.is_done(RenameStep::ProjectsData));
+        assert_eq!(read.entries.len(), 2);
+        assert_eq!(read.status, JournalStatus::InProgress);
+        assert_eq!(read.plan.new_path, PathBuf::from("/new/project"));
+        assert_eq!(RenameStep::ProjectsData.number(), 2);
+    }
+
+    #[test]
+    fn test_find_interrupted_skips_finished_journals() {
+        let temp_dir = TempDir::new().unwrap();
+        for (name, status) in [
+            ("rename-1", JournalStatus::InProgress),
+            ("rename-2", JournalStatus::InProgress),
+            ("rename-3", JournalStatus::Completed),
+        ] {
+            let backup_dir = temp_dir.path().join(name);
+            fs::create_dir_all(&backup_dir).unwrap();
+            let mut journal =
+                RenameJournal::new(Operation::Rename, plan(), Some(backup_dir)).unwrap();
+            journal.started_at = format!("2026-10-16T00:00:0{}Z", &name[7..]);
+            journal.set_status(status).unwrap();
+        }
+
+        let interrupted = find_interrupted(temp_dir.path()).unwrap().unwrap();
+        assert!(interrupted.backup_dir.unwrap().ends_with("rename-2"));
+        assert!(find_interrupted(&temp_dir.path().join("missing"))
+            .unwrap()
+            .is_none());
+    }
+}
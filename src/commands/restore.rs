@@ -3,18 +3,46 @@
 use anyhow::{bail, Context, Result};
 use flate2::read::GzDecoder;
 use owo_colors::OwoColorize;
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension};
+use serde_json::Value;
+use std::collections::HashSet;
 use std::fs::{self, File};
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
 use tar::Archive;
+use url::Url;
+use uuid::Uuid;
 
-use super::backup::BackupManifest;
+use super::backup::{
+    profile_project_dir, BackupManifest, GlobalChatRow, ProfileManifest, WorkspaceKind,
+    CODE_WORKSPACE_FILE, GLOBAL_CHAT_FILE, MANIFEST_VERSION, PROFILE_MANIFEST_FILE,
+    STORAGE_JSON_FILE, UNLINKED_PROJECTS_DIR,
+};
+use super::rename;
 use super::utils;
 use crate::config;
-use crate::cursor::{folder_id, workspace};
+use crate::cursor::chat_sessions::GLOBAL_HEADERS_KEY;
+use crate::cursor::path_mapping::{PathMapping, PathTranslator};
+use crate::cursor::{folder_id, storage, workspace};
+
+/// Profile a workspace uses when it has no association
+const DEFAULT_PROFILE: &str = "__default__profile__";
+
+/// Manifest of a backup archive, by backup kind
+pub enum ArchiveManifest {
+    /// A single-project backup (`manifest.json`)
+    Project(BackupManifest),
+    /// A full-profile backup (`profile.json`)
+    Profile(ProfileManifest),
+}
 
 /// Execute the restore command
-pub fn execute(backup_file: &str, new_path: &str) -> Result<()> {
+///
+/// `mappings` (`OLD=NEW` prefixes) rewrite embedded paths outside the project,
+/// which itself always moves to `new_path`.
+pub fn execute(backup_file: &str, new_path: &str, mappings: &[String]) -> Result<()> {
+    let mappings = parse_mappings(mappings)?;
     let backup_path = PathBuf::from(backup_file);
     let new_path = PathBuf::from(new_path);
 
@@ -31,6 +59,12 @@ pub fn execute(backup_file: &str, new_path: &str) -> Result<()> {
 
     // Read and parse manifest from archive
     let manifest = read_manifest(&backup_path)?;
+    check_version(manifest.version)?;
+
+    // storage.json and the global state DB are kept open by Cursor
+    if rename::is_cursor_running() {
+        bail!("Cursor is running. Please close it completely before running this script.");
+    }
 
     println!("Restoring from backup:");
     println!("  Original path: {}", manifest.project_path);
@@ -38,20 +72,293 @@ pub fn execute(backup_file: &str, new_path: &str) -> Result<()> {
     println!("  Backup version: {}", manifest.version);
     println!();
 
-    // Compute new identifiers
     // For restore, we need the new path to exist first to compute the hash
     // We'll create it if it doesn't exist
+    let original_exists = Path::new(&manifest.project_path).exists();
     if !new_path.exists() {
         fs::create_dir_all(&new_path)
             .with_context(|| format!("Failed to create: {}", new_path.display()))?;
         println!("{} {}", "Created:".green(), new_path.display());
     }
+    let new_path = rename::normalize_path(&new_path.to_string_lossy())?;
+    // Without the original project around, its global entries move to the
+    // restored one; otherwise the restore is a copy and leaves them alone
+    let reclaim = !original_exists || new_path == Path::new(&manifest.project_path);
+
+    // Extract archive
+    println!("Extracting backup...");
+    let temp_dir = unpack_archive(&backup_path)?;
+
+    restore_project(
+        temp_dir.path(),
+        &manifest,
+        Some(&new_path),
+        reclaim,
+        &mappings,
+    )?;
+
+    println!();
+    println!("{}", "Restore complete!".green());
+    println!("You can now open {} in Cursor.", new_path.display());
+
+    Ok(())
+}
+
+/// Execute `restore --all`: restore every project of a full-profile backup
+///
+/// Project paths are rewritten with `mappings` (`OLD=NEW` prefixes) and
+/// projects whose folder is missing on this machine are skipped. Remote
+/// workspaces are restored under their original identity.
+pub fn execute_all(backup_file: &str, mappings: &[String]) -> Result<()> {
+    let mappings = parse_mappings(mappings)?;
+    let translator = PathTranslator::new(&mappings)?;
+    let backup_path = PathBuf::from(backup_file);
+
+    if !backup_path.exists() {
+        bail!("Backup file does not exist: {}", backup_path.display());
+    }
+
+    let profile = match read_archive_manifest(&backup_path)? {
+        ArchiveManifest::Profile(profile) => profile,
+        ArchiveManifest::Project(_) => bail!(
+            "{} is a single-project backup; use `restore <BACKUP_FILE> <NEW_PATH>`",
+            backup_path.display()
+        ),
+    };
+    check_version(profile.version)?;
+
+    if rename::is_cursor_running() {
+        bail!("Cursor is running. Please close it completely before running this script.");
+    }
+
+    println!("Restoring full-profile backup:");
+    println!("  Projects: {}", profile.projects.len());
+    println!("  Backup version: {}", profile.version);
+    for mapping in &mappings {
+        println!("  Mapping: {} -> {}", mapping.from, mapping.to);
+    }
+    println!();
+
+    println!("Extracting backup...");
+    let temp_dir = unpack_archive(&backup_path)?;
+
+    let mut restored = 0;
+    let mut skipped = Vec::new();
+    for manifest in &profile.projects {
+        let root = temp_dir.path().join(profile_project_dir(manifest));
+        let archived_workspace = root.join(CODE_WORKSPACE_FILE);
+        let has_workspace_file =
+            manifest.kind == WorkspaceKind::MultiRoot && archived_workspace.exists();
+        let new_path = match manifest.kind {
+            WorkspaceKind::Remote => None,
+            WorkspaceKind::Folder | WorkspaceKind::MultiRoot => {
+                let mapped = translator
+                    .translate_path(&manifest.project_path)
+                    .unwrap_or_else(|| PathBuf::from(&manifest.project_path));
+                // A multi-root workspace file comes from the backup if missing
+                if !mapped.exists() && !has_workspace_file {
+                    skipped.push(mapped);
+                    continue;
+                }
+                Some(mapped)
+            }
+        };
+
+        println!();
+        println!("{}", format!("=== {} ===", manifest.project_path).green());
+        if let (true, Some(new_path)) = (has_workspace_file, &new_path) {
+            restore_code_workspace(&archived_workspace, new_path, &translator)?;
+        }
+        let new_path = new_path
+            .map(|path| rename::normalize_path(&path.to_string_lossy()))
+            .transpose()?;
+        let original = Path::new(&manifest.project_path);
+        let reclaim = match &new_path {
+            Some(new_path) => {
+                if new_path != original {
+                    println!("New path: {}", new_path.display());
+                }
+                !original.exists() || new_path == original
+            }
+            None => true,
+        };
+
+        restore_project(&root, manifest, new_path.as_deref(), reclaim, &mappings)
+            .with_context(|| format!("Failed to restore {}", manifest.project_path))?;
+        restored += 1;
+    }
+
+    if !profile.unlinked_projects_data.is_empty() {
+        println!();
+        println!("Restoring projects/ data without a workspace...");
+        let kept = restore_unlinked_projects_data(
+            &temp_dir.path().join(UNLINKED_PROJECTS_DIR),
+            &profile.unlinked_projects_data,
+            &config::cursor_projects_dir()?,
+            &|text| translator.rewrite(text),
+        )?;
+        for path in &kept {
+            println!("  {} Kept existing {}", "Note:".yellow(), path.display());
+        }
+        println!(
+            "  -> {} of {} restored",
+            profile.unlinked_projects_data.len() - kept.len(),
+            profile.unlinked_projects_data.len()
+        );
+    }
+
+    let archived_storage_json = temp_dir.path().join(STORAGE_JSON_FILE);
+    if archived_storage_json.exists() {
+        println!();
+        println!("Merging workspace profile associations into storage.json...");
+        let storage_json = config::global_storage_dir()?.join("storage.json");
+        let added = merge_profile_associations(&archived_storage_json, &storage_json, |uri| {
+            translator.rewrite(uri)
+        })?;
+        println!("  -> {} association(s) added", added);
+    }
+
+    println!();
+    if !skipped.is_empty() {
+        println!(
+            "{} {} project(s) skipped because their folder does not exist here:",
+            "Note:".yellow(),
+            skipped.len()
+        );
+        for path in &skipped {
+            println!("  {}", path.display());
+        }
+        println!("Copy them over (or add a --map) and run `restore --all` again.");
+        println!();
+    }
+    println!(
+        "{} Restored {} of {} projects.",
+        "Restore complete!".green(),
+        restored,
+        profile.projects.len()
+    );
+
+    Ok(())
+}
+
+/// Copy `~/.cursor/projects` directories no restored project owns back from
+/// `extracted`, rewriting paths in their text files
+///
+/// Directories that already exist are left alone and returned.
+fn restore_unlinked_projects_data(
+    extracted: &Path,
+    folder_ids: &[String],
+    cursor_projects_dir: &Path,
+    rewrite: &dyn Fn(&str) -> String,
+) -> Result<Vec<PathBuf>> {
+    let mut kept = Vec::new();
+    for id in folder_ids {
+        let (source, target) = (extracted.join(id), cursor_projects_dir.join(id));
+        if !source.exists() {
+            continue;
+        }
+        if target.exists() {
+            kept.push(target);
+            continue;
+        }
+        fs::create_dir_all(cursor_projects_dir)?;
+        fs::rename(&source, &target).or_else(|_| utils::copy_dir(&source, &target))?;
+        rewrite_text_files(&target, rewrite)?;
+    }
+    Ok(kept)
+}
+
+/// Write a multi-root workspace's `.code-workspace` file to `target` with its
+/// folder paths and URIs rewritten by `translator`
+///
+/// A file already at `target` (copied over by hand) is kept and only has its
+/// paths rewritten. Relative folder paths are left as they are.
+fn restore_code_workspace(
+    archived: &Path,
+    target: &Path,
+    translator: &PathTranslator,
+) -> Result<()> {
+    let existing = target.exists();
+    let source = if existing { target } else { archived };
+    let content = fs::read_to_string(source)
+        .with_context(|| format!("Failed to read: {}", source.display()))?;
+    let rewritten = translator.rewrite(&content);
+
+    if existing && rewritten == content {
+        return Ok(());
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create: {}", parent.display()))?;
+    }
+    fs::write(target, &rewritten)
+        .with_context(|| format!("Failed to write: {}", target.display()))?;
+    if existing {
+        println!("Rewrote folder paths in: {}", target.display());
+    } else {
+        println!("{} {}", "Created:".green(), target.display());
+    }
+    Ok(())
+}
+
+fn parse_mappings(specs: &[String]) -> Result<Vec<PathMapping>> {
+    specs.iter().map(|spec| PathMapping::parse(spec)).collect()
+}
+
+/// Refuse backups written by a newer cursor-helper
+fn check_version(version: u32) -> Result<()> {
+    if version > MANIFEST_VERSION {
+        bail!(
+            "Backup format version {} is newer than this cursor-helper supports ({})",
+            version,
+            MANIFEST_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// Restore one project of a backup unpacked at `root`
+///
+/// `new_path` is the project's new location (the `.code-workspace` file of a
+/// multi-root workspace). Remote workspaces keep their identity and take None;
+/// their data is restored without path rewrites. Embedded paths are rewritten
+/// with `mappings` and the move of the project itself, in any OS's form.
+fn restore_project(
+    root: &Path,
+    manifest: &BackupManifest,
+    new_path: Option<&Path>,
+    reclaim: bool,
+    mappings: &[PathMapping],
+) -> Result<()> {
+    let translator = match new_path {
+        Some(new_path) => {
+            let mut mappings = mappings.to_vec();
+            mappings.push(PathMapping {
+                from: manifest.project_path.clone(),
+                to: new_path.to_string_lossy().to_string(),
+            });
+            PathTranslator::new(&mappings)?
+        }
+        None => PathTranslator::new(&[])?,
+    };
 
-    let new_folder_id = folder_id::path_to_folder_id(&new_path);
-    let new_workspace_hash = workspace::compute_workspace_hash(&new_path)?;
+    // Compute new identifiers
+    let (new_folder_id, new_workspace_hash) = match (manifest.kind, new_path) {
+        (WorkspaceKind::Folder, Some(new_path)) => (
+            folder_id::path_to_folder_id(new_path),
+            workspace::compute_workspace_hash(new_path)?,
+        ),
+        (WorkspaceKind::MultiRoot, Some(new_path)) => (
+            manifest.folder_id.clone(),
+            workspace::compute_multi_root_hash(new_path),
+        ),
+        _ => (manifest.folder_id.clone(), manifest.workspace_hash.clone()),
+    };
 
     println!("New identifiers:");
-    println!("  Folder ID: {}", new_folder_id);
+    if !new_folder_id.is_empty() {
+        println!("  Folder ID: {}", new_folder_id);
+    }
     println!("  Workspace hash: {}", new_workspace_hash);
     println!();
 
@@ -63,7 +370,7 @@ pub fn execute(backup_file: &str, new_path: &str) -> Result<()> {
     let new_workspace_dir = workspace_storage_dir.join(&new_workspace_hash);
 
     // Check for conflicts
-    if new_projects_dir.exists() {
+    if manifest.includes.projects_data && new_projects_dir.exists() {
         println!(
             "{} projects/ already exists: {}",
             "Warning:".yellow(),
@@ -78,24 +385,9 @@ pub fn execute(backup_file: &str, new_path: &str) -> Result<()> {
         );
     }
 
-    // Extract archive
-    println!("Extracting backup...");
-
-    let file = File::open(&backup_path)
-        .with_context(|| format!("Failed to open: {}", backup_path.display()))?;
-    let decoder = GzDecoder::new(file);
-    let mut archive = Archive::new(decoder);
-
-    // Create temp directory for extraction
-    let temp_dir = tempfile::tempdir().context("Failed to create temp directory")?;
-
-    archive
-        .unpack(temp_dir.path())
-        .context("Failed to extract backup")?;
-
     // Move extracted content to correct locations
-    let extracted_workspace = temp_dir.path().join("workspaceStorage");
-    let extracted_projects = temp_dir.path().join("projects");
+    let extracted_workspace = root.join("workspaceStorage");
+    let extracted_projects = root.join("projects");
 
     if extracted_workspace.exists() && manifest.includes.workspace_storage {
         println!("Restoring workspaceStorage/...");
@@ -118,9 +410,21 @@ pub fn execute(backup_file: &str, new_path: &str) -> Result<()> {
 
         // Update workspace.json with new path
         let workspace_json_path = new_workspace_dir.join("workspace.json");
-        if workspace_json_path.exists() {
-            let ws = workspace::WorkspaceJson::new(&new_path)?;
-            ws.write(&workspace_json_path)?;
+        if let (true, Some(new_path)) = (workspace_json_path.exists(), new_path) {
+            match manifest.kind {
+                WorkspaceKind::MultiRoot => {
+                    let uri = Url::from_file_path(new_path).map_err(|_| {
+                        anyhow::anyhow!("Failed to convert path to URL: {}", new_path.display())
+                    })?;
+                    let content = serde_json::to_string_pretty(
+                        &serde_json::json!({ "workspace": uri.to_string() }),
+                    )?;
+                    fs::write(&workspace_json_path, content).with_context(|| {
+                        format!("Failed to write: {}", workspace_json_path.display())
+                    })?;
+                }
+                _ => workspace::WorkspaceJson::new(new_path)?.write(&workspace_json_path)?,
+            }
             println!("  Updated workspace.json with new path");
         }
 
@@ -145,15 +449,483 @@ pub fn execute(backup_file: &str, new_path: &str) -> Result<()> {
         println!("  -> {}", new_projects_dir.display());
     }
 
-    println!();
-    println!("{}", "Restore complete!".green());
-    println!("You can now open {} in Cursor.", new_path.display());
+    let hash = [(
+        manifest.workspace_hash.as_str(),
+        new_workspace_hash.as_str(),
+    )];
+    let rewrite_refs =
+        |text: &str| storage::rewrite_workspace_refs(&translator.rewrite(text), &hash);
+
+    let workspace_db = new_workspace_dir.join("state.vscdb");
+    if !translator.is_empty() && workspace_db.exists() {
+        let rewritten = rewrite_database(&workspace_db, &rewrite_refs)?;
+        if rewritten > 0 {
+            println!("  Rewrote paths in {} workspace DB entries", rewritten);
+        }
+    }
+    if !translator.is_empty() && manifest.includes.projects_data && new_projects_dir.exists() {
+        let rewritten = rewrite_text_files(&new_projects_dir, &rewrite_refs)?;
+        if rewritten > 0 {
+            println!("  Rewrote paths in {} projects/ files", rewritten);
+        }
+    }
+
+    let extracted_chat = root.join(GLOBAL_CHAT_FILE);
+    if extracted_chat.exists() {
+        println!("Restoring chat history...");
+        let global_state_db = config::global_storage_dir()?.join("state.vscdb");
+        let (restored, remapped) = restore_global_chat(
+            &extracted_chat,
+            &global_state_db,
+            &rewrite_refs,
+            &manifest.workspace_hash,
+            &new_workspace_hash,
+            reclaim,
+        )?;
+        if remapped > 0 {
+            println!(
+                "  {} sessions already belong to another workspace and were given new IDs",
+                remapped
+            );
+        }
+        println!(
+            "  -> {} sessions in {}",
+            restored,
+            global_state_db.display()
+        );
+    }
+
+    if let Some(new_path) = new_path {
+        sync_metadata(
+            manifest,
+            new_path,
+            &new_workspace_dir,
+            &new_workspace_hash,
+            reclaim,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Rewrite every text value of a restored workspace DB, returning the number
+/// of changed entries
+fn rewrite_database(db_path: &Path, rewrite: &dyn Fn(&str) -> String) -> Result<usize> {
+    let mut conn = Connection::open(db_path)
+        .with_context(|| format!("Failed to open database: {}", db_path.display()))?;
+    let tx = conn.transaction()?;
+    let mut rewritten = 0;
+    for table in ["ItemTable", "cursorDiskKV"] {
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [table],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            continue;
+        }
+
+        let updates: Vec<(i64, String)> = {
+            let mut stmt = tx.prepare(&format!("SELECT rowid, value FROM {}", table))?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get_ref(1)?.as_str().ok().map(str::to_string),
+                ))
+            })?;
+            let mut updates = Vec::new();
+            for row in rows {
+                let (rowid, value) = row?;
+                let Some(value) = value else {
+                    continue;
+                };
+                let new_value = rewrite(&value);
+                if new_value != value {
+                    updates.push((rowid, new_value));
+                }
+            }
+            updates
+        };
+        for (rowid, value) in &updates {
+            tx.execute(
+                &format!("UPDATE {} SET value = ?1 WHERE rowid = ?2", table),
+                rusqlite::params![value, rowid],
+            )?;
+        }
+        rewritten += updates.len();
+    }
+    tx.commit()?;
+    Ok(rewritten)
+}
+
+/// Rewrite the UTF-8 text files under a directory, returning the number of
+/// changed files
+fn rewrite_text_files(dir: &Path, rewrite: &dyn Fn(&str) -> String) -> Result<usize> {
+    let mut rewritten = 0;
+    for entry in walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let new_content = rewrite(&content);
+        if new_content != content {
+            fs::write(entry.path(), new_content)
+                .with_context(|| format!("Failed to write: {}", entry.path().display()))?;
+            rewritten += 1;
+        }
+    }
+    Ok(rewritten)
+}
+
+/// Re-insert a backup's chat data into the global state DB
+///
+/// Workspace identifiers, paths and URIs are rewritten with `rewrite_refs`. A
+/// session whose ID is already in use by another workspace is restored under a
+/// new ID; with `reclaim`, sessions still owned by the backed-up workspace are
+/// taken over instead. Returns the number of restored and of re-identified
+/// sessions.
+fn restore_global_chat(
+    chat_file: &Path,
+    global_state_db: &Path,
+    rewrite_refs: &dyn Fn(&str) -> String,
+    old_workspace_hash: &str,
+    new_workspace_hash: &str,
+    reclaim: bool,
+) -> Result<(usize, usize)> {
+    let file = File::open(chat_file)
+        .with_context(|| format!("Failed to open: {}", chat_file.display()))?;
+    let mut headers = Vec::new();
+    let mut rows = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line)
+            .with_context(|| format!("Failed to parse {}", GLOBAL_CHAT_FILE))?
+        {
+            GlobalChatRow::Header { value } => headers.push(value),
+            GlobalChatRow::DiskKv { key, value } => rows.push((key, value)),
+        }
+    }
+
+    if let Some(parent) = global_state_db.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut conn = Connection::open(global_state_db).with_context(|| {
+        format!(
+            "Failed to open global state DB: {}",
+            global_state_db.display()
+        )
+    })?;
+    // A fresh install may not have created the tables yet
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS ItemTable (key TEXT UNIQUE ON CONFLICT REPLACE, value BLOB);
+         CREATE TABLE IF NOT EXISTS cursorDiskKV (key TEXT UNIQUE ON CONFLICT REPLACE, value BLOB);",
+    )?;
+    let tx = conn.transaction()?;
+
+    let mut all_headers: Value = tx
+        .query_row(
+            "SELECT value FROM ItemTable WHERE key = ?1",
+            [GLOBAL_HEADERS_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?
+        .map(|data| serde_json::from_str(&data))
+        .transpose()
+        .context("Failed to parse global composer headers")?
+        .unwrap_or_else(|| serde_json::json!({ "allComposers": [] }));
+    if !all_headers["allComposers"].is_array() {
+        all_headers["allComposers"] = Value::Array(Vec::new());
+    }
+
+    // Sessions restored over themselves keep their IDs; any other session
+    // already in the DB belongs to another workspace and is copied instead.
+    // Header-less child sessions follow the restore mode
+    let mut id_map: Vec<(String, String)> = Vec::new();
+    for (key, _) in &rows {
+        let Some(id) = key.strip_prefix("composerData:") else {
+            continue;
+        };
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM cursorDiskKV WHERE key = ?1)",
+            [key],
+            |row| row.get(0),
+        )?;
+        let owner = all_headers["allComposers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|header| header["composerId"] == id)
+            .and_then(|header| header.pointer("/workspaceIdentifier/id"))
+            .and_then(|v| v.as_str());
+        let owned_here = match owner {
+            Some(owner) => owner == new_workspace_hash || (reclaim && owner == old_workspace_hash),
+            None => reclaim,
+        };
+        if exists && !owned_here {
+            id_map.push((id.to_string(), Uuid::new_v4().to_string()));
+        }
+    }
+
+    // One pass, longest ID first, so no ID is rewritten inside another
+    let mut old_ids: Vec<&str> = id_map.iter().map(|(old, _)| old.as_str()).collect();
+    old_ids.sort_by_key(|id| std::cmp::Reverse(id.len()));
+    let id_pattern = (!old_ids.is_empty())
+        .then(|| {
+            let alternatives: Vec<String> = old_ids.iter().map(|id| regex::escape(id)).collect();
+            Regex::new(&alternatives.join("|"))
+        })
+        .transpose()?;
+    let rewrite = |text: &str| {
+        let remapped = match &id_pattern {
+            Some(pattern) => pattern
+                .replace_all(text, |caps: &regex::Captures| {
+                    let old = &caps[0];
+                    id_map
+                        .iter()
+                        .find(|(id, _)| id == old)
+                        .map_or_else(|| old.to_string(), |(_, new)| new.clone())
+                })
+                .into_owned(),
+            None => text.to_string(),
+        };
+        rewrite_refs(&remapped)
+    };
+
+    for (key, value) in &rows {
+        tx.execute(
+            "INSERT OR REPLACE INTO cursorDiskKV (key, value) VALUES (?1, ?2)",
+            rusqlite::params![rewrite(key), rewrite(value)],
+        )
+        .with_context(|| format!("Failed to restore {}", key))?;
+    }
+
+    let restored = headers.len();
+    let composers = all_headers["allComposers"]
+        .as_array_mut()
+        .expect("allComposers is an array");
+    for header in headers {
+        let header: Value = serde_json::from_str(&rewrite(&header.to_string()))?;
+        match composers
+            .iter_mut()
+            .find(|existing| existing["composerId"] == header["composerId"])
+        {
+            Some(existing) => *existing = header,
+            None => composers.push(header),
+        }
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO ItemTable (key, value) VALUES (?1, ?2)",
+        rusqlite::params![GLOBAL_HEADERS_KEY, all_headers.to_string()],
+    )
+    .context("Failed to update global composer headers")?;
+
+    tx.commit()?;
+    Ok((restored, id_map.len()))
+}
+
+/// Run the metadata updates of `rename` Steps 5-7 for a restored project
+///
+/// The composer index in the restored workspace DB always points at the new
+/// path. `storage.json` and the global state DB are only rewritten when
+/// reclaiming, so restoring a copy never takes entries from the original.
+fn sync_metadata(
+    manifest: &BackupManifest,
+    new_path: &Path,
+    new_workspace_dir: &Path,
+    new_workspace_hash: &str,
+    reclaim: bool,
+) -> Result<()> {
+    let new_path_raw = new_path.to_string_lossy().to_string();
+    let new_uri = Url::from_file_path(new_path)
+        .map_err(|_| anyhow::anyhow!("Invalid path: {}", new_path.display()))?
+        .to_string();
+    // A backup taken on another OS has a path that is not local here; its
+    // references were already translated, so only the index is completed
+    let local_old_uri = Url::from_file_path(&manifest.project_path)
+        .ok()
+        .map(|url| url.to_string());
+    let (old_path, old_uri) = match &local_old_uri {
+        Some(old_uri) => (manifest.project_path.as_str(), old_uri.as_str()),
+        None => (new_path_raw.as_str(), new_uri.as_str()),
+    };
+
+    let workspace_db = new_workspace_dir.join("state.vscdb");
+    if workspace_db.exists() {
+        println!("Synchronizing composer index...");
+        let updated = rename::sync_workspace_composer_index(
+            None,
+            &workspace_db,
+            old_uri,
+            &new_uri,
+            old_path,
+            &new_path_raw,
+            &manifest.workspace_hash,
+            new_workspace_hash,
+            false,
+            false,
+        )?;
+        if updated {
+            println!("  -> Composer index synchronized");
+        } else {
+            println!("  -> Composer index already complete");
+        }
+    }
+
+    if local_old_uri.is_none() {
+        println!(
+            "{} Original path {} is from another OS; skipping global metadata sync",
+            "Note:".yellow(),
+            manifest.project_path
+        );
+        return Ok(());
+    }
+    if !reclaim {
+        println!(
+            "{} Original project still exists at {}; its global entries are left in place",
+            "Note:".yellow(),
+            old_path
+        );
+        return Ok(());
+    }
+
+    let global_storage_dir = config::global_storage_dir()?;
+    let storage_json = global_storage_dir.join("storage.json");
+    if storage_json.exists() {
+        println!("Updating globalStorage/storage.json...");
+        if storage::update_storage_json(&storage_json, old_uri, &new_uri, false)? {
+            println!("  -> {} -> {}", old_uri, new_uri);
+        } else {
+            println!("  -> No matching storage.json entries");
+        }
+    }
+
+    let global_state_db = global_storage_dir.join("state.vscdb");
+    if global_state_db.exists() {
+        println!("Updating globalStorage/state.vscdb...");
+        let modified = storage::update_global_state_db(
+            &global_state_db,
+            old_path,
+            &new_path_raw,
+            old_uri,
+            &new_uri,
+            &manifest.workspace_hash,
+            new_workspace_hash,
+            false,
+        )?;
+        if modified {
+            println!("  -> Updated global state references");
+        } else {
+            println!("  -> No matching global state references found");
+        }
+    }
 
     Ok(())
 }
 
-/// Read manifest from a backup archive
-fn read_manifest(backup_path: &Path) -> Result<BackupManifest> {
+/// Add archived `profileAssociations.workspaces` entries missing from
+/// `storage.json`, with their URIs rewritten by `map_uri`
+///
+/// Associations with a profile this installation does not have are left out,
+/// as they would open the workspace in a missing profile. Returns the number
+/// of added entries.
+fn merge_profile_associations(
+    archived: &Path,
+    storage_json: &Path,
+    map_uri: impl Fn(&str) -> String,
+) -> Result<usize> {
+    let content = fs::read_to_string(archived)
+        .with_context(|| format!("Failed to read: {}", archived.display()))?;
+    let archived: Value =
+        serde_json::from_str(&content).context("Failed to parse archived storage.json")?;
+    let Some(entries) = archived
+        .pointer("/profileAssociations/workspaces")
+        .and_then(|v| v.as_object())
+    else {
+        return Ok(0);
+    };
+
+    let mut current: Value = if storage_json.exists() {
+        let content = fs::read_to_string(storage_json)
+            .with_context(|| format!("Failed to read: {}", storage_json.display()))?;
+        serde_json::from_str(&content).context("Failed to parse storage.json")?
+    } else {
+        serde_json::json!({})
+    };
+    let known_profiles: HashSet<String> = current["userDataProfiles"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|profile| profile["location"].as_str())
+        .map(str::to_string)
+        .collect();
+
+    let associations = current
+        .as_object_mut()
+        .context("storage.json is not a JSON object")?
+        .entry("profileAssociations")
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .context("Unexpected profileAssociations in storage.json")?
+        .entry("workspaces")
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .context("Unexpected profileAssociations.workspaces in storage.json")?;
+
+    let mut added = 0;
+    for (uri, profile) in entries {
+        let known = profile
+            .as_str()
+            .is_some_and(|p| p == DEFAULT_PROFILE || known_profiles.contains(p));
+        let uri = map_uri(uri);
+        if known && !associations.contains_key(&uri) {
+            associations.insert(uri, profile.clone());
+            added += 1;
+        }
+    }
+
+    if added > 0 {
+        if let Some(parent) = storage_json.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(storage_json, serde_json::to_string_pretty(&current)?)
+            .with_context(|| format!("Failed to write: {}", storage_json.display()))?;
+    }
+    Ok(added)
+}
+
+/// Extract a backup archive into a temporary directory
+pub fn unpack_archive(backup_path: &Path) -> Result<tempfile::TempDir> {
+    let file = File::open(backup_path)
+        .with_context(|| format!("Failed to open: {}", backup_path.display()))?;
+    let decoder = GzDecoder::new(file);
+    let mut archive = Archive::new(decoder);
+
+    let temp_dir = tempfile::tempdir().context("Failed to create temp directory")?;
+    archive
+        .unpack(temp_dir.path())
+        .context("Failed to extract backup")?;
+    Ok(temp_dir)
+}
+
+/// Read manifest from a single-project backup archive
+pub fn read_manifest(backup_path: &Path) -> Result<BackupManifest> {
+    match read_archive_manifest(backup_path)? {
+        ArchiveManifest::Project(manifest) => Ok(manifest),
+        ArchiveManifest::Profile(_) => bail!(
+            "{} is a full-profile backup; use `restore --all`",
+            backup_path.display()
+        ),
+    }
+}
+
+/// Read the manifest of either kind of backup archive
+pub fn read_archive_manifest(backup_path: &Path) -> Result<ArchiveManifest> {
     let file = File::open(backup_path)
         .with_context(|| format!("Failed to open: {}", backup_path.display()))?;
     let decoder = GzDecoder::new(file);
@@ -163,12 +935,21 @@ fn read_manifest(backup_path: &Path) -> Result<BackupManifest> {
         let mut entry = entry?;
         let path = entry.path()?;
 
-        if path.to_string_lossy() == "manifest.json" {
+        // Archives repacked with tar may prefix entries with ./
+        let path = path.strip_prefix(".").unwrap_or(&path).to_path_buf();
+        if path == Path::new("manifest.json") {
             let mut content = String::new();
             entry.read_to_string(&mut content)?;
             let manifest: BackupManifest =
                 serde_json::from_str(&content).context("Failed to parse manifest.json")?;
-            return Ok(manifest);
+            return Ok(ArchiveManifest::Project(manifest));
+        }
+        if path == Path::new(PROFILE_MANIFEST_FILE) {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            let manifest: ProfileManifest = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", PROFILE_MANIFEST_FILE))?;
+            return Ok(ArchiveManifest::Profile(manifest));
         }
     }
 
@@ -177,5 +958,331 @@ fn read_manifest(backup_path: &Path) -> Result<BackupManifest> {
 
 #[cfg(test)]
 mod tests {
-    // Integration tests would require actual backup files
+    use super::*;
+    use crate::commands::backup::BackupContents;
+    use std::io::Write;
+
+    #[test]
+    fn test_restore_global_chat() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("state.vscdb");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ItemTable (key TEXT PRIMARY KEY, value BLOB);
+             CREATE TABLE cursorDiskKV (key TEXT PRIMARY KEY, value BLOB);
+             INSERT INTO cursorDiskKV VALUES ('composerData:taken', '{}');",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO ItemTable VALUES (?1, ?2)",
+            rusqlite::params![
+                GLOBAL_HEADERS_KEY,
+                r#"{"allComposers":[{"composerId":"taken","workspaceIdentifier":{"id":"other"}}]}"#
+            ],
+        )
+        .unwrap();
+        drop(conn);
+
+        let chat_file = dir.path().join(GLOBAL_CHAT_FILE);
+        let mut file = File::create(&chat_file).unwrap();
+        let header = |id: &str| {
+            serde_json::json!({"kind": "header", "value": {
+                "composerId": id,
+                "workspaceIdentifier": {"id": "oldhash", "uri": {"path": "/old/proj"}}
+            }})
+        };
+        for line in [
+            header("fresh"),
+            header("taken"),
+            serde_json::json!({"kind": "disk_kv", "key": "composerData:fresh", "value": "{}"}),
+            serde_json::json!({"kind": "disk_kv", "key": "composerData:taken", "value": "{\"composerId\":\"taken\"}"}),
+            serde_json::json!({"kind": "disk_kv", "key": "bubbleId:taken:b1", "value": "{\"fsPath\":\"/old/proj/src/main.rs\"}"}),
+        ] {
+            writeln!(file, "{}", line).unwrap();
+        }
+        drop(file);
+
+        let manifest = BackupManifest {
+            version: MANIFEST_VERSION,
+            project_path: "/old/proj".to_string(),
+            folder_id: "old-proj".to_string(),
+            workspace_hash: "oldhash".to_string(),
+            created_at: 0,
+            includes: BackupContents {
+                workspace_storage: true,
+                projects_data: false,
+                global_chat_sessions: 2,
+            },
+            checksums: Default::default(),
+            kind: Default::default(),
+        };
+        let (restored, remapped) = restore_global_chat(
+            &chat_file,
+            &db_path,
+            &rewrite_old_proj,
+            &manifest.workspace_hash,
+            "newhash",
+            false,
+        )
+        .unwrap();
+        assert_eq!((restored, remapped), (2, 1));
+
+        let conn = Connection::open(&db_path).unwrap();
+        let headers: String = conn
+            .query_row(
+                "SELECT value FROM ItemTable WHERE key = ?1",
+                [GLOBAL_HEADERS_KEY],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let headers: Value = serde_json::from_str(&headers).unwrap();
+        let composers = headers["allComposers"].as_array().unwrap();
+        assert_eq!(composers.len(), 3);
+        // The original owner of "taken" keeps it
+        assert_eq!(composers[0]["workspaceIdentifier"]["id"], "other");
+        assert_eq!(composers[1]["composerId"], "fresh");
+        assert_eq!(composers[1]["workspaceIdentifier"]["id"], "newhash");
+        assert_eq!(
+            composers[1]["workspaceIdentifier"]["uri"]["path"],
+            "/new/proj"
+        );
+
+        let copy_id = composers[2]["composerId"].as_str().unwrap();
+        assert_ne!(copy_id, "taken");
+        let bubble: String = conn
+            .query_row(
+                "SELECT value FROM cursorDiskKV WHERE key = ?1",
+                [format!("bubbleId:{}:b1", copy_id)],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(bubble, r#"{"fsPath":"/new/proj/src/main.rs"}"#);
+    }
+
+    #[test]
+    fn test_restore_global_chat_reclaims_own_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("state.vscdb");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ItemTable (key TEXT PRIMARY KEY, value BLOB);
+             CREATE TABLE cursorDiskKV (key TEXT PRIMARY KEY, value BLOB);
+             INSERT INTO cursorDiskKV VALUES ('composerData:c1', '{}');",
+        )
+        .unwrap();
+        let header = r#"{"composerId":"c1","workspaceIdentifier":{"id":"oldhash"}}"#;
+        conn.execute(
+            "INSERT INTO ItemTable VALUES (?1, ?2)",
+            rusqlite::params![
+                GLOBAL_HEADERS_KEY,
+                format!(r#"{{"allComposers":[{}]}}"#, header)
+            ],
+        )
+        .unwrap();
+        drop(conn);
+
+        let chat_file = dir.path().join(GLOBAL_CHAT_FILE);
+        fs::write(
+            &chat_file,
+            format!(
+                "{}\n{}\n",
+                serde_json::json!({"kind": "header", "value": serde_json::from_str::<Value>(header).unwrap()}),
+                serde_json::json!({"kind": "disk_kv", "key": "composerData:c1", "value": "{}"}),
+            ),
+        )
+        .unwrap();
+
+        let manifest = BackupManifest {
+            version: MANIFEST_VERSION,
+            project_path: "/old/proj".to_string(),
+            folder_id: "old-proj".to_string(),
+            workspace_hash: "oldhash".to_string(),
+            created_at: 0,
+            includes: BackupContents {
+                workspace_storage: true,
+                projects_data: false,
+                global_chat_sessions: 1,
+            },
+            checksums: Default::default(),
+            kind: Default::default(),
+        };
+        let (restored, remapped) = restore_global_chat(
+            &chat_file,
+            &db_path,
+            &rewrite_old_proj,
+            &manifest.workspace_hash,
+            "newhash",
+            true,
+        )
+        .unwrap();
+        assert_eq!((restored, remapped), (1, 0));
+
+        let conn = Connection::open(&db_path).unwrap();
+        let headers: String = conn
+            .query_row(
+                "SELECT value FROM ItemTable WHERE key = ?1",
+                [GLOBAL_HEADERS_KEY],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(
+            headers,
+            r#"{"allComposers":[{"composerId":"c1","workspaceIdentifier":{"id":"newhash"}}]}"#
+        );
+    }
+
+    /// Rewrites of a project moved from `/old/proj` (hash `oldhash`) to
+    /// `/new/proj` (hash `newhash`)
+    fn rewrite_old_proj(text: &str) -> String {
+        let translator = PathTranslator::new(&[PathMapping {
+            from: "/old/proj".to_string(),
+            to: "/new/proj".to_string(),
+        }])
+        .unwrap();
+        storage::rewrite_workspace_refs(&translator.rewrite(text), &[("oldhash", "newhash")])
+    }
+
+    #[test]
+    fn test_rewrite_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("state.vscdb");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            r#"CREATE TABLE ItemTable (key TEXT UNIQUE ON CONFLICT REPLACE, value BLOB);
+               CREATE TABLE cursorDiskKV (key TEXT UNIQUE ON CONFLICT REPLACE, value BLOB);
+               INSERT INTO ItemTable VALUES ('history', '{"uri":"file:///old/proj/src/main.rs"}');
+               INSERT INTO ItemTable VALUES ('other', '{"uri":"file:///old/project"}');
+               INSERT INTO ItemTable VALUES ('binary', x'00ff');
+               INSERT INTO cursorDiskKV VALUES ('bubble', '{"fsPath":"/old/proj/a.rs"}');"#,
+        )
+        .unwrap();
+        drop(conn);
+
+        assert_eq!(rewrite_database(&db_path, &rewrite_old_proj).unwrap(), 2);
+
+        let conn = Connection::open(&db_path).unwrap();
+        let value = |table: &str, key: &str| -> String {
+            conn.query_row(
+                &format!("SELECT CAST(value AS TEXT) FROM {} WHERE key = ?1", table),
+                [key],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            value("ItemTable", "history"),
+            r#"{"uri":"file:///new/proj/src/main.rs"}"#
+        );
+        assert_eq!(
+            value("ItemTable", "other"),
+            r#"{"uri":"file:///old/project"}"#
+        );
+        assert_eq!(
+            value("cursorDiskKV", "bubble"),
+            r#"{"fsPath":"/new/proj/a.rs"}"#
+        );
+    }
+
+    #[test]
+    fn test_restore_code_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let archived = dir.path().join("archived.code-workspace");
+        fs::write(
+            &archived,
+            r#"{"folders":[{"path":"/Users/alice/api"},{"path":"web"},{"path":"/opt/shared"}]}"#,
+        )
+        .unwrap();
+        let translator =
+            PathTranslator::new(&[PathMapping::parse("/Users/alice=/home/alice").unwrap()])
+                .unwrap();
+
+        let target = dir.path().join("new/mono.code-workspace");
+        restore_code_workspace(&archived, &target, &translator).unwrap();
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            r#"{"folders":[{"path":"/home/alice/api"},{"path":"web"},{"path":"/opt/shared"}]}"#
+        );
+
+        // A file copied over by hand is kept, with its paths rewritten
+        fs::write(&target, r#"{"folders":[{"path":"/Users/alice/cli"}]}"#).unwrap();
+        restore_code_workspace(&archived, &target, &translator).unwrap();
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            r#"{"folders":[{"path":"/home/alice/cli"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_restore_unlinked_projects_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let extracted = dir.path().join("archive/projects");
+        let projects_dir = dir.path().join("projects");
+        for id in ["Users-alice-scratch", "ssh-box"] {
+            fs::create_dir_all(extracted.join(id)).unwrap();
+            fs::write(
+                extracted.join(id).join("mcp.json"),
+                r#"{"cwd":"/Users/alice/scratch"}"#,
+            )
+            .unwrap();
+        }
+        fs::create_dir_all(projects_dir.join("ssh-box")).unwrap();
+        fs::write(projects_dir.join("ssh-box/mcp.json"), "current").unwrap();
+
+        let translator =
+            PathTranslator::new(&[PathMapping::parse("/Users/alice=/home/alice").unwrap()])
+                .unwrap();
+        let ids = ["Users-alice-scratch".to_string(), "ssh-box".to_string()];
+        let kept = restore_unlinked_projects_data(&extracted, &ids, &projects_dir, &|text| {
+            translator.rewrite(text)
+        })
+        .unwrap();
+
+        assert_eq!(kept, vec![projects_dir.join("ssh-box")]);
+        assert_eq!(
+            fs::read_to_string(projects_dir.join("Users-alice-scratch/mcp.json")).unwrap(),
+            r#"{"cwd":"/home/alice/scratch"}"#
+        );
+        assert_eq!(
+            fs::read_to_string(projects_dir.join("ssh-box/mcp.json")).unwrap(),
+            "current"
+        );
+    }
+
+    #[test]
+    fn test_merge_profile_associations() {
+        let dir = tempfile::tempdir().unwrap();
+        let archived = dir.path().join("archived.json");
+        fs::write(
+            &archived,
+            r#"{"profileAssociations":{"workspaces":{
+                "file:///Users/alice/a":"__default__profile__",
+                "file:///Users/alice/b":"-1a2b3c",
+                "file:///Users/alice/c":"-missing"
+            }}}"#,
+        )
+        .unwrap();
+        let storage_json = dir.path().join("storage.json");
+        fs::write(
+            &storage_json,
+            r#"{"userDataProfiles":[{"location":"-1a2b3c","name":"Work"}],
+                "profileAssociations":{"workspaces":{"file:///home/alice/a":"-1a2b3c"}}}"#,
+        )
+        .unwrap();
+
+        let translator =
+            PathTranslator::new(&[PathMapping::parse("/Users/alice=/home/alice").unwrap()])
+                .unwrap();
+        let added =
+            merge_profile_associations(&archived, &storage_json, |uri| translator.rewrite(uri))
+                .unwrap();
+        assert_eq!(added, 1);
+
+        let merged: Value =
+            serde_json::from_str(&fs::read_to_string(&storage_json).unwrap()).unwrap();
+        let workspaces = &merged["profileAssociations"]["workspaces"];
+        // Existing associations win and unknown profiles are left out
+        assert_eq!(workspaces["file:///home/alice/a"], "-1a2b3c");
+        assert_eq!(workspaces["file:///home/alice/b"], "-1a2b3c");
+        assert!(workspaces.get("file:///home/alice/c").is_none());
+    }
 }
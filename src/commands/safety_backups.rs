@@ -0,0 +1,661 @@
+//! Safety backups - Storage, listing and pruning of rename and clone backups
+//!
+//! Every rename and clone leaves a backup directory under
+//! `<data dir>/cursor-helper/rename-backups`. The global `state.vscdb` is
+//! most of its size, so global DB snapshots are stored once per distinct
+//! content in `global-db/<md5>.vscdb` and referenced from the backups that
+//! use them. A retention policy (keep last N, max age, max total size), given
+//! on the command line or in `retention.toml`, decides what gets pruned.
+
+use anyhow::{bail, Context, Result};
+use comfy_table::{presets::UTF8_FULL_CONDENSED, Cell, ContentArrangement, Table};
+use owo_colors::OwoColorize;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::rename;
+use super::rename_journal::{self, JournalStatus, RenameJournal};
+use super::utils;
+
+/// Directory inside the backups directory holding global DB snapshots
+const GLOBAL_DB_DIR: &str = "global-db";
+
+/// File inside a backup directory naming its global DB snapshot
+const GLOBAL_DB_REF: &str = "state.vscdb.ref";
+
+/// Retention policy file inside the backups directory
+pub const RETENTION_FILE: &str = "retention.toml";
+
+/// Snapshot the global state DB for a backup, reusing an identical snapshot
+///
+/// Returns the snapshot path and whether an earlier backup already had it.
+pub fn snapshot_global_db(backup_dir: &Path, db_path: &Path) -> Result<(PathBuf, bool)> {
+    let snapshots_dir = backup_dir
+        .parent()
+        .context("Backup directory has no parent")?
+        .join(GLOBAL_DB_DIR);
+    fs::create_dir_all(&snapshots_dir)
+        .with_context(|| format!("Failed to create directory: {}", snapshots_dir.display()))?;
+
+    let name = format!("{}.vscdb", file_md5(db_path)?);
+    let snapshot = snapshots_dir.join(&name);
+    let reused = snapshot.exists();
+    if !reused {
+        // Copy under a temporary name so an interrupted copy is never reused
+        let tmp = snapshot.with_extension("vscdb.tmp");
+        fs::copy(db_path, &tmp).with_context(|| {
+            format!(
+                "Failed to backup {} to {}",
+                db_path.display(),
+                tmp.display()
+            )
+        })?;
+        fs::rename(&tmp, &snapshot)
+            .with_context(|| format!("Failed to write: {}", snapshot.display()))?;
+    }
+
+    share_global_db(backup_dir, &snapshot)?;
+    Ok((snapshot, reused))
+}
+
+/// Make a backup refer to an existing global DB snapshot
+///
+/// Used when several backups are taken before the global DB changes, so
+/// the snapshot is hashed and copied only once.
+pub fn share_global_db(backup_dir: &Path, snapshot: &Path) -> Result<()> {
+    let name = snapshot
+        .file_name()
+        .context("Global DB snapshot has no file name")?;
+    let ref_path = backup_dir.join(GLOBAL_DB_REF);
+    fs::write(&ref_path, name.to_string_lossy().as_bytes())
+        .with_context(|| format!("Failed to write: {}", ref_path.display()))
+}
+
+/// The global DB snapshot of a backup, if it has one
+///
+/// Backups made before snapshots were shared hold a plain `state.vscdb`.
+pub fn global_db_snapshot(backup_dir: &Path) -> Option<PathBuf> {
+    let legacy = backup_dir.join("state.vscdb");
+    if legacy.exists() {
+        return Some(legacy);
+    }
+
+    let snapshot = backup_dir
+        .parent()?
+        .join(GLOBAL_DB_DIR)
+        .join(global_db_ref(backup_dir)?);
+    snapshot.exists().then_some(snapshot)
+}
+
+/// Name of the shared snapshot a backup refers to
+fn global_db_ref(backup_dir: &Path) -> Option<String> {
+    let name = fs::read_to_string(backup_dir.join(GLOBAL_DB_REF)).ok()?;
+    Some(name.trim().to_string()).filter(|name| !name.is_empty())
+}
+
+/// MD5 of a file's content, read in chunks
+fn file_md5(path: &Path) -> Result<String> {
+    let mut file =
+        fs::File::open(path).with_context(|| format!("Failed to open: {}", path.display()))?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0; 1 << 20];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .with_context(|| format!("Failed to read: {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        context.consume(&buffer[..read]);
+    }
+    Ok(format!("{:x}", context.finalize()))
+}
+
+/// Which safety backups to keep
+///
+/// ```toml
+/// keep_last = 10     # keep at most the 10 most recent backups
+/// max_age = "30d"    # prune backups older than 30 days
+/// max_size = "5GB"   # prune the oldest backups until the rest fit
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionPolicy {
+    /// Keep at most this many backups
+    pub keep_last: Option<usize>,
+    /// Prune backups started before this age or date (e.g. `30d`)
+    pub max_age: Option<String>,
+    /// Prune the oldest backups until the total fits (e.g. `5GB`)
+    pub max_size: Option<String>,
+}
+
+impl RetentionPolicy {
+    /// Load the policy file from the backups directory, if there is one
+    pub fn load(backups_dir: &Path) -> Result<Option<Self>> {
+        let path = backups_dir.join(RETENTION_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read retention policy: {}", path.display()))?;
+        toml::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Failed to parse retention policy: {}", path.display()))
+    }
+
+    /// Take limits not set here from `fallback`
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            keep_last: self.keep_last.or(fallback.keep_last),
+            max_age: self.max_age.or(fallback.max_age),
+            max_size: self.max_size.or(fallback.max_size),
+        }
+    }
+
+    /// Whether no limit is set
+    pub fn is_empty(&self) -> bool {
+        self.keep_last.is_none() && self.max_age.is_none() && self.max_size.is_none()
+    }
+
+    /// Human-readable summary, e.g. "keep last 10, max age 30d"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(n) = self.keep_last {
+            parts.push(format!("keep last {}", n));
+        }
+        if let Some(age) = &self.max_age {
+            parts.push(format!("max age {}", age));
+        }
+        if let Some(size) = &self.max_size {
+            parts.push(format!("max size {}", size));
+        }
+        parts.join(", ")
+    }
+
+    fn limits(&self) -> Result<Limits> {
+        Ok(Limits {
+            keep_last: self.keep_last,
+            cutoff_ms: self
+                .max_age
+                .as_deref()
+                .map(|age| utils::parse_time_bound(age, chrono::Utc::now(), false))
+                .transpose()?,
+            max_size: self
+                .max_size
+                .as_deref()
+                .map(utils::parse_size)
+                .transpose()?,
+        })
+    }
+}
+
+/// Resolved retention limits
+struct Limits {
+    keep_last: Option<usize>,
+    cutoff_ms: Option<i64>,
+    max_size: Option<u64>,
+}
+
+/// A backup directory and what it holds
+struct BackupEntry {
+    id: String,
+    dir: PathBuf,
+    journal: Option<RenameJournal>,
+    /// Unix milliseconds
+    started_at: i64,
+    /// Size of the directory itself, excluding the shared global DB snapshot
+    size: u64,
+    /// Shared global DB snapshot it refers to
+    global_db: Option<String>,
+}
+
+impl BackupEntry {
+    /// Interrupted operations still need their backup for `--resume`/`--rollback`
+    fn is_interrupted(&self) -> bool {
+        self.journal
+            .as_ref()
+            .is_some_and(|j| j.status == JournalStatus::InProgress)
+    }
+}
+
+/// All backups, oldest first
+fn list_entries(backups_dir: &Path) -> Result<Vec<BackupEntry>> {
+    if !backups_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(backups_dir)
+        .with_context(|| format!("Failed to read: {}", backups_dir.display()))?
+    {
+        let entry = entry?;
+        let dir = entry.path();
+        if !dir.is_dir() || entry.file_name() == GLOBAL_DB_DIR {
+            continue;
+        }
+
+        let journal = RenameJournal::read(&dir).ok();
+        let started_at = journal
+            .as_ref()
+            .and_then(|j| chrono::DateTime::parse_from_rfc3339(&j.started_at).ok())
+            .map(|dt| dt.timestamp_millis())
+            .or_else(|| {
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some(chrono::DateTime::<chrono::Utc>::from(modified).timestamp_millis())
+            })
+            .unwrap_or(0);
+
+        entries.push(BackupEntry {
+            id: entry.file_name().to_string_lossy().to_string(),
+            size: utils::calculate_dir_size(&dir).unwrap_or(0),
+            global_db: global_db_ref(&dir),
+            started_at,
+            journal,
+            dir,
+        });
+    }
+
+    entries.sort_by(|a, b| a.started_at.cmp(&b.started_at).then(a.id.cmp(&b.id)));
+    Ok(entries)
+}
+
+/// Sizes of the shared global DB snapshots by file name
+fn snapshot_sizes(backups_dir: &Path) -> HashMap<String, u64> {
+    fs::read_dir(backups_dir.join(GLOBAL_DB_DIR))
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let size = entry.metadata().ok()?.len();
+            Some((entry.file_name().to_string_lossy().to_string(), size))
+        })
+        .collect()
+}
+
+/// Total size of the given backups, counting each shared snapshot once
+fn total_size<'a>(
+    entries: impl Iterator<Item = &'a BackupEntry>,
+    snapshot_sizes: &HashMap<String, u64>,
+) -> u64 {
+    let mut snapshots = HashSet::new();
+    let mut total = 0;
+    for entry in entries {
+        total += entry.size;
+        if let Some(name) = &entry.global_db {
+            if snapshots.insert(name) {
+                total += snapshot_sizes.get(name).copied().unwrap_or(0);
+            }
+        }
+    }
+    total
+}
+
+/// Indices of the backups the limits prune
+///
+/// Interrupted operations and the `protected` backups are always kept.
+fn select_for_pruning(
+    entries: &[BackupEntry],
+    limits: &Limits,
+    snapshot_sizes: &HashMap<String, u64>,
+    protected: &[PathBuf],
+) -> Vec<usize> {
+    let pinned = |entry: &BackupEntry| entry.is_interrupted() || protected.contains(&entry.dir);
+    let mut prune = vec![false; entries.len()];
+
+    if let Some(keep_last) = limits.keep_last {
+        for (i, entry) in entries.iter().enumerate().rev().skip(keep_last) {
+            prune[i] = !pinned(entry);
+        }
+    }
+
+    if let Some(cutoff) = limits.cutoff_ms {
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.started_at < cutoff && !pinned(entry) {
+                prune[i] = true;
+            }
+        }
+    }
+
+    if let Some(max_size) = limits.max_size {
+        for (i, entry) in entries.iter().enumerate() {
+            let kept = entries.iter().zip(&prune).filter(|(_, p)| !**p);
+            if total_size(kept.map(|(e, _)| e), snapshot_sizes) <= max_size {
+                break;
+            }
+            if !pinned(entry) {
+                prune[i] = true;
+            }
+        }
+    }
+
+    (0..entries.len()).filter(|&i| prune[i]).collect()
+}
+
+/// Delete backups, then the global DB snapshots no remaining backup refers to
+///
+/// Returns the number of bytes freed.
+fn delete_backups(
+    backups_dir: &Path,
+    entries: &[BackupEntry],
+    selected: &[usize],
+    snapshot_sizes: &HashMap<String, u64>,
+) -> Result<u64> {
+    let mut freed = 0;
+    for &i in selected {
+        fs::remove_dir_all(&entries[i].dir)
+            .with_context(|| format!("Failed to remove backup: {}", entries[i].dir.display()))?;
+        freed += entries[i].size;
+    }
+
+    let referenced: HashSet<&String> = entries
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !selected.contains(i))
+        .filter_map(|(_, entry)| entry.global_db.as_ref())
+        .collect();
+    for (name, size) in snapshot_sizes {
+        if !referenced.contains(name) {
+            let path = backups_dir.join(GLOBAL_DB_DIR).join(name);
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove snapshot: {}", path.display()))?;
+            freed += size;
+        }
+    }
+
+    Ok(freed)
+}
+
+/// Table of safety backups, newest first
+pub fn list() -> Result<String> {
+    let backups_dir = rename_journal::backups_dir()?;
+    let entries = list_entries(&backups_dir)?;
+    if entries.is_empty() {
+        return Ok("No safety backups found.".to_string());
+    }
+
+    let snapshot_sizes = snapshot_sizes(&backups_dir);
+    let mut users: HashMap<&String, usize> = HashMap::new();
+    for name in entries.iter().filter_map(|e| e.global_db.as_ref()) {
+        *users.entry(name).or_default() += 1;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        Cell::new("ID"),
+        Cell::new("Started"),
+        Cell::new("Operation"),
+        Cell::new("Status"),
+        Cell::new("Size"),
+        Cell::new("Global DB"),
+    ]);
+
+    for entry in entries.iter().rev() {
+        let global_db = match &entry.global_db {
+            Some(name) => {
+                let size = snapshot_sizes.get(name).copied().unwrap_or(0);
+                let shared = if users[name] > 1 { ", shared" } else { "" };
+                format!(
+                    "{} ({}{})",
+                    &name[..name.len().min(8)],
+                    utils::format_size(size),
+                    shared
+                )
+            }
+            None if entry.dir.join("state.vscdb").exists() => "full copy".to_string(),
+            None => "-".to_string(),
+        };
+        let (operation, status) = match &entry.journal {
+            Some(journal) => (journal.kind(), journal.status.label()),
+            None => ("-", "unknown"),
+        };
+        table.add_row(vec![
+            Cell::new(&entry.id),
+            Cell::new(
+                chrono::DateTime::from_timestamp_millis(entry.started_at)
+                    .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default(),
+            ),
+            Cell::new(operation),
+            Cell::new(status),
+            Cell::new(utils::format_size(entry.size)),
+            Cell::new(global_db),
+        ]);
+    }
+
+    let policy = match RetentionPolicy::load(&backups_dir)? {
+        Some(policy) if !policy.is_empty() => format!("Retention policy: {}", policy.describe()),
+        _ => format!(
+            "No retention policy ({} not found)",
+            backups_dir.join(RETENTION_FILE).display()
+        ),
+    };
+
+    Ok(format!(
+        "{}\n\n{} backup(s), {} in total ({} global DB snapshot(s))\n{}",
+        table,
+        entries.len(),
+        utils::format_size(total_size(entries.iter(), &snapshot_sizes)),
+        snapshot_sizes.len(),
+        policy
+    ))
+}
+
+/// Prune safety backups by the given limits, falling back to `retention.toml`
+pub fn prune(limits: RetentionPolicy, dry_run: bool, yes: bool) -> Result<()> {
+    let backups_dir = rename_journal::backups_dir()?;
+    let policy = limits.or(RetentionPolicy::load(&backups_dir)?.unwrap_or_default());
+    if policy.is_empty() {
+        bail!(
+            "No retention limits. Pass --keep-last, --max-age or --max-size, or set them in {}",
+            backups_dir.join(RETENTION_FILE).display()
+        );
+    }
+
+    let entries = list_entries(&backups_dir)?;
+    let snapshot_sizes = snapshot_sizes(&backups_dir);
+    let selected = select_for_pruning(&entries, &policy.limits()?, &snapshot_sizes, &[]);
+
+    println!("Retention policy: {}", policy.describe());
+    if selected.is_empty() {
+        println!("Nothing to prune.");
+        return Ok(());
+    }
+
+    println!();
+    println!("Backups to delete:");
+    for &i in &selected {
+        let entry = &entries[i];
+        println!(
+            "  {} ({}, {})",
+            entry.id,
+            entry.journal.as_ref().map_or("-", |j| j.kind()),
+            utils::format_size(entry.size)
+        );
+    }
+    let remaining = entries
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !selected.contains(i))
+        .map(|(_, e)| e);
+    let freed =
+        total_size(entries.iter(), &snapshot_sizes) - total_size(remaining, &snapshot_sizes);
+    println!();
+
+    if dry_run {
+        println!(
+            "  {} Would delete {} backup(s), freeing {}",
+            "[DRY-RUN]".blue(),
+            selected.len(),
+            utils::format_size(freed)
+        );
+        return Ok(());
+    }
+
+    if !yes && !rename::confirm(&format!("Delete {} backup(s)?", selected.len()))? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let freed = delete_backups(&backups_dir, &entries, &selected, &snapshot_sizes)?;
+    println!(
+        "{} Deleted {} backup(s), freed {}",
+        "Done.".green(),
+        selected.len(),
+        utils::format_size(freed)
+    );
+    Ok(())
+}
+
+/// Apply `retention.toml`, if present, after an operation made the `current` backups
+///
+/// Failures only warn: the operation itself already succeeded.
+pub fn apply_retention_policy(current: &[PathBuf]) {
+    let result = (|| -> Result<usize> {
+        let backups_dir = rename_journal::backups_dir()?;
+        let Some(policy) = RetentionPolicy::load(&backups_dir)? else {
+            return Ok(0);
+        };
+        let entries = list_entries(&backups_dir)?;
+        let snapshot_sizes = snapshot_sizes(&backups_dir);
+        let selected = select_for_pruning(&entries, &policy.limits()?, &snapshot_sizes, current);
+        if !selected.is_empty() {
+            delete_backups(&backups_dir, &entries, &selected, &snapshot_sizes)?;
+        }
+        Ok(selected.len())
+    })();
+
+    match result {
+        Ok(0) => {}
+        Ok(pruned) => println!("Pruned {} old safety backup(s) (retention policy)", pruned),
+        Err(err) => eprintln!(
+            "Warning: Failed to apply backup retention policy: {:#}",
+            err
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(id: &str, started_at: i64, size: u64, global_db: Option<&str>) -> BackupEntry {
+        BackupEntry {
+            id: id.to_string(),
+            dir: PathBuf::from("/backups").join(id),
+            journal: None,
+            started_at,
+            size,
+            global_db: global_db.map(str::to_string),
+        }
+    }
+
+    fn limits(keep_last: Option<usize>, cutoff_ms: Option<i64>, max_size: Option<u64>) -> Limits {
+        Limits {
+            keep_last,
+            cutoff_ms,
+            max_size,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_global_db_shares_identical_content() {
+        let temp = TempDir::new().unwrap();
+        let db = temp.path().join("state.vscdb");
+        fs::write(&db, "global db v1").unwrap();
+
+        let first = temp.path().join("rename-1");
+        let second = temp.path().join("rename-2");
+        let third = temp.path().join("rename-3");
+        for dir in [&first, &second, &third] {
+            fs::create_dir(dir).unwrap();
+        }
+
+        let (snapshot, reused) = snapshot_global_db(&first, &db).unwrap();
+        assert!(!reused);
+        assert_eq!(snapshot_global_db(&second, &db).unwrap(), (snapshot, true));
+
+        fs::write(&db, "global db v2").unwrap();
+        let (changed, reused) = snapshot_global_db(&third, &db).unwrap();
+        assert!(!reused);
+        assert_eq!(fs::read_to_string(&changed).unwrap(), "global db v2");
+        assert_eq!(
+            fs::read_dir(temp.path().join(GLOBAL_DB_DIR))
+                .unwrap()
+                .count(),
+            2
+        );
+
+        assert_eq!(global_db_snapshot(&second), global_db_snapshot(&first));
+        assert_eq!(global_db_snapshot(&third), Some(changed));
+
+        // Backups made before snapshots were shared keep a full copy
+        let legacy = temp.path().join("rename-0");
+        fs::create_dir(&legacy).unwrap();
+        fs::write(legacy.join("state.vscdb"), "old").unwrap();
+        assert_eq!(
+            global_db_snapshot(&legacy),
+            Some(legacy.join("state.vscdb"))
+        );
+    }
+
+    #[test]
+    fn test_select_for_pruning() {
+        let sizes = HashMap::from([("a.vscdb".to_string(), 100), ("b.vscdb".to_string(), 100)]);
+        let entries = vec![
+            entry("rename-1", 1_000, 10, Some("a.vscdb")),
+            entry("rename-2", 2_000, 10, Some("a.vscdb")),
+            entry("rename-3", 3_000, 10, Some("b.vscdb")),
+            entry("rename-4", 4_000, 10, Some("b.vscdb")),
+        ];
+
+        let select = |limits: Limits, protected: Option<&str>| {
+            let protected: Vec<PathBuf> = protected
+                .map(|id| PathBuf::from("/backups").join(id))
+                .into_iter()
+                .collect();
+            select_for_pruning(&entries, &limits, &sizes, &protected)
+        };
+
+        assert_eq!(select(limits(Some(2), None, None), None), vec![0, 1]);
+        assert_eq!(select(limits(None, Some(2_500), None), None), vec![0, 1]);
+        assert!(select(limits(Some(10), Some(0), Some(1_000)), None).is_empty());
+        // Dropping rename-1 alone frees only its own 10 bytes; the shared
+        // snapshot goes once rename-2 is pruned too
+        assert_eq!(select(limits(None, None, Some(230)), None), vec![0]);
+        assert_eq!(select(limits(None, None, Some(130)), None), vec![0, 1]);
+        // The backup just made survives even a zero budget
+        assert_eq!(
+            select(limits(Some(0), None, Some(0)), Some("rename-4")),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_retention_policy_file() {
+        let temp = TempDir::new().unwrap();
+        assert_eq!(RetentionPolicy::load(temp.path()).unwrap(), None);
+
+        fs::write(
+            temp.path().join(RETENTION_FILE),
+            "keep_last = 5\nmax_size = \"2GB\"\n",
+        )
+        .unwrap();
+        let policy = RetentionPolicy::load(temp.path()).unwrap().unwrap();
+        let merged = RetentionPolicy {
+            keep_last: Some(3),
+            ..Default::default()
+        }
+        .or(policy);
+        assert_eq!(merged.describe(), "keep last 3, max size 2GB");
+
+        fs::write(temp.path().join(RETENTION_FILE), "keep_lats = 5\n").unwrap();
+        assert!(RetentionPolicy::load(temp.path()).is_err());
+    }
+}
@@ -0,0 +1,416 @@
+//! Search command - Full-text search across all chat history
+
+use anyhow::{Context, Result};
+use owo_colors::OwoColorize;
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::export_chat::{self, ChatMessage, ExportOptions};
+use super::list;
+use crate::config;
+use crate::cursor::chat_sessions;
+use crate::cursor::search_index;
+
+/// Characters of context shown on each side of a match
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// Options for the search command
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Text (or regular expression) to search for
+    pub query: String,
+    /// Treat the query as a regular expression
+    pub regex: bool,
+    /// Match case exactly (default is case-insensitive)
+    pub case_sensitive: bool,
+    /// Only search projects whose path contains this pattern
+    pub project: Option<String>,
+    /// Include archived chat sessions
+    pub include_archived: bool,
+    /// Maximum number of hits to show
+    pub limit: Option<usize>,
+    /// Use (and refresh) the persistent search index
+    pub use_index: bool,
+}
+
+/// A single search hit
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    /// Project path (or remote path) the session belongs to
+    pub project: String,
+    /// Session UUID
+    pub session_id: String,
+    /// Session title if available
+    pub session_title: Option<String>,
+    /// Role of the matching message
+    pub role: String,
+    /// Message timestamp, falling back to the session update time
+    pub timestamp: Option<i64>,
+    /// Snippet surrounding the match
+    pub snippet: Snippet,
+}
+
+/// Text surrounding a match, split so the match can be highlighted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub before: String,
+    pub matched: String,
+    pub after: String,
+}
+
+/// Execute the search command
+pub fn execute(options: &SearchOptions) -> Result<()> {
+    let matcher = build_matcher(&options.query, options.regex, options.case_sensitive)?;
+    let hits = search(&matcher, options)?;
+
+    if hits.is_empty() {
+        println!("No matches found for: {}", options.query);
+        return Ok(());
+    }
+
+    for hit in &hits {
+        println!("{}", format_hit(hit));
+    }
+
+    println!("{} match(es) found", hits.len());
+
+    Ok(())
+}
+
+/// Build the matcher for a query
+fn build_matcher(query: &str, is_regex: bool, case_sensitive: bool) -> Result<Regex> {
+    let pattern = if is_regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .with_context(|| format!("Invalid search pattern: {}", query))
+}
+
+/// Search every workspace for messages matching `matcher`
+fn search(matcher: &Regex, options: &SearchOptions) -> Result<Vec<SearchHit>> {
+    let mut hits = Vec::new();
+
+    let workspace_storage_dir = config::workspace_storage_dir()
+        .context("Failed to determine workspace storage directory")?;
+    if !workspace_storage_dir.exists() {
+        return Ok(hits);
+    }
+
+    let Some(global_conn) = chat_sessions::open_global_state_db()? else {
+        return Ok(hits);
+    };
+
+    let index = if options.use_index {
+        let (index, stats) = search_index::open_refreshed(&global_conn)?;
+        if stats.updated > 0 || stats.removed > 0 {
+            eprintln!(
+                "Index refreshed: {} updated, {} removed",
+                stats.updated, stats.removed
+            );
+        }
+        Some(index)
+    } else {
+        None
+    };
+
+    // The FTS index only speeds up plain-text queries; regexes scan every session
+    let candidates = match index.as_ref() {
+        Some(index) if !options.regex => index.matching_sessions(&options.query)?,
+        _ => None,
+    };
+
+    // Match against full tool output, not the truncated export view
+    let export_options = ExportOptions {
+        with_thinking: true,
+        with_tools: true,
+        include_archived: options.include_archived,
+        tool_params_limit: None,
+        tool_result_limit: None,
+        ..ExportOptions::default()
+    };
+
+    for (workspace_dir, project) in workspace_dirs(&workspace_storage_dir)? {
+        if let Some(ref pattern) = options.project {
+            if !project.contains(pattern.as_str()) {
+                continue;
+            }
+        }
+
+        let Ok(sessions) =
+            chat_sessions::discover_workspace_sessions(&workspace_dir, options.include_archived)
+        else {
+            continue;
+        };
+
+        for session in sessions {
+            if let (Some(index), Some(ids)) = (index.as_ref(), candidates.as_ref()) {
+                if index.rules_out(ids, &session.composer_id) {
+                    continue;
+                }
+            }
+
+            let conn = search_index::session_connection(
+                index.as_ref(),
+                &global_conn,
+                &session.composer_id,
+            );
+            let messages =
+                export_chat::fetch_session_messages(conn, &session.composer_id, &export_options)
+                    .unwrap_or_default();
+
+            for message in &messages {
+                let text = searchable_text(message);
+                let Some(found) = matcher.find(&text) else {
+                    continue;
+                };
+
+                hits.push(SearchHit {
+                    project: project.clone(),
+                    session_id: session.composer_id.clone(),
+                    session_title: session.title.clone(),
+                    role: message.role.clone(),
+                    timestamp: message
+                        .timestamp
+                        .or(session.updated_at_ms.map(|ts| ts / 1000)),
+                    snippet: make_snippet(&text, found.start(), found.end()),
+                });
+
+                if options.limit.is_some_and(|limit| hits.len() >= limit) {
+                    return Ok(hits);
+                }
+            }
+        }
+    }
+
+    Ok(hits)
+}
+
+/// List workspace storage directories with a display path for their project
+fn workspace_dirs(workspace_storage_dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut dirs = Vec::new();
+
+    let entries = fs::read_dir(workspace_storage_dir)
+        .with_context(|| format!("Failed to read: {}", workspace_storage_dir.display()))?;
+
+    for entry in entries.flatten() {
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let workspace_json = entry.path().join("workspace.json");
+        let Ok(content) = fs::read_to_string(&workspace_json) else {
+            continue;
+        };
+        let Ok(ws) = serde_json::from_str::<serde_json::Value>(&content) else {
+            continue;
+        };
+        let Some(folder) = ws
+            .get("folder")
+            .or_else(|| ws.get("workspace"))
+            .and_then(|v| v.as_str())
+        else {
+            continue;
+        };
+
+        let project = list::parse_folder_url(folder)
+            .map(|parsed| match parsed.remote {
+                Some(remote) => format!(
+                    "{}:{}:{}",
+                    remote.remote_type,
+                    remote.name,
+                    parsed.path.display()
+                ),
+                None => parsed.path.display().to_string(),
+            })
+            .unwrap_or_else(|| folder.to_string());
+
+        dirs.push((entry.path(), project));
+    }
+
+    dirs.sort_by(|a, b| a.1.cmp(&b.1));
+
+    Ok(dirs)
+}
+
+/// Collect the text of a message that should be searched
+fn searchable_text(message: &ChatMessage) -> String {
+    let Some(ref tool) = message.tool_call else {
+        return message.content.clone();
+    };
+
+    let mut parts = vec![tool.name.as_str()];
+    if let Some(ref params) = tool.params {
+        parts.push(params);
+    }
+    if let Some(ref result) = tool.result {
+        parts.push(result);
+    }
+    parts.join("\n")
+}
+
+/// Cut a single-line snippet around the byte range `start..end` of `text`
+fn make_snippet(text: &str, start: usize, end: usize) -> Snippet {
+    let before: Vec<char> = text[..start].chars().collect();
+    let before_start = before.len().saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let mut before_str: String = before[before_start..].iter().collect();
+    if before_start > 0 {
+        before_str.insert_str(0, "...");
+    }
+
+    let after: Vec<char> = text[end..].chars().collect();
+    let after_end = after.len().min(SNIPPET_CONTEXT_CHARS);
+    let mut after_str: String = after[..after_end].iter().collect();
+    if after_end < after.len() {
+        after_str.push_str("...");
+    }
+
+    Snippet {
+        before: collapse_whitespace(&before_str),
+        matched: collapse_whitespace(&text[start..end]),
+        after: collapse_whitespace(&after_str),
+    }
+}
+
+/// Replace newlines and runs of whitespace with single spaces
+fn collapse_whitespace(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut prev_space = false;
+
+    for c in s.chars() {
+        if c.is_whitespace() {
+            if !prev_space {
+                result.push(' ');
+            }
+            prev_space = true;
+        } else {
+            result.push(c);
+            prev_space = false;
+        }
+    }
+
+    result
+}
+
+/// Format a hit for terminal display
+fn format_hit(hit: &SearchHit) -> String {
+    let title = hit.session_title.as_deref().unwrap_or("Untitled Session");
+    let timestamp = hit
+        .timestamp
+        .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "-".to_string());
+
+    format!(
+        "{} {} {}\n  [{}] {}{}{}\n  {}\n",
+        hit.project.cyan(),
+        "›".dimmed(),
+        title.bold(),
+        hit.role,
+        hit.snippet.before,
+        hit.snippet.matched.yellow().bold(),
+        hit.snippet.after,
+        format!("{} · {}", timestamp, hit.session_id).dimmed()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use export_chat::ToolCall;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: None,
+            thinking_duration_ms: None,
+            tool_call: None,
+            model: None,
+            tokens: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_build_matcher_literal_escapes_regex_chars() {
+        let matcher = build_matcher("fn main()", false, false).unwrap();
+        assert!(matcher.is_match("pub fn main() {}"));
+        assert!(!matcher.is_match("fn main"));
+    }
+
+    #[test]
+    fn test_build_matcher_case_options() {
+        let insensitive = build_matcher("Migration", false, false).unwrap();
+        assert!(insensitive.is_match("fix the migration bug"));
+
+        let sensitive = build_matcher("Migration", false, true).unwrap();
+        assert!(!sensitive.is_match("fix the migration bug"));
+    }
+
+    #[test]
+    fn test_build_matcher_regex() {
+        let matcher = build_matcher(r"migrat(e|ion)s?\b", true, false).unwrap();
+        assert!(matcher.is_match("run the migrations"));
+        assert!(build_matcher("(unclosed", true, false).is_err());
+    }
+
+    #[test]
+    fn test_make_snippet_short_text() {
+        let text = "fix the migration bug";
+        let start = text.find("migration").unwrap();
+        let snippet = make_snippet(text, start, start + "migration".len());
+        assert_eq!(
+            snippet,
+            Snippet {
+                before: "fix the ".to_string(),
+                matched: "migration".to_string(),
+                after: " bug".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_make_snippet_truncates_and_collapses_whitespace() {
+        let text = format!("{}\n\nneedle\n{}", "a".repeat(100), "b".repeat(100));
+        let start = text.find("needle").unwrap();
+        let snippet = make_snippet(&text, start, start + "needle".len());
+        assert!(snippet.before.starts_with("..."));
+        assert!(snippet.before.ends_with("a "));
+        assert!(snippet.after.starts_with(" b"));
+        assert!(snippet.after.ends_with("..."));
+        assert!(!snippet.before.contains('\n'));
+    }
+
+    #[test]
+    fn test_make_snippet_unicode() {
+        let text = "你好世界 needle 再见";
+        let start = text.find("needle").unwrap();
+        let snippet = make_snippet(text, start, start + "needle".len());
+        assert_eq!(snippet.before, "你好世界 ");
+        assert_eq!(snippet.after, " 再见");
+    }
+
+    #[test]
+    fn test_searchable_text_includes_tool_params_and_result() {
+        let mut msg = message("tool", "[run_terminal_cmd]");
+        msg.tool_call = Some(ToolCall {
+            name: "run_terminal_cmd".to_string(),
+            params: Some(r#"{"command":"cargo test"}"#.to_string()),
+            result: Some("test result: ok".to_string()),
+            status: None,
+            params_truncation: None,
+            result_truncation: None,
+            details: None,
+        });
+
+        let text = searchable_text(&msg);
+        assert!(text.contains("cargo test"));
+        assert!(text.contains("test result: ok"));
+    }
+}
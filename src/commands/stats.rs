@@ -1,14 +1,18 @@
 //! Stats command - Show usage statistics for a project
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 use std::path::PathBuf;
 
-use super::utils;
+use super::utils::{self, ReportFormat};
 use crate::config;
 use crate::cursor::folder_id;
 
 /// Usage statistics for a Cursor project
-#[derive(Debug, Default)]
+///
+/// Serialized as-is by `stats --format json|csv`; field names and order are
+/// part of that schema.
+#[derive(Debug, Default, Serialize)]
 pub struct Stats {
     /// Project path
     pub project_path: PathBuf,
@@ -82,6 +86,42 @@ pub fn stats(project_path: Option<PathBuf>) -> Result<Stats> {
     })
 }
 
+/// Format stats in the requested output format
+pub fn format_stats_as(stats: &Stats, format: ReportFormat) -> Result<String> {
+    match format {
+        ReportFormat::Table => Ok(format_stats(stats)),
+        ReportFormat::Json => {
+            serde_json::to_string_pretty(stats).context("Failed to serialize stats")
+        }
+        ReportFormat::Csv => {
+            let header = [
+                "project_path",
+                "chat_sessions",
+                "workspace_size",
+                "projects_size",
+                "folder_id",
+                "workspace_hash",
+            ];
+            let row = [
+                stats.project_path.to_string_lossy().to_string(),
+                stats
+                    .chat_sessions
+                    .map(|count| count.to_string())
+                    .unwrap_or_default(),
+                stats.workspace_size.to_string(),
+                stats.projects_size.to_string(),
+                stats.folder_id.clone(),
+                stats.workspace_hash.clone().unwrap_or_default(),
+            ];
+            Ok(format!(
+                "{}\n{}",
+                utils::csv_row(&header),
+                utils::csv_row(&row)
+            ))
+        }
+    }
+}
+
 /// Format stats for display
 pub fn format_stats(stats: &Stats) -> String {
     let mut lines = vec![];
@@ -146,4 +186,29 @@ mod tests {
         let formatted = format_stats(&stats);
         assert!(formatted.contains("Chat Sessions: unknown"));
     }
+
+    #[test]
+    fn test_format_stats_machine_readable() {
+        let stats = Stats {
+            project_path: PathBuf::from("/tmp/project"),
+            chat_sessions: Some(3),
+            workspace_size: 2048,
+            projects_size: 0,
+            folder_id: "tmp-project".to_string(),
+            workspace_hash: None,
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_stats_as(&stats, ReportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["project_path"], "/tmp/project");
+        assert_eq!(json["chat_sessions"], 3);
+        assert_eq!(json["workspace_size"], 2048);
+        assert!(json["workspace_hash"].is_null());
+
+        assert_eq!(
+            format_stats_as(&stats, ReportFormat::Csv).unwrap(),
+            "project_path,chat_sessions,workspace_size,projects_size,folder_id,workspace_hash\n\
+             /tmp/project,3,2048,0,tmp-project,"
+        );
+    }
 }
@@ -0,0 +1,353 @@
+//! Undo command - List and revert past rename, copy and clone operations
+//!
+//! Operations are read from the journals kept next to their safety backups.
+//! Undoing restores the pre-operation `workspaceStorage`, `~/.cursor/projects`,
+//! `storage.json` and global `state.vscdb` from the backup and moves a moved
+//! project folder back. Copied and cloned folders are left in place.
+
+use anyhow::{bail, Context, Result};
+use comfy_table::{presets::UTF8_FULL_CONDENSED, Cell, ContentArrangement, Table};
+use owo_colors::OwoColorize;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::rename::{self, Locations};
+use super::rename_journal::{self, JournalStatus, Operation, RenameJournal};
+use super::safety_backups;
+
+/// Table of recorded operations, newest first
+pub fn list() -> Result<String> {
+    let journals = rename_journal::list_journals(&rename_journal::backups_dir()?)?;
+    if journals.is_empty() {
+        return Ok("No rename, copy or clone operations recorded.".to_string());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        Cell::new("ID"),
+        Cell::new("Started"),
+        Cell::new("Finished"),
+        Cell::new("Operation"),
+        Cell::new("Status"),
+        Cell::new("Old path"),
+        Cell::new("New path"),
+    ]);
+
+    for journal in journals.iter().rev() {
+        table.add_row(vec![
+            Cell::new(match &journal.batch {
+                Some(batch) => format!("{} ({})", journal.id(), batch),
+                None => journal.id(),
+            }),
+            Cell::new(format_time(&journal.started_at)),
+            Cell::new(
+                journal
+                    .finished_at
+                    .as_deref()
+                    .map(format_time)
+                    .unwrap_or_default(),
+            ),
+            Cell::new(journal.kind()),
+            Cell::new(journal.status.label()),
+            Cell::new(journal.plan.old_path.display()),
+            Cell::new(journal.plan.new_path.display()),
+        ]);
+    }
+
+    Ok(format!(
+        "{}\n\n{} operation(s) recorded",
+        table,
+        journals.len()
+    ))
+}
+
+/// Undo the operation with `id`, or the most recent completed one
+///
+/// Renames from the same `rename --batch` are undone together.
+pub fn execute(id: Option<&str>, force: bool, dry_run: bool) -> Result<()> {
+    let mut journals = rename_journal::list_journals(&rename_journal::backups_dir()?)?;
+    let index = match id {
+        Some(id) => journals
+            .iter()
+            .position(|j| j.id() == id || j.batch.as_deref() == Some(id))
+            .with_context(|| format!("No recorded operation with ID: {}", id))?,
+        None => journals
+            .iter()
+            .rposition(|j| j.status == JournalStatus::Completed)
+            .context("No completed operation to undo")?,
+    };
+
+    let members: Vec<usize> = match &journals[index].batch {
+        Some(batch) => (0..journals.len())
+            .filter(|&i| journals[i].batch.as_ref() == Some(batch))
+            .collect(),
+        None => vec![index],
+    };
+
+    for journal in members.iter().map(|&i| &journals[i]) {
+        match journal.status {
+            JournalStatus::Completed => {}
+            JournalStatus::InProgress => bail!(
+                "Operation {} was interrupted. Use `cursor-helper rename --resume` or `--rollback`",
+                journal.id()
+            ),
+            status => bail!("Operation {} was already {}", journal.id(), status.label()),
+        }
+    }
+
+    // Restoring older snapshots would silently revert later operations
+    let last = *members.last().expect("at least one operation");
+    if let Some(newer) = journals[last + 1..].iter().rfind(|j| {
+        matches!(
+            j.status,
+            JournalStatus::Completed | JournalStatus::InProgress
+        )
+    }) {
+        bail!(
+            "A newer operation ({}, {}) depends on this one. Undo it first",
+            newer.id(),
+            newer.kind()
+        );
+    }
+
+    for journal in members.iter().map(|&i| &journals[i]) {
+        let moves_back = matches!(
+            (journal.operation, journal.plan.copy_mode),
+            (Operation::Rename, false)
+        ) && !journal.plan.folder_moved;
+        if moves_back && journal.plan.old_path.exists() {
+            bail!(
+                "Cannot move the project back: {} already exists",
+                journal.plan.old_path.display()
+            );
+        }
+    }
+
+    if !dry_run && rename::is_cursor_running() {
+        bail!("Cursor is running. Please close it completely before running this script.");
+    }
+
+    let locations = Locations::from_config()?;
+    let finished_at = journals[last].finished_at.clone();
+    let mut conflicts = Vec::new();
+    for &i in &members {
+        for conflict in find_conflicts(&journals[i], finished_at.as_deref(), &locations) {
+            if !conflicts.contains(&conflict) {
+                conflicts.push(conflict);
+            }
+        }
+    }
+
+    let first = &journals[members[0]];
+    println!();
+    match &first.batch {
+        Some(batch) => println!(
+            "Undo batch {} ({} {}s) started at {}",
+            batch,
+            members.len(),
+            first.kind(),
+            format_time(&first.started_at)
+        ),
+        None => println!(
+            "Undo {} started at {}",
+            first.kind(),
+            format_time(&first.started_at)
+        ),
+    }
+    for journal in members.iter().map(|&i| &journals[i]) {
+        println!("Old path: {}", journal.plan.old_path.display());
+        println!("New path: {}", journal.plan.new_path.display());
+        if let Some(backup_dir) = &journal.backup_dir {
+            println!("Backup: {}", backup_dir.display());
+        }
+    }
+    println!();
+
+    if !conflicts.is_empty() {
+        println!(
+            "{}",
+            "Changed since the operation (would be overwritten):".yellow()
+        );
+        for conflict in &conflicts {
+            println!("  - {}", conflict);
+        }
+        println!();
+        if !force && !dry_run {
+            bail!("Refusing to overwrite newer data. Use --force to undo anyway");
+        }
+    }
+
+    if dry_run {
+        println!("{}", "(DRY-RUN) Would undo:".blue());
+        for journal in members.iter().rev().map(|&i| &journals[i]) {
+            if members.len() > 1 {
+                println!("  {}", journal.plan.new_path.display());
+            }
+            for entry in journal.entries.iter().rev() {
+                println!(
+                    "  Step {}: {}",
+                    entry.step.number(),
+                    entry.step.description()
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    if !rename::confirm("Undo?")? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    println!("{}", "Undoing...".yellow());
+    let kind = first.kind();
+    let mut undone = Vec::new();
+    for &i in members.iter().rev() {
+        let journal = &mut journals[i];
+        rename::roll_back(journal, &locations, false)?;
+        undone.push(journal.plan.old_path.display().to_string());
+    }
+
+    println!();
+    println!("{}", "=== Undo complete! ===".green());
+    println!();
+    for old_path in undone.iter().rev() {
+        println!(
+            "Cursor data for {} is back to its state before the {}.",
+            old_path, kind
+        );
+    }
+    Ok(())
+}
+
+/// Data the undo would overwrite that changed after the operation finished
+///
+/// `finished_at` is when the operation (or the batch it belongs to) finished.
+fn find_conflicts(
+    journal: &RenameJournal,
+    finished_at: Option<&str>,
+    locations: &Locations,
+) -> Vec<String> {
+    let mut conflicts = Vec::new();
+    let (Some(backup_dir), Some(finished)) = (
+        journal.backup_dir.as_ref(),
+        finished_at
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(SystemTime::from),
+    ) else {
+        return conflicts;
+    };
+    let plan = &journal.plan;
+
+    let mut check = |path: &Path| {
+        if modified_after(path, finished) {
+            conflicts.push(format!("{} was modified", path.display()));
+        }
+    };
+
+    if backup_dir.join("storage.json").exists() {
+        check(&locations.storage_json_path);
+    }
+    if safety_backups::global_db_snapshot(backup_dir).is_some() {
+        check(&locations.global_state_db_path);
+        check(&locations.global_state_db_path.with_extension("vscdb-wal"));
+    }
+    if let Some(hash) = &plan.new_workspace_hash {
+        check(
+            &locations
+                .workspace_storage_dir
+                .join(hash)
+                .join("state.vscdb"),
+        );
+    }
+
+    // A moved workspace directory Cursor has since recreated at the old hash
+    let old_workspace_dir = locations
+        .workspace_storage_dir
+        .join(&plan.old_workspace_hash);
+    if !plan.copy_mode
+        && backup_dir.join("old_workspace").exists()
+        && old_workspace_dir.exists()
+        && plan.new_workspace_hash.as_deref() != Some(plan.old_workspace_hash.as_str())
+    {
+        conflicts.push(format!("{} exists again", old_workspace_dir.display()));
+    }
+
+    conflicts
+}
+
+fn modified_after(path: &Path, time: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(|modified| modified > time)
+        .unwrap_or(false)
+}
+
+fn format_time(rfc3339: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(rfc3339)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| rfc3339.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::rename::RenamePlan;
+    use crate::commands::rename_journal::Operation;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_conflicts_reports_data_modified_after_operation() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let backup_dir = root.join("backup");
+        fs::create_dir_all(&backup_dir).unwrap();
+        fs::write(backup_dir.join("storage.json"), "{}").unwrap();
+        fs::create_dir_all(backup_dir.join("old_workspace")).unwrap();
+
+        let locations = Locations {
+            projects_dir: root.join("projects"),
+            workspace_storage_dir: root.join("workspaceStorage"),
+            storage_json_path: root.join("storage.json"),
+            global_state_db_path: root.join("state.vscdb"),
+        };
+        fs::write(&locations.storage_json_path, "{}").unwrap();
+        fs::write(&locations.global_state_db_path, "").unwrap();
+        fs::create_dir_all(locations.workspace_storage_dir.join("hash_old")).unwrap();
+
+        let plan = RenamePlan {
+            old_path: PathBuf::from("/old/project"),
+            new_path: PathBuf::from("/new/project"),
+            copy_mode: false,
+            force_index: false,
+            old_folder_id: "old-project".to_string(),
+            new_folder_id: "new-project".to_string(),
+            old_workspace_hash: "hash_old".to_string(),
+            new_workspace_hash: Some("hash_new".to_string()),
+            new_projects_dir_existed: false,
+            new_workspace_dir_existed: false,
+            merge_existing: false,
+            folder_moved: false,
+            multi_root: Vec::new(),
+        };
+        let mut journal = RenameJournal::new(Operation::Rename, plan, Some(backup_dir)).unwrap();
+
+        journal.finished_at = Some("2020-01-01T00:00:00Z".to_string());
+        let conflicts = find_conflicts(&journal, journal.finished_at.as_deref(), &locations);
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts[0].contains("storage.json was modified"));
+        assert!(conflicts[1].contains("hash_old exists again"));
+
+        // The global DB had no snapshot, so it is not restored or checked
+        journal.finished_at = Some("2999-01-01T00:00:00Z".to_string());
+        assert_eq!(
+            find_conflicts(&journal, journal.finished_at.as_deref(), &locations).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,632 @@
+//! Usage command - Token usage and estimated spend across all chat history
+//!
+//! Cursor records input/output token counts on each assistant bubble. This
+//! command sums them per model, session, project, or day, and can price them
+//! with a user-supplied table of per-million-token rates.
+
+use anyhow::{Context, Result};
+use comfy_table::{presets::UTF8_FULL_CONDENSED, Cell, CellAlignment, ContentArrangement, Table};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use super::list;
+use super::utils::{self, ReportFormat};
+use crate::config;
+use crate::cursor::chat_sessions::{self, SessionFilter};
+use crate::cursor::search_index;
+
+/// Model name used for bubbles that do not record one
+const UNKNOWN_MODEL: &str = "unknown";
+
+/// How usage is grouped into report rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGroup {
+    Model,
+    Session,
+    Project,
+    Day,
+}
+
+impl UsageGroup {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "model" => Some(Self::Model),
+            "session" => Some(Self::Session),
+            "project" => Some(Self::Project),
+            "day" | "date" => Some(Self::Day),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Model => "model",
+            Self::Session => "session",
+            Self::Project => "project",
+            Self::Day => "day",
+        }
+    }
+}
+
+/// Options for the usage command
+#[derive(Debug, Clone)]
+pub struct UsageOptions {
+    /// Grouping of report rows
+    pub group_by: UsageGroup,
+    /// Date range filter (sessions outside it are skipped, and so are
+    /// individual messages with a timestamp outside it)
+    pub filter: SessionFilter,
+    /// Only include projects whose path contains this pattern
+    pub project: Option<String>,
+    /// Include archived chat sessions
+    pub include_archived: bool,
+    /// Price table used to estimate spend
+    pub prices: Option<PriceTable>,
+}
+
+/// Prices per million tokens, loaded from a TOML file
+///
+/// ```toml
+/// currency = "USD"
+///
+/// [models."claude-4-sonnet"]
+/// input = 3.0
+/// output = 15.0
+///
+/// [models."gpt-5*"]       # trailing '*' matches by prefix
+/// input = 1.25
+/// output = 10.0
+///
+/// [default]               # optional fallback for unlisted models
+/// input = 1.0
+/// output = 5.0
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PriceTable {
+    /// Currency label shown in reports
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// Prices keyed by model name or `prefix*` pattern
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelPrice>,
+    /// Price for models not matched by `models`
+    #[serde(default)]
+    pub default: Option<ModelPrice>,
+}
+
+/// Price of one million input and output tokens
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl PriceTable {
+    /// Load a price table from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read price table: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse price table: {}", path.display()))
+    }
+
+    /// Find the price for a model: exact match, then the longest matching
+    /// `prefix*` pattern, then the default
+    pub fn price_for(&self, model: &str) -> Option<ModelPrice> {
+        if let Some(price) = self.models.get(model) {
+            return Some(*price);
+        }
+
+        self.models
+            .iter()
+            .filter_map(|(pattern, price)| {
+                let prefix = pattern.strip_suffix('*')?;
+                model.starts_with(prefix).then_some((prefix.len(), price))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, price)| *price)
+            .or(self.default)
+    }
+
+    fn currency(&self) -> &str {
+        self.currency.as_deref().unwrap_or("USD")
+    }
+}
+
+/// Tokens recorded on a single bubble
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BubbleUsage {
+    model: String,
+    input_tokens: i64,
+    output_tokens: i64,
+    /// Bubble creation time in milliseconds
+    timestamp_ms: Option<i64>,
+}
+
+/// Tokens recorded on a single bubble, attributed to a session and project
+#[derive(Debug, Clone)]
+pub struct UsageEntry {
+    pub project: String,
+    pub session_id: String,
+    pub session_title: Option<String>,
+    pub model: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Bubble creation time in milliseconds
+    pub timestamp_ms: Option<i64>,
+}
+
+/// One row of the usage report
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageRow {
+    /// Model name, session ID, project path, or day (YYYY-MM-DD)
+    pub key: String,
+    /// Session title (session grouping only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Project path (session grouping only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Number of bubbles with token counts
+    pub messages: usize,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+    /// Estimated cost (only with a price table)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+}
+
+/// Aggregated usage report
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    /// Grouping of `rows`
+    pub group_by: &'static str,
+    /// Currency of `cost` values (only with a price table)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    pub rows: Vec<UsageRow>,
+    pub totals: UsageRow,
+    /// Models that had no price in the price table
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unpriced_models: Vec<String>,
+}
+
+/// Execute the usage command and return formatted output
+pub fn execute(options: &UsageOptions, format: ReportFormat) -> Result<String> {
+    let entries = collect_usage(options)?;
+    let report = aggregate(&entries, options.group_by, options.prices.as_ref());
+
+    if !report.unpriced_models.is_empty() {
+        eprintln!(
+            "Warning: No price for model(s): {}",
+            report.unpriced_models.join(", ")
+        );
+    }
+
+    match format {
+        ReportFormat::Table => Ok(format_table(&report)),
+        ReportFormat::Csv => Ok(format_csv(&report)),
+        ReportFormat::Json => {
+            serde_json::to_string_pretty(&report).context("Failed to serialize usage report")
+        }
+    }
+}
+
+/// Read token counts from every matching session in every workspace
+///
+/// Sub-agent sessions are counted toward the session that spawned them.
+fn collect_usage(options: &UsageOptions) -> Result<Vec<UsageEntry>> {
+    let mut entries = Vec::new();
+
+    let workspace_storage_dir = config::workspace_storage_dir()
+        .context("Failed to determine workspace storage directory")?;
+    let Some(global_conn) = chat_sessions::open_global_state_db()? else {
+        return Ok(entries);
+    };
+
+    for project in list::discover_projects()? {
+        let project_path = project.display_path();
+        if let Some(ref pattern) = options.project {
+            if !project_path.contains(pattern.as_str()) {
+                continue;
+            }
+        }
+
+        let workspace_dir = workspace_storage_dir.join(&project.folder_id);
+        let sessions = match chat_sessions::discover_workspace_sessions(
+            &workspace_dir,
+            options.include_archived,
+        ) {
+            Ok(sessions) => sessions,
+            Err(err) => {
+                eprintln!("Warning: Skipping {}: {}", project_path, err);
+                continue;
+            }
+        };
+
+        for session in options.filter.apply(sessions) {
+            let mut pending = vec![session.composer_id.clone()];
+            let mut visited = HashSet::new();
+
+            while let Some(composer_id) = pending.pop() {
+                if !visited.insert(composer_id.clone()) {
+                    continue;
+                }
+
+                for usage in session_usage(&global_conn, &composer_id)? {
+                    if !in_range(usage.timestamp_ms, &options.filter) {
+                        continue;
+                    }
+                    entries.push(UsageEntry {
+                        project: project_path.clone(),
+                        session_id: session.composer_id.clone(),
+                        session_title: session.title.clone(),
+                        model: usage.model,
+                        input_tokens: usage.input_tokens,
+                        output_tokens: usage.output_tokens,
+                        timestamp_ms: usage.timestamp_ms.or(session.created_at_ms),
+                    });
+                }
+
+                let children = chat_sessions::load_child_sessions(&global_conn, &composer_id)
+                    .unwrap_or_default();
+                pending.extend(children.into_iter().map(|child| child.composer_id));
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Whether a message timestamp falls inside the filter's date range
+fn in_range(timestamp_ms: Option<i64>, filter: &SessionFilter) -> bool {
+    let Some(ts) = timestamp_ms else {
+        return true;
+    };
+    filter.since_ms.is_none_or(|since| ts >= since)
+        && filter.until_ms.is_none_or(|until| ts <= until)
+}
+
+/// Token usage of every bubble in a session
+fn session_usage(conn: &Connection, composer_id: &str) -> Result<Vec<BubbleUsage>> {
+    let (lower, upper) = search_index::bubble_key_range(composer_id);
+    let mut stmt = conn
+        .prepare("SELECT value FROM cursorDiskKV WHERE key >= ?1 AND key < ?2")
+        .context("Failed to prepare bubble query")?;
+    let mut rows = stmt.query(params![lower, upper])?;
+
+    let mut usage = Vec::new();
+    while let Some(row) = rows.next()? {
+        let Ok(value) = row.get::<_, String>(0) else {
+            continue;
+        };
+        let Ok(bubble) = serde_json::from_str::<Value>(&value) else {
+            continue;
+        };
+        usage.extend(bubble_usage(&bubble));
+    }
+
+    Ok(usage)
+}
+
+/// Token usage of a bubble, if it recorded any
+fn bubble_usage(bubble: &Value) -> Option<BubbleUsage> {
+    let tokens = bubble.get("tokenCount")?;
+    let input = tokens
+        .get("inputTokens")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    let output = tokens
+        .get("outputTokens")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0);
+    if input <= 0 && output <= 0 {
+        return None;
+    }
+
+    let model = bubble
+        .get("modelInfo")
+        .and_then(|m| m.get("modelName"))
+        .and_then(|v| v.as_str())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(UNKNOWN_MODEL)
+        .to_string();
+    let timestamp_ms = bubble
+        .get("createdAt")
+        .and_then(|v| v.as_str())
+        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.timestamp_millis());
+
+    Some(BubbleUsage {
+        model,
+        input_tokens: input,
+        output_tokens: output,
+        timestamp_ms,
+    })
+}
+
+/// Sum entries into report rows, most tokens first
+pub fn aggregate(
+    entries: &[UsageEntry],
+    group_by: UsageGroup,
+    prices: Option<&PriceTable>,
+) -> UsageReport {
+    let mut rows: HashMap<String, UsageRow> = HashMap::new();
+    let mut totals = UsageRow {
+        key: "total".to_string(),
+        cost: prices.map(|_| 0.0),
+        ..UsageRow::default()
+    };
+    let mut unpriced = BTreeSet::new();
+
+    for entry in entries {
+        let key = match group_by {
+            UsageGroup::Model => entry.model.clone(),
+            UsageGroup::Session => entry.session_id.clone(),
+            UsageGroup::Project => entry.project.clone(),
+            UsageGroup::Day => entry
+                .timestamp_ms
+                .and_then(chrono::DateTime::from_timestamp_millis)
+                .map(|dt| dt.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+        };
+
+        let cost = prices.map(|table| match table.price_for(&entry.model) {
+            Some(price) => {
+                (entry.input_tokens as f64 * price.input
+                    + entry.output_tokens as f64 * price.output)
+                    / 1_000_000.0
+            }
+            None => {
+                unpriced.insert(entry.model.clone());
+                0.0
+            }
+        });
+
+        let row = rows.entry(key.clone()).or_insert_with(|| UsageRow {
+            key,
+            title: (group_by == UsageGroup::Session)
+                .then(|| entry.session_title.clone())
+                .flatten(),
+            project: (group_by == UsageGroup::Session).then(|| entry.project.clone()),
+            cost: prices.map(|_| 0.0),
+            ..UsageRow::default()
+        });
+
+        for target in [row, &mut totals] {
+            target.messages += 1;
+            target.input_tokens += entry.input_tokens;
+            target.output_tokens += entry.output_tokens;
+            target.total_tokens += entry.input_tokens + entry.output_tokens;
+            if let (Some(sum), Some(cost)) = (target.cost.as_mut(), cost) {
+                *sum += cost;
+            }
+        }
+    }
+
+    let mut rows: Vec<UsageRow> = rows.into_values().collect();
+    match group_by {
+        UsageGroup::Day => rows.sort_by(|a, b| a.key.cmp(&b.key)),
+        _ => rows.sort_by(|a, b| {
+            b.total_tokens
+                .cmp(&a.total_tokens)
+                .then_with(|| a.key.cmp(&b.key))
+        }),
+    }
+
+    UsageReport {
+        group_by: group_by.name(),
+        currency: prices.map(|table| table.currency().to_string()),
+        rows,
+        totals,
+        unpriced_models: unpriced.into_iter().collect(),
+    }
+}
+
+/// Column headers for a report, in display order
+fn headers(report: &UsageReport) -> Vec<String> {
+    let mut headers = vec![report.group_by.to_string()];
+    if report.group_by == UsageGroup::Session.name() {
+        headers.push("title".to_string());
+        headers.push("project".to_string());
+    }
+    headers.extend(["messages", "input_tokens", "output_tokens", "total_tokens"].map(String::from));
+    if let Some(ref currency) = report.currency {
+        headers.push(format!("cost_{}", currency.to_lowercase()));
+    }
+    headers
+}
+
+/// Field values of a row, matching `headers`
+fn row_fields(report: &UsageReport, row: &UsageRow) -> Vec<String> {
+    let mut fields = vec![row.key.clone()];
+    if report.group_by == UsageGroup::Session.name() {
+        fields.push(row.title.clone().unwrap_or_default());
+        fields.push(row.project.clone().unwrap_or_default());
+    }
+    fields.push(row.messages.to_string());
+    fields.push(row.input_tokens.to_string());
+    fields.push(row.output_tokens.to_string());
+    fields.push(row.total_tokens.to_string());
+    if let Some(cost) = row.cost {
+        fields.push(format!("{:.4}", cost));
+    }
+    fields
+}
+
+/// Format a report as a table with a totals row
+pub fn format_table(report: &UsageReport) -> String {
+    if report.rows.is_empty() {
+        return "No token usage found".to_string();
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    let headers = headers(report);
+    table.set_header(headers.iter().map(|h| Cell::new(h.replace('_', " "))));
+
+    let numeric_from = if report.group_by == UsageGroup::Session.name() {
+        3
+    } else {
+        1
+    };
+    let mut add_row = |fields: Vec<String>| {
+        table.add_row(fields.into_iter().enumerate().map(|(i, field)| {
+            let cell = Cell::new(field);
+            if i >= numeric_from {
+                cell.set_alignment(CellAlignment::Right)
+            } else {
+                cell
+            }
+        }));
+    };
+
+    for row in &report.rows {
+        add_row(row_fields(report, row));
+    }
+    add_row(row_fields(report, &report.totals));
+
+    table.to_string()
+}
+
+/// Format a report as CSV (no totals row, so the output sums cleanly)
+pub fn format_csv(report: &UsageReport) -> String {
+    let mut lines = vec![utils::csv_row(&headers(report))];
+    for row in &report.rows {
+        lines.push(utils::csv_row(&row_fields(report, row)));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(model: &str, session: &str, project: &str, input: i64, output: i64) -> UsageEntry {
+        UsageEntry {
+            project: project.to_string(),
+            session_id: session.to_string(),
+            session_title: Some(format!("Title {}", session)),
+            model: model.to_string(),
+            input_tokens: input,
+            output_tokens: output,
+            // 2026-01-01T00:00:00Z
+            timestamp_ms: Some(1_767_225_600_000),
+        }
+    }
+
+    fn prices() -> PriceTable {
+        toml::from_str(
+            r#"
+            [models."claude-4-sonnet"]
+            input = 3.0
+            output = 15.0
+
+            [models."gpt-*"]
+            input = 1.0
+            output = 2.0
+
+            [models."gpt-5*"]
+            input = 1.25
+            output = 10.0
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_price_for_prefers_exact_then_longest_prefix() {
+        let table = prices();
+        assert_eq!(table.price_for("claude-4-sonnet").unwrap().input, 3.0);
+        assert_eq!(table.price_for("gpt-5-codex").unwrap().input, 1.25);
+        assert_eq!(table.price_for("gpt-4o").unwrap().input, 1.0);
+        assert_eq!(table.price_for("o3"), None);
+        assert_eq!(table.currency(), "USD");
+    }
+
+    #[test]
+    fn test_aggregate_by_model_with_prices() {
+        let entries = vec![
+            entry("claude-4-sonnet", "s1", "/a", 1_000_000, 100_000),
+            entry("claude-4-sonnet", "s2", "/b", 1_000_000, 0),
+            entry("o3", "s2", "/b", 10, 20),
+        ];
+        let report = aggregate(&entries, UsageGroup::Model, Some(&prices()));
+
+        assert_eq!(report.rows.len(), 2);
+        assert_eq!(report.rows[0].key, "claude-4-sonnet");
+        assert_eq!(report.rows[0].messages, 2);
+        assert_eq!(report.rows[0].input_tokens, 2_000_000);
+        assert!((report.rows[0].cost.unwrap() - 7.5).abs() < 1e-9);
+        assert_eq!(report.totals.total_tokens, 2_100_030);
+        assert_eq!(report.unpriced_models, vec!["o3"]);
+        assert_eq!(report.currency.as_deref(), Some("USD"));
+    }
+
+    #[test]
+    fn test_aggregate_by_session_and_day() {
+        let entries = vec![
+            entry("m", "s1", "/a", 5, 5),
+            entry("m", "s2", "/b", 50, 50),
+            entry("m", "s1", "/a", 5, 5),
+        ];
+
+        let report = aggregate(&entries, UsageGroup::Session, None);
+        assert_eq!(report.rows[0].key, "s2");
+        assert_eq!(report.rows[1].total_tokens, 20);
+        assert_eq!(report.rows[1].title.as_deref(), Some("Title s1"));
+        assert_eq!(report.rows[1].project.as_deref(), Some("/a"));
+        assert!(report.totals.cost.is_none());
+
+        let report = aggregate(&entries, UsageGroup::Day, None);
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].key, "2026-01-01");
+    }
+
+    #[test]
+    fn test_format_csv_and_json() {
+        let entries = vec![entry("claude-4-sonnet", "s1", "/a, b", 1_000_000, 0)];
+        let report = aggregate(&entries, UsageGroup::Project, Some(&prices()));
+
+        assert_eq!(
+            format_csv(&report),
+            "project,messages,input_tokens,output_tokens,total_tokens,cost_usd\n\
+             \"/a, b\",1,1000000,0,1000000,3.0000"
+        );
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["group_by"], "project");
+        assert_eq!(json["rows"][0]["key"], "/a, b");
+        assert_eq!(json["totals"]["cost"], 3.0);
+        assert!(json.get("unpriced_models").is_none());
+    }
+
+    #[test]
+    fn test_bubble_usage() {
+        let bubble = serde_json::json!({
+            "tokenCount": {"inputTokens": 120, "outputTokens": 30},
+            "modelInfo": {"modelName": "gpt-5"},
+            "createdAt": "2026-01-01T00:00:01.000Z"
+        });
+        assert_eq!(
+            bubble_usage(&bubble),
+            Some(BubbleUsage {
+                model: "gpt-5".to_string(),
+                input_tokens: 120,
+                output_tokens: 30,
+                timestamp_ms: Some(1_767_225_601_000),
+            })
+        );
+
+        let empty = serde_json::json!({"tokenCount": {"inputTokens": 0, "outputTokens": 0}});
+        assert_eq!(bubble_usage(&empty), None);
+    }
+}
@@ -1,10 +1,13 @@
 //! Shared utilities for commands
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
 use fs_extra::dir::{self, CopyOptions};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::cursor::code_workspace::{self, CodeWorkspace};
+
 /// Format bytes as human-readable size
 pub fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -22,6 +25,33 @@ pub fn format_size(bytes: u64) -> String {
     }
 }
 
+/// Parse a size like `500MB`, `1.5G` or `2048` (bytes)
+///
+/// Units are binary to match [`format_size`]: `K`/`KB`, `M`/`MB`, `G`/`GB`
+/// and `T`/`TB` are powers of 1024. Case and a space before the unit are
+/// ignored.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => bail!("Unknown size unit in: {} (use B, KB, MB, GB or TB)", input),
+    };
+    let amount: f64 = amount
+        .parse()
+        .with_context(|| format!("Invalid size: {} (use e.g. 500MB or 2GB)", input))?;
+
+    Ok((amount * multiplier as f64) as u64)
+}
+
 /// Strip Windows extended-length path prefix (\\?\)
 ///
 /// On Windows, `canonicalize()` returns paths like `\\?\C:\path` which don't
@@ -81,6 +111,9 @@ pub fn calculate_dir_size(path: &Path) -> Result<u64> {
 ///
 /// Supports both local paths and remote paths:
 /// - Local: matches file:// URLs in workspace.json
+/// - Multi-root: a `.code-workspace` file matches its own workspace, and a
+///   folder with no workspace of its own matches a multi-root workspace that
+///   contains it
 /// - Remote: if path doesn't exist locally, searches vscode-remote:// URLs for matching path component
 pub fn find_workspace_dir(project_path: &Path) -> Result<Option<std::path::PathBuf>> {
     let workspace_storage_dir = crate::config::workspace_storage_dir()?;
@@ -95,6 +128,7 @@ pub fn find_workspace_dir(project_path: &Path) -> Result<Option<std::path::PathB
             .map_err(|_| anyhow::anyhow!("Invalid project path"))?
             .to_string();
         let project_uri_normalized = normalize_uri_for_comparison(&project_uri);
+        let mut multi_root_match = None;
 
         // Scan workspace storage for matching local project
         for entry in fs::read_dir(&workspace_storage_dir)?.flatten() {
@@ -116,6 +150,24 @@ pub fn find_workspace_dir(project_path: &Path) -> Result<Option<std::path::PathB
                     return Ok(Some(entry.path()));
                 }
             }
+
+            if let Some(workspace) = ws.get("workspace").and_then(|v| v.as_str()) {
+                if normalize_uri_for_comparison(workspace) == project_uri_normalized {
+                    return Ok(Some(entry.path()));
+                }
+                if multi_root_match.is_none() {
+                    let contains_project = code_workspace::config_path_from_uri(workspace)
+                        .and_then(|config_path| CodeWorkspace::read(&config_path).ok())
+                        .is_some_and(|ws| ws.contains_folder(project_path));
+                    if contains_project {
+                        multi_root_match = Some(entry.path());
+                    }
+                }
+            }
+        }
+
+        if multi_root_match.is_some() {
+            return Ok(multi_root_match);
         }
     }
 
@@ -175,10 +227,120 @@ fn normalize_uri_for_comparison(uri: &str) -> String {
     uri.trim_end_matches('/').to_lowercase().replace("%3a", ":")
 }
 
+/// Parse a time bound given as a relative age or an absolute date/time.
+///
+/// Accepts relative ages like `30m`, `12h`, `7d` or `2w` (counted back from
+/// `now`), dates like `2026-01-31`, naive times like `2026-01-31T09:00`, and
+/// RFC 3339 timestamps. Dates and naive times are interpreted as UTC. With
+/// `end_of_day`, a bare date resolves to the last millisecond of that day so it
+/// can be used as an inclusive upper bound. Returns unix milliseconds.
+pub fn parse_time_bound(input: &str, now: DateTime<Utc>, end_of_day: bool) -> Result<i64> {
+    let input = input.trim();
+
+    if let Some(unit) = input.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        let amount = &input[..input.len() - 1];
+        if let Ok(amount) = amount.parse::<i64>() {
+            let duration = match unit {
+                's' => TimeDelta::try_seconds(amount),
+                'm' => TimeDelta::try_minutes(amount),
+                'h' => TimeDelta::try_hours(amount),
+                'd' => TimeDelta::try_days(amount),
+                'w' => TimeDelta::try_weeks(amount),
+                _ => bail!(
+                    "Unknown time unit '{}' in: {} (use s, m, h, d, or w)",
+                    unit,
+                    input
+                ),
+            };
+            let Some(bound) = duration.and_then(|duration| now.checked_sub_signed(duration)) else {
+                bail!("Time out of range: {}", input);
+            };
+            return Ok(bound.timestamp_millis());
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        let time = if end_of_day {
+            NaiveTime::from_hms_milli_opt(23, 59, 59, 999)
+        } else {
+            NaiveTime::from_hms_opt(0, 0, 0)
+        }
+        .expect("valid time of day");
+        return Ok(date.and_time(time).and_utc().timestamp_millis());
+    }
+
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(input, format) {
+            return Ok(datetime.and_utc().timestamp_millis());
+        }
+    }
+
+    DateTime::parse_from_rfc3339(input)
+        .map(|dt| dt.timestamp_millis())
+        .with_context(|| {
+            format!(
+                "Invalid time: {} (use e.g. 7d, 12h, 2026-01-31, or 2026-01-31T09:00)",
+                input
+            )
+        })
+}
+
+/// Output format for tabular reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "table" => Some(Self::Table),
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Join fields into one CSV line, quoting fields that contain separators,
+/// quotes, or line breaks (RFC 4180)
+pub fn csv_row<S: AsRef<str>>(fields: &[S]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_csv_row_quotes_when_needed() {
+        assert_eq!(csv_row(&["a", "b c", "1"]), "a,b c,1");
+        assert_eq!(
+            csv_row(&["x,y", "say \"hi\"", "two\nlines"]),
+            "\"x,y\",\"say \"\"hi\"\"\",\"two\nlines\""
+        );
+        assert_eq!(ReportFormat::from_str("CSV"), Some(ReportFormat::Csv));
+        assert_eq!(ReportFormat::from_str("xml"), None);
+    }
+
     #[test]
     fn test_format_size() {
         assert_eq!(format_size(0), "0 B");
@@ -247,4 +409,58 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
     }
+
+    #[test]
+    fn test_parse_time_bound_relative() {
+        let now = DateTime::from_timestamp(1_000_000, 0).unwrap();
+        assert_eq!(
+            parse_time_bound("7d", now, false).unwrap(),
+            (1_000_000 - 7 * 86400) * 1000
+        );
+        assert_eq!(
+            parse_time_bound("2h", now, true).unwrap(),
+            (1_000_000 - 7200) * 1000
+        );
+        assert_eq!(
+            parse_time_bound("1w", now, false).unwrap(),
+            (1_000_000 - 604_800) * 1000
+        );
+        assert!(parse_time_bound("3y", now, false).is_err());
+        // Out-of-range ages are errors, not panics
+        assert!(parse_time_bound("99999999999999d", now, false).is_err());
+        assert!(parse_time_bound("9223372036854775807s", now, false).is_err());
+    }
+
+    #[test]
+    fn test_parse_time_bound_absolute() {
+        let now = Utc::now();
+        // 2024-01-01 00:00:00 UTC
+        assert_eq!(
+            parse_time_bound("2024-01-01", now, false).unwrap(),
+            1_704_067_200_000
+        );
+        assert_eq!(
+            parse_time_bound("2024-01-01", now, true).unwrap(),
+            1_704_153_599_999
+        );
+        assert_eq!(
+            parse_time_bound("2024-01-01T01:00", now, false).unwrap(),
+            1_704_070_800_000
+        );
+        assert_eq!(
+            parse_time_bound("2024-01-01T02:00:00+01:00", now, false).unwrap(),
+            1_704_070_800_000
+        );
+        assert!(parse_time_bound("last tuesday", now, false).is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("2048").unwrap(), 2048);
+        assert_eq!(parse_size("500MB").unwrap(), 500 * 1024 * 1024);
+        assert_eq!(parse_size("1.5g").unwrap(), 3 * 512 * 1024 * 1024);
+        assert_eq!(parse_size("10 KiB").unwrap(), 10 * 1024);
+        assert!(parse_size("5 parsecs").is_err());
+        assert!(parse_size("GB").is_err());
+    }
 }
@@ -55,6 +55,24 @@ pub fn cursor_cache_dirs() -> Result<Vec<PathBuf>> {
     Ok(vec![base.join("CachedData"), base.join("GPUCache")])
 }
 
+/// Get the cursor-helper cache directory
+/// - macOS: ~/Library/Caches/cursor-helper/
+/// - Linux: ~/.cache/cursor-helper/
+/// - Windows: %LOCALAPPDATA%/cursor-helper/
+pub fn cursor_helper_cache_dir() -> Result<PathBuf> {
+    let cache = dirs::cache_dir().context("Could not determine cache directory")?;
+    Ok(cache.join("cursor-helper"))
+}
+
+/// Get the cursor-helper data directory (safety backups and journals)
+/// - macOS: ~/Library/Application Support/cursor-helper/
+/// - Linux: ~/.local/share/cursor-helper/
+/// - Windows: %LOCALAPPDATA%/cursor-helper/
+pub fn cursor_helper_data_dir() -> Result<PathBuf> {
+    let data = dirs::data_local_dir().context("Could not determine data directory")?;
+    Ok(data.join("cursor-helper"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,6 +127,12 @@ mod tests {
         assert_eq!(components[len - 2].as_os_str().to_string_lossy(), "User");
     }
 
+    #[test]
+    fn test_cursor_helper_cache_dir_structure() {
+        let path = cursor_helper_cache_dir().unwrap();
+        assert_eq!(path.file_name().unwrap().to_string_lossy(), "cursor-helper");
+    }
+
     #[test]
     fn test_paths_share_common_base() {
         // workspace_storage_dir and global_storage_dir should share base up to User/
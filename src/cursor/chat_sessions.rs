@@ -8,7 +8,9 @@ use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
-const GLOBAL_HEADERS_KEY: &str = "composer.composerHeaders";
+use super::path_mapping::normalize_drive_letter;
+
+pub(crate) const GLOBAL_HEADERS_KEY: &str = "composer.composerHeaders";
 const LOCAL_COMPOSER_DATA_KEY: &str = "composer.composerData";
 
 /// Stable session metadata used by list, stats, and export.
@@ -20,6 +22,81 @@ pub struct SessionMetadata {
     pub updated_at_ms: Option<i64>,
 }
 
+impl SessionMetadata {
+    /// Last activity time, falling back to creation time
+    pub fn last_active_ms(&self) -> Option<i64> {
+        self.updated_at_ms.or(self.created_at_ms)
+    }
+}
+
+/// Criteria for narrowing down discovered sessions.
+#[derive(Debug, Clone, Default)]
+pub struct SessionFilter {
+    /// Keep sessions active at or after this time (unix ms)
+    pub since_ms: Option<i64>,
+    /// Keep sessions created at or before this time (unix ms)
+    pub until_ms: Option<i64>,
+    /// Keep only these composer IDs (empty keeps all)
+    pub session_ids: Vec<String>,
+    /// Keep sessions whose title contains this text (case-insensitive)
+    pub title: Option<String>,
+    /// Keep only the N most recently active sessions
+    pub last: Option<usize>,
+}
+
+impl SessionFilter {
+    /// Apply the filter, preserving the input order of the kept sessions.
+    ///
+    /// A session matches a date range if it overlaps it: it was still active
+    /// after `since_ms` and had been created before `until_ms`. Sessions without
+    /// timestamps never match a date bound.
+    pub fn apply(&self, sessions: Vec<SessionMetadata>) -> Vec<SessionMetadata> {
+        let title = self.title.as_ref().map(|t| t.to_lowercase());
+
+        let mut kept: Vec<SessionMetadata> = sessions
+            .into_iter()
+            .filter(|s| self.session_ids.is_empty() || self.session_ids.contains(&s.composer_id))
+            .filter(|s| match title {
+                Some(ref pattern) => s
+                    .title
+                    .as_ref()
+                    .is_some_and(|t| t.to_lowercase().contains(pattern.as_str())),
+                None => true,
+            })
+            .filter(|s| match self.since_ms {
+                Some(since) => s.last_active_ms().is_some_and(|ts| ts >= since),
+                None => true,
+            })
+            .filter(|s| match self.until_ms {
+                Some(until) => s
+                    .created_at_ms
+                    .or(s.updated_at_ms)
+                    .is_some_and(|ts| ts <= until),
+                None => true,
+            })
+            .collect();
+
+        if let Some(last) = self.last {
+            let mut by_activity: Vec<(usize, Option<i64>)> = kept
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i, s.last_active_ms()))
+                .collect();
+            by_activity.sort_by_key(|(_, ts)| std::cmp::Reverse(*ts));
+            let keep: HashSet<usize> = by_activity.into_iter().take(last).map(|(i, _)| i).collect();
+
+            kept = kept
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| keep.contains(i))
+                .map(|(_, s)| s)
+                .collect();
+        }
+
+        kept
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 struct WorkspaceIdentity {
     workspace_id: Option<String>,
@@ -152,6 +229,165 @@ pub fn discover_workspace_sessions(
     Ok(sessions)
 }
 
+/// Load metadata for the sub-composer and sub-agent sessions of a session.
+///
+/// Children are listed in the parent's `composerData` (`subComposerIds` and
+/// `subagentComposerIds`). Their title and timestamps come from their own
+/// `composerData`, falling back to the global composer headers.
+pub fn load_child_sessions(conn: &Connection, parent_id: &str) -> Result<Vec<SessionMetadata>> {
+    let Some(data) = query_cursor_disk_value(conn, &format!("composerData:{}", parent_id))? else {
+        return Ok(vec![]);
+    };
+    let Ok(json) = serde_json::from_str::<Value>(&data) else {
+        return Ok(vec![]);
+    };
+
+    let mut child_ids: Vec<String> = Vec::new();
+    for key in ["subComposerIds", "subagentComposerIds"] {
+        let ids = json
+            .get(key)
+            .and_then(|value| value.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value.as_str());
+        for id in ids {
+            if id != parent_id && !child_ids.iter().any(|existing| existing == id) {
+                child_ids.push(id.to_string());
+            }
+        }
+    }
+
+    let mut headers: Option<HashMap<String, SessionMetadata>> = None;
+    let mut children = Vec::with_capacity(child_ids.len());
+
+    for child_id in child_ids {
+        let mut metadata = query_cursor_disk_value(conn, &format!("composerData:{}", child_id))
+            .ok()
+            .flatten()
+            .and_then(|data| serde_json::from_str::<Value>(&data).ok())
+            .and_then(|mut value| {
+                // Sub-agent composerData often omits its own ID
+                if value.get("composerId").is_none() && value.is_object() {
+                    value["composerId"] = Value::String(child_id.clone());
+                }
+                parse_session_metadata(&value, true)
+            })
+            .unwrap_or_else(|| SessionMetadata {
+                composer_id: child_id.clone(),
+                title: None,
+                created_at_ms: None,
+                updated_at_ms: None,
+            });
+        metadata.composer_id = child_id;
+
+        if metadata.title.is_none() || metadata.created_at_ms.is_none() {
+            let headers = headers.get_or_insert_with(|| load_global_header_map(conn));
+            if let Some(header) = headers.get(&metadata.composer_id) {
+                merge_session_metadata(&mut metadata, header);
+            }
+        }
+
+        children.push(metadata);
+    }
+
+    Ok(children)
+}
+
+/// All sessions in the global composer headers, keyed by composer ID
+fn load_global_header_map(conn: &Connection) -> HashMap<String, SessionMetadata> {
+    let Some(data) = query_item_table_value(conn, GLOBAL_HEADERS_KEY)
+        .ok()
+        .flatten()
+    else {
+        return HashMap::new();
+    };
+    let Ok(json) = serde_json::from_str::<Value>(&data) else {
+        return HashMap::new();
+    };
+
+    json.get("allComposers")
+        .and_then(|value| value.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|value| parse_session_metadata(value, true))
+        .map(|metadata| (metadata.composer_id.clone(), metadata))
+        .collect()
+}
+
+/// A workspace's chat data in the global state DB.
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceChatData {
+    /// Its entries of `composer.composerHeaders` (`allComposers`), archived included
+    pub headers: Vec<Value>,
+    /// Its `composerData:<id>` and `bubbleId:<id>:<bubble>` rows in `cursorDiskKV`
+    pub rows: Vec<(String, String)>,
+}
+
+/// Collect the global chat data of a workspace, following child sessions.
+pub fn load_workspace_chat_data(
+    conn: &Connection,
+    workspace_dir: &Path,
+) -> Result<WorkspaceChatData> {
+    let identity = WorkspaceIdentity::from_workspace_dir(workspace_dir);
+    let all_headers = match query_item_table_value(conn, GLOBAL_HEADERS_KEY)? {
+        Some(data) => serde_json::from_str::<Value>(&data)
+            .context("Failed to parse global composer headers")?
+            .get_mut("allComposers")
+            .map(Value::take)
+            .and_then(|value| match value {
+                Value::Array(headers) => Some(headers),
+                _ => None,
+            })
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let mut ids: Vec<String> = all_headers
+        .iter()
+        .filter(|header| session_matches_workspace(header, &identity))
+        .filter_map(|header| header.get("composerId")?.as_str().map(str::to_string))
+        .collect();
+
+    // Children are listed in their parent's composerData, and may have no header
+    let mut rows = Vec::new();
+    let mut next = 0;
+    while next < ids.len() {
+        let id = ids[next].clone();
+        next += 1;
+
+        let key = format!("composerData:{}", id);
+        let Some(data) = query_cursor_disk_value(conn, &key)? else {
+            continue;
+        };
+        if let Ok(json) = serde_json::from_str::<Value>(&data) {
+            let children = ["subComposerIds", "subagentComposerIds"]
+                .iter()
+                .filter_map(|field| json.get(*field)?.as_array())
+                .flatten()
+                .filter_map(|value| value.as_str());
+            for child in children {
+                if !ids.iter().any(|known| known == child) {
+                    ids.push(child.to_string());
+                }
+            }
+        }
+        rows.push((key, data));
+        rows.extend(query_bubble_rows(conn, &id)?);
+    }
+
+    let headers = all_headers
+        .into_iter()
+        .filter(|header| {
+            header
+                .get("composerId")
+                .and_then(|v| v.as_str())
+                .is_some_and(|id| ids.iter().any(|known| known == id))
+        })
+        .collect();
+
+    Ok(WorkspaceChatData { headers, rows })
+}
+
 /// Count stable, top-level exportable sessions for a workspace.
 pub fn count_workspace_sessions(workspace_dir: &Path, include_archived: bool) -> Result<usize> {
     Ok(discover_workspace_sessions(workspace_dir, include_archived)?.len())
@@ -265,6 +501,28 @@ fn query_cursor_disk_value(conn: &Connection, key: &str) -> Result<Option<String
     .with_context(|| format!("Failed to query cursorDiskKV for key: {}", key))
 }
 
+fn query_bubble_rows(conn: &Connection, composer_id: &str) -> Result<Vec<(String, String)>> {
+    // Range scan on the key index: ';' sorts right after ':'
+    let mut stmt =
+        conn.prepare("SELECT key, value FROM cursorDiskKV WHERE key >= ?1 AND key < ?2")?;
+    let rows = stmt
+        .query_map(
+            rusqlite::params![
+                format!("bubbleId:{}:", composer_id),
+                format!("bubbleId:{};", composer_id)
+            ],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+        )?
+        .filter_map(|row| match row {
+            Ok((key, Some(value))) => Some(Ok((key, value))),
+            Ok((_, None)) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .with_context(|| format!("Failed to query bubbles of composer: {}", composer_id))?;
+    Ok(rows)
+}
+
 fn parse_global_registry(
     data: &str,
     identity: &WorkspaceIdentity,
@@ -336,8 +594,10 @@ fn session_matches_workspace(value: &Value, identity: &WorkspaceIdentity) -> boo
         }
     }
 
+    // Multi-root workspaces are identified by their `.code-workspace` file
     let actual_uri = value
         .pointer("/workspaceIdentifier/uri/external")
+        .or_else(|| value.pointer("/workspaceIdentifier/configPath/external"))
         .and_then(|v| v.as_str());
     if let (Some(expected_uri), Some(actual_uri)) =
         (identity.folder_uri_normalized.as_deref(), actual_uri)
@@ -541,23 +801,6 @@ fn split_uri(uri: &str) -> Option<(&str, String, String)> {
     Some((scheme, authority.to_string(), path.to_string()))
 }
 
-fn normalize_drive_letter(path: &str) -> String {
-    let mut chars: Vec<char> = path.chars().collect();
-
-    let drive_index = match chars.as_slice() {
-        ['/', drive, ':', '/', ..] if drive.is_ascii_alphabetic() => Some(1),
-        [drive, ':', '/', ..] if drive.is_ascii_alphabetic() => Some(0),
-        _ => None,
-    };
-
-    if let Some(index) = drive_index {
-        chars[index] = chars[index].to_ascii_lowercase();
-        chars.into_iter().collect()
-    } else {
-        path.to_string()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -593,6 +836,80 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn load_child_sessions_reads_sub_composers_and_falls_back_to_headers() {
+        let conn = init_test_db();
+        insert_disk_value(
+            &conn,
+            "composerData:parent",
+            r#"{"subComposerIds":["child-a"],"subagentComposerIds":["child-b","child-a","parent"]}"#,
+        );
+        insert_disk_value(
+            &conn,
+            "composerData:child-a",
+            r#"{"composerId":"child-a","name":"Explore","createdAt":1000}"#,
+        );
+        insert_disk_value(&conn, "composerData:child-b", r#"{"createdAt":2000}"#);
+        insert_item(
+            &conn,
+            GLOBAL_HEADERS_KEY,
+            r#"{"allComposers":[{"composerId":"child-b","name":"Subagent","createdAt":1500,"lastUpdatedAt":2500}]}"#,
+        );
+
+        let children = load_child_sessions(&conn, "parent").unwrap();
+        let ids: Vec<_> = children.iter().map(|c| c.composer_id.as_str()).collect();
+        assert_eq!(ids, vec!["child-a", "child-b"]);
+        assert_eq!(children[0].title.as_deref(), Some("Explore"));
+        assert_eq!(children[1].title.as_deref(), Some("Subagent"));
+        assert_eq!(children[1].created_at_ms, Some(1500));
+        assert_eq!(children[1].updated_at_ms, Some(2500));
+
+        assert!(load_child_sessions(&conn, "missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn load_workspace_chat_data_follows_children_and_bubbles() {
+        let conn = init_test_db();
+        insert_item(
+            &conn,
+            GLOBAL_HEADERS_KEY,
+            r#"{"allComposers":[
+                {"composerId":"main","isArchived":true,"workspaceIdentifier":{"id":"ws-1"}},
+                {"composerId":"other","workspaceIdentifier":{"id":"ws-2"}},
+                {"composerId":"sub","workspaceIdentifier":{"id":"ws-2"}}
+            ]}"#,
+        );
+        insert_disk_value(
+            &conn,
+            "composerData:main",
+            r#"{"subagentComposerIds":["sub"]}"#,
+        );
+        insert_disk_value(&conn, "composerData:sub", "{}");
+        insert_disk_value(&conn, "composerData:other", "{}");
+        insert_disk_value(&conn, "bubbleId:main:b1", r#"{"text":"hi"}"#);
+        insert_disk_value(&conn, "bubbleId:main-2:b1", "{}");
+        insert_disk_value(&conn, "bubbleId:sub:b1", "{}");
+        insert_disk_value(&conn, "bubbleId:other:b1", "{}");
+
+        let data = load_workspace_chat_data(&conn, Path::new("/storage/ws-1")).unwrap();
+        let header_ids: Vec<_> = data
+            .headers
+            .iter()
+            .map(|h| h["composerId"].as_str().unwrap())
+            .collect();
+        assert_eq!(header_ids, vec!["main", "sub"]);
+        let keys: Vec<_> = data.rows.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "composerData:main",
+                "bubbleId:main:b1",
+                "composerData:sub",
+                "bubbleId:sub:b1"
+            ]
+        );
+    }
+
     #[test]
     fn parse_global_registry_matches_local_workspace_by_id() {
         let headers = r#"{
@@ -672,6 +989,35 @@ mod tests {
         assert!(!session_matches_workspace(&wrong_host, &identity));
     }
 
+    #[test]
+    fn multi_root_workspace_matches_by_config_path() {
+        let identity = WorkspaceIdentity {
+            workspace_id: Some("workspace-multi".to_string()),
+            folder_uri_normalized: Some(normalize_uri_for_comparison(
+                "file:///repo/mono.code-workspace",
+            )),
+            workspace_path_normalized: Some(normalize_workspace_path("/repo/mono.code-workspace")),
+            remote_authority: None,
+            is_remote: false,
+        };
+
+        let matching = serde_json::json!({
+            "workspaceIdentifier": {
+                "id": "other-id",
+                "configPath": {"external": "file:///repo/mono.code-workspace"}
+            }
+        });
+        let other = serde_json::json!({
+            "workspaceIdentifier": {
+                "id": "other-id",
+                "configPath": {"external": "file:///repo/other.code-workspace"}
+            }
+        });
+
+        assert!(session_matches_workspace(&matching, &identity));
+        assert!(!session_matches_workspace(&other, &identity));
+    }
+
     #[test]
     fn dedupes_global_and_local_sessions_and_prefers_richer_metadata() {
         let mut sessions = vec![
@@ -759,4 +1105,74 @@ mod tests {
             "file:///c:/Users/me/Project"
         );
     }
+
+    fn session(id: &str, title: &str, created: i64, updated: i64) -> SessionMetadata {
+        SessionMetadata {
+            composer_id: id.to_string(),
+            title: Some(title.to_string()),
+            created_at_ms: Some(created),
+            updated_at_ms: Some(updated),
+        }
+    }
+
+    #[test]
+    fn session_filter_date_range_keeps_overlapping_sessions() {
+        let sessions = vec![
+            session("old", "Old", 100, 200),
+            session("spanning", "Spanning", 150, 450),
+            session("inside", "Inside", 300, 350),
+            session("future", "Future", 600, 700),
+        ];
+        let filter = SessionFilter {
+            since_ms: Some(300),
+            until_ms: Some(500),
+            ..SessionFilter::default()
+        };
+
+        let ids: Vec<String> = filter
+            .apply(sessions)
+            .into_iter()
+            .map(|s| s.composer_id)
+            .collect();
+        assert_eq!(ids, vec!["spanning", "inside"]);
+    }
+
+    #[test]
+    fn session_filter_ids_title_and_last() {
+        let sessions = vec![
+            session("a", "Fix migration", 100, 900),
+            session("b", "Add feature", 200, 300),
+            session("c", "Migration cleanup", 300, 400),
+            session("d", "Docs", 400, 500),
+        ];
+
+        let by_title = SessionFilter {
+            title: Some("MIGRATION".to_string()),
+            ..SessionFilter::default()
+        };
+        assert_eq!(by_title.apply(sessions.clone()).len(), 2);
+
+        let by_id = SessionFilter {
+            session_ids: vec!["b".to_string(), "d".to_string()],
+            ..SessionFilter::default()
+        };
+        let ids: Vec<String> = by_id
+            .apply(sessions.clone())
+            .into_iter()
+            .map(|s| s.composer_id)
+            .collect();
+        assert_eq!(ids, vec!["b", "d"]);
+
+        // --last picks by most recent activity but keeps input order
+        let last_two = SessionFilter {
+            last: Some(2),
+            ..SessionFilter::default()
+        };
+        let ids: Vec<String> = last_two
+            .apply(sessions)
+            .into_iter()
+            .map(|s| s.composer_id)
+            .collect();
+        assert_eq!(ids, vec!["a", "d"]);
+    }
 }
@@ -0,0 +1,469 @@
+//! Multi-root workspaces (`.code-workspace` files)
+//!
+//! For a multi-root workspace, `workspace.json` has a `workspace` key with the
+//! URI of a `.code-workspace` file instead of a `folder` key. That file is
+//! JSONC (comments and trailing commas allowed) and lists the member folders,
+//! each as a `path` (absolute, or relative to the file) or a `uri`.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+/// A parsed `.code-workspace` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeWorkspace {
+    /// Path of the `.code-workspace` file
+    pub config_path: PathBuf,
+    /// Member folders, in file order
+    pub folders: Vec<WorkspaceFolder>,
+}
+
+/// A folder entry of a multi-root workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceFolder {
+    /// Absolute folder path (the URI path for non-file URIs)
+    pub path: PathBuf,
+    /// Display name override
+    pub name: Option<String>,
+    /// Original URI, for folders given as a non-file `uri`
+    pub uri: Option<String>,
+}
+
+impl CodeWorkspace {
+    /// Read and parse a `.code-workspace` file
+    pub fn read(config_path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(config_path)
+            .with_context(|| format!("Failed to read: {}", config_path.display()))?;
+        Self::parse(&content, config_path)
+    }
+
+    /// Parse `.code-workspace` content, resolving relative folders against
+    /// the directory of `config_path`
+    pub fn parse(content: &str, config_path: &Path) -> Result<Self> {
+        let json: Value = serde_json::from_str(&strip_jsonc(content))
+            .with_context(|| format!("Failed to parse: {}", config_path.display()))?;
+        let base = config_path.parent().unwrap_or(Path::new(""));
+
+        let folders = json
+            .get("folders")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+
+                if let Some(path) = entry.get("path").and_then(|v| v.as_str()) {
+                    return Some(WorkspaceFolder {
+                        path: normalize_lexically(&base.join(path)),
+                        name,
+                        uri: None,
+                    });
+                }
+
+                let uri = entry.get("uri").and_then(|v| v.as_str())?;
+                let url = Url::parse(uri).ok()?;
+                if url.scheme() == "file" {
+                    Some(WorkspaceFolder {
+                        path: url.to_file_path().ok()?,
+                        name,
+                        uri: None,
+                    })
+                } else {
+                    Some(WorkspaceFolder {
+                        path: PathBuf::from(url.path()),
+                        name,
+                        uri: Some(uri.to_string()),
+                    })
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            config_path: config_path.to_path_buf(),
+            folders,
+        })
+    }
+
+    /// Whether one of the local member folders is `folder`
+    pub fn contains_folder(&self, folder: &Path) -> bool {
+        let folder = normalize_lexically(folder);
+        self.folders
+            .iter()
+            .any(|f| f.uri.is_none() && f.path == folder)
+    }
+}
+
+/// Local path of a `.code-workspace` file from the `workspace` URI in
+/// `workspace.json` (None for remote workspaces)
+pub fn config_path_from_uri(uri: &str) -> Option<PathBuf> {
+    let url = Url::parse(uri).ok()?;
+    if url.scheme() != "file" {
+        return None;
+    }
+    url.to_file_path().ok()
+}
+
+/// Rewrite the folder entries of `.code-workspace` content after a move
+///
+/// `remap` returns the new location of a folder (None if it did not move).
+/// The file may move as well (`new_config_path`), so relative entries are
+/// kept when they still resolve and re-derived otherwise. Only the string
+/// values are replaced, which keeps comments and formatting intact. Returns
+/// None if nothing changed.
+pub fn rewrite_folders(
+    content: &str,
+    config_path: &Path,
+    new_config_path: &Path,
+    remap: impl Fn(&Path) -> Option<PathBuf>,
+) -> Option<String> {
+    let old_base = config_path.parent().unwrap_or(Path::new(""));
+    let new_base = new_config_path.parent().unwrap_or(Path::new(""));
+
+    let mut rewritten = String::with_capacity(content.len());
+    let mut last = 0;
+    let mut changed = false;
+
+    for (key, range) in folder_value_spans(content) {
+        let Ok(value) = serde_json::from_str::<String>(&content[range.clone()]) else {
+            continue;
+        };
+
+        let replacement = if key == "path" {
+            let old_abs = normalize_lexically(&old_base.join(&value));
+            let new_abs = remap(&old_abs).unwrap_or_else(|| old_abs.clone());
+            if Path::new(&value).is_absolute() {
+                (new_abs != old_abs).then(|| new_abs.to_string_lossy().into_owned())
+            } else if normalize_lexically(&new_base.join(&value)) == new_abs {
+                None
+            } else {
+                Some(
+                    relative_path(&new_abs, new_base)
+                        .unwrap_or_else(|| new_abs.clone())
+                        .to_string_lossy()
+                        .into_owned(),
+                )
+            }
+        } else {
+            Url::parse(&value)
+                .ok()
+                .filter(|url| url.scheme() == "file")
+                .and_then(|url| url.to_file_path().ok())
+                .and_then(|old_abs| remap(&old_abs))
+                .and_then(|new_abs| Url::from_file_path(new_abs).ok())
+                .map(|url| url.to_string())
+        };
+
+        if let Some(replacement) = replacement {
+            rewritten.push_str(&content[last..range.start]);
+            rewritten.push_str(&Value::String(replacement).to_string());
+            last = range.end;
+            changed = true;
+        }
+    }
+
+    rewritten.push_str(&content[last..]);
+    changed.then_some(rewritten)
+}
+
+/// Where the scanner is relative to the top-level `folders` array
+#[derive(PartialEq, Eq)]
+enum FoldersArray {
+    NotSeen,
+    Expected,
+    At(usize),
+    Done,
+}
+
+/// Byte ranges of the `path` / `uri` string literals of entries in the
+/// top-level `folders` array, skipping comments
+fn folder_value_spans(content: &str) -> Vec<(&'static str, Range<usize>)> {
+    let bytes = content.as_bytes();
+    let mut spans = Vec::new();
+    let mut depth = 0usize;
+    let mut folders = FoldersArray::NotSeen;
+    let mut pending_key: Option<String> = None;
+    let mut expect_value: Option<&'static str> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+                let end = i.min(bytes.len());
+
+                if let Some(key) = expect_value.take() {
+                    spans.push((key, start..end));
+                } else {
+                    pending_key = serde_json::from_str(&content[start..end]).ok();
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            b':' => {
+                let key = pending_key.take();
+                let in_entry = depth > 0 && folders == FoldersArray::At(depth - 1);
+                expect_value = match key.as_deref() {
+                    Some("path") if in_entry => Some("path"),
+                    Some("uri") if in_entry => Some("uri"),
+                    Some("folders") if depth == 1 && folders == FoldersArray::NotSeen => {
+                        folders = FoldersArray::Expected;
+                        None
+                    }
+                    _ => None,
+                };
+            }
+            b'[' => {
+                depth += 1;
+                if folders == FoldersArray::Expected {
+                    folders = FoldersArray::At(depth);
+                }
+            }
+            b'{' => {
+                depth += 1;
+                if folders == FoldersArray::Expected {
+                    folders = FoldersArray::Done;
+                }
+            }
+            b']' | b'}' => {
+                if folders == FoldersArray::At(depth) {
+                    folders = FoldersArray::Done;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            b',' => {
+                pending_key = None;
+                expect_value = None;
+            }
+            c if !c.is_ascii_whitespace() => expect_value = None,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    spans
+}
+
+/// Remove `//` and `/* */` comments and trailing commas so JSONC parses as JSON
+pub(crate) fn strip_jsonc(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = String::with_capacity(input.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' => {
+                out.push(c);
+                i += 1;
+                while i < chars.len() {
+                    out.push(chars[i]);
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        out.push(chars[i + 1]);
+                        i += 2;
+                        continue;
+                    }
+                    i += 1;
+                    if chars[i - 1] == '"' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            ',' => {
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(c);
+                }
+                i += 1;
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+/// `path` relative to `base`, using `..` as needed (None across roots)
+fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    let path: Vec<_> = path.components().collect();
+    let base: Vec<_> = base.components().collect();
+    if path.first() != base.first() {
+        return None;
+    }
+
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(component.as_os_str());
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    Some(relative)
+}
+
+/// Resolve `.` and `..` components without touching the filesystem
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_jsonc() {
+        let input = r#"{
+            // line comment
+            "a": "http://x/*not a comment*/", /* block */
+            "b": [1, 2,],
+        }"#;
+        let value: Value = serde_json::from_str(&strip_jsonc(input)).unwrap();
+        assert_eq!(value["a"], "http://x/*not a comment*/");
+        assert_eq!(value["b"], serde_json::json!([1, 2]));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_parse_code_workspace_folders() {
+        let content = r#"{
+            "folders": [
+                { "path": "api" },
+                { "path": "../shared", "name": "Shared" },
+                { "path": "/abs/tools" },
+                { "uri": "file:///other/web" },
+                { "uri": "vscode-remote://ssh-remote%2Bbox/srv/app" },
+            ],
+            "settings": {}
+        }"#;
+        let ws =
+            CodeWorkspace::parse(content, Path::new("/repo/mono/mono.code-workspace")).unwrap();
+
+        let paths: Vec<_> = ws.folders.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/repo/mono/api"),
+                PathBuf::from("/repo/shared"),
+                PathBuf::from("/abs/tools"),
+                PathBuf::from("/other/web"),
+                PathBuf::from("/srv/app"),
+            ]
+        );
+        assert_eq!(ws.folders[1].name.as_deref(), Some("Shared"));
+        assert!(ws.folders[4].uri.is_some());
+
+        assert!(ws.contains_folder(Path::new("/repo/shared/")));
+        assert!(!ws.contains_folder(Path::new("/srv/app")));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_rewrite_folders_preserves_jsonc() {
+        let content = r#"{
+  // team workspace
+  "folders": [
+    { "path": "api" }, // moved
+    { "path": "/repo/mono/web", "name": "Web" },
+    { "uri": "file:///repo/mono/api/docs" },
+    { "path": "tools" },
+  ],
+  "settings": { "path": "api" }
+}"#;
+        let remap = |p: &Path| {
+            p.strip_prefix("/repo/mono/api")
+                .ok()
+                .map(|rest| Path::new("/repo/mono/server").join(rest))
+        };
+
+        let config = Path::new("/repo/mono/mono.code-workspace");
+        let rewritten = rewrite_folders(content, config, config, remap).unwrap();
+        assert_eq!(
+            rewritten,
+            content
+                .replace(r#"{ "path": "api" },"#, r#"{ "path": "server" },"#)
+                .replace(
+                    "file:///repo/mono/api/docs",
+                    "file:///repo/mono/server/docs"
+                )
+        );
+        assert!(rewritten.contains(r#""settings": { "path": "api" }"#));
+
+        // Moving the file as well keeps relative entries that still resolve
+        let moved = rewrite_folders(
+            content,
+            config,
+            Path::new("/elsewhere/mono.code-workspace"),
+            |_| None,
+        )
+        .unwrap();
+        assert!(moved.contains(r#""path": "../repo/mono/api""#));
+        assert!(moved.contains(r#""path": "../repo/mono/tools""#));
+
+        assert_eq!(rewrite_folders(content, config, config, |_| None), None);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_config_path_from_uri() {
+        assert_eq!(
+            config_path_from_uri("file:///repo/my%20mono.code-workspace"),
+            Some(PathBuf::from("/repo/my mono.code-workspace"))
+        );
+        assert_eq!(
+            config_path_from_uri("vscode-remote://ssh-remote%2Bbox/srv/a.code-workspace"),
+            None
+        );
+    }
+}
@@ -1,7 +1,10 @@
 //! Core Cursor IDE operations
 
 pub mod chat_sessions;
+pub mod code_workspace;
 pub mod folder_id;
+pub mod path_mapping;
+pub mod search_index;
 pub mod storage;
 pub mod workspace;
 
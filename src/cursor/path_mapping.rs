@@ -0,0 +1,522 @@
+//! Path prefix mappings for restoring backups on another machine or OS
+//!
+//! Backups embed their original paths in several forms: native paths
+//! (`/Users/alice/proj`, or `C:\Users\alice\proj` with the drive letter in
+//! either case), URI paths (`/c:/Users/alice/proj`) and `file://` URIs
+//! (`file:///c%3A/Users/alice/proj`). [`PathTranslator`] finds every form of a
+//! mapped prefix, including paths below it, and rewrites it into the same form
+//! of the new location on this machine.
+
+use anyhow::{bail, Result};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::path::{PathBuf, MAIN_SEPARATOR};
+use url::Url;
+
+/// Characters percent-encoded in `file://` URI path segments
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// One segment below a mapped prefix; paths embedded in text end at whitespace,
+/// quotes and characters that cannot appear in file names
+const TAIL_SEGMENT: &str = r#"[^\\/\s"'<>|*?`]*"#;
+
+/// One segment below a mapped prefix in JSON text, where a path ends with its
+/// string value; segments may contain spaces (`My Project`), but no `:`,
+/// which Windows file names cannot contain
+const JSON_TAIL_SEGMENT: &str = r#"[^\\/"<>|*?`:\x00-\x1f]*"#;
+
+/// A `--map OLD=NEW` path prefix rewrite
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathMapping {
+    /// Prefix as written on the machine that took the backup
+    pub from: String,
+    /// Prefix on this machine
+    pub to: String,
+}
+
+impl PathMapping {
+    /// Parse an `OLD=NEW` mapping
+    pub fn parse(spec: &str) -> Result<Self> {
+        let Some((from, to)) = spec.split_once('=') else {
+            bail!("Invalid path mapping (expected OLD=NEW): {}", spec);
+        };
+        let (from, to) = (
+            from.trim_end_matches(['/', '\\']),
+            to.trim_end_matches(['/', '\\']),
+        );
+        if from.is_empty() || to.is_empty() {
+            bail!("Invalid path mapping (expected OLD=NEW): {}", spec);
+        }
+        Ok(Self {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
+}
+
+/// Lowercase the drive letter of a Windows path or URI path
+///
+/// Cursor stores drive letters in lowercase (`c:\Users`, `/c:/Users`), which is
+/// also how they enter the workspace hash on Windows.
+pub fn normalize_drive_letter(path: &str) -> String {
+    let mut chars: Vec<char> = path.chars().collect();
+
+    let drive_index = match chars.as_slice() {
+        ['/', drive, ':'] | ['/', drive, ':', '/' | '\\', ..] if drive.is_ascii_alphabetic() => {
+            Some(1)
+        }
+        [drive, ':'] | [drive, ':', '/' | '\\', ..] if drive.is_ascii_alphabetic() => Some(0),
+        _ => None,
+    };
+
+    if let Some(index) = drive_index {
+        chars[index] = chars[index].to_ascii_lowercase();
+        chars.into_iter().collect()
+    } else {
+        path.to_string()
+    }
+}
+
+/// A path as written on the machine that took the backup
+#[derive(Debug, Clone, PartialEq)]
+struct SourcePath {
+    /// Lowercase drive letter of a Windows path
+    drive: Option<char>,
+    components: Vec<String>,
+}
+
+impl SourcePath {
+    /// Parse an absolute POSIX or Windows path (None for anything else)
+    fn parse(path: &str) -> Option<Self> {
+        let path = path.strip_prefix(r"\\?\").unwrap_or(path);
+        let split = |rest: &str, separators: &[char]| {
+            rest.split(separators)
+                .filter(|c| !c.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        let mut chars = path.chars();
+        match (chars.next(), chars.next()) {
+            (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => Some(Self {
+                drive: Some(drive.to_ascii_lowercase()),
+                components: split(&path[2..], &['\\', '/']),
+            }),
+            (Some('/'), _) => Some(Self {
+                drive: None,
+                components: split(path, &['/']),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Components below `prefix`, if this path is at or below it
+    fn strip_prefix(&self, prefix: &SourcePath) -> Option<&[String]> {
+        (self.drive == prefix.drive && self.components.starts_with(&prefix.components))
+            .then(|| &self.components[prefix.components.len()..])
+    }
+
+    /// Every form this path takes in stored data
+    fn variants(&self) -> Vec<(String, Form, bool)> {
+        let join = |separator: &str, encode: bool| -> String {
+            self.components
+                .iter()
+                .map(|c| match encode {
+                    true => format!("{}{}", separator, utf8_percent_encode(c, SEGMENT)),
+                    false => format!("{}{}", separator, c),
+                })
+                .collect()
+        };
+
+        let Some(drive) = self.drive else {
+            return vec![
+                (join("/", false), Form::Native, false),
+                (format!("file://{}", join("/", true)), Form::Uri, false),
+            ];
+        };
+
+        let mut variants = Vec::new();
+        for drive in [drive.to_ascii_uppercase(), drive] {
+            let lowercase = drive.is_ascii_lowercase();
+            variants.extend([
+                (
+                    format!("{}:{}", drive, join("\\", false)),
+                    Form::Native,
+                    lowercase,
+                ),
+                (
+                    format!("{}:{}", drive, join("/", false)),
+                    Form::Native,
+                    lowercase,
+                ),
+                (
+                    format!("/{}:{}", drive, join("/", false)),
+                    Form::UriPath,
+                    lowercase,
+                ),
+                (
+                    format!("file:///{}:{}", drive, join("/", true)),
+                    Form::Uri,
+                    lowercase,
+                ),
+                (
+                    format!("file:///{}%3A{}", drive, join("/", true)),
+                    Form::Uri,
+                    lowercase,
+                ),
+            ]);
+        }
+        variants
+    }
+}
+
+/// How a path is written
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Form {
+    /// A native path (`/home/alice`, `C:\Users\alice`)
+    Native,
+    /// The path of a `file://` URI, decoded (`/c:/Users/alice`)
+    UriPath,
+    /// A `file://` URI
+    Uri,
+}
+
+/// A mapping with the forms of its new location
+#[derive(Debug)]
+struct Rule {
+    from: SourcePath,
+    to: String,
+    uri_path: String,
+    uri: String,
+}
+
+/// A form of a mapped prefix, as matched in text
+#[derive(Debug)]
+struct Variant {
+    rule: usize,
+    form: Form,
+    lowercase_drive: bool,
+}
+
+/// Rewrites embedded paths and URIs with a set of path mappings
+#[derive(Debug)]
+pub struct PathTranslator {
+    rules: Vec<Rule>,
+    plain: Option<(Regex, HashMap<String, Variant>)>,
+    json: Option<(Regex, HashMap<String, Variant>)>,
+}
+
+impl PathTranslator {
+    /// Build a translator; the new side of each mapping must be an absolute
+    /// path on this machine
+    pub fn new(mappings: &[PathMapping]) -> Result<Self> {
+        let mut rules = Vec::new();
+        for mapping in mappings {
+            let Some(from) = SourcePath::parse(&mapping.from) else {
+                bail!("Mapping source must be an absolute path: {}", mapping.from);
+            };
+            let Ok(url) = Url::from_file_path(&mapping.to) else {
+                bail!(
+                    "Mapping target must be an absolute path on this machine: {}",
+                    mapping.to
+                );
+            };
+            rules.push(Rule {
+                from,
+                to: mapping.to.clone(),
+                uri_path: percent_decode_str(url.path().trim_end_matches('/'))
+                    .decode_utf8_lossy()
+                    .into_owned(),
+                uri: url.to_string().trim_end_matches('/').to_string(),
+            });
+        }
+
+        let plain = Self::build_matcher(&rules, false)?;
+        let json = Self::build_matcher(&rules, true)?;
+        Ok(Self { rules, plain, json })
+    }
+
+    /// Match any form of any prefix, longest first, followed by the rest of
+    /// the path. In JSON text, backslashes are escaped and the path runs to
+    /// the end of its string value.
+    fn build_matcher(
+        rules: &[Rule],
+        json: bool,
+    ) -> Result<Option<(Regex, HashMap<String, Variant>)>> {
+        let mut variants = HashMap::new();
+        for (index, rule) in rules.iter().enumerate() {
+            for (text, form, lowercase_drive) in rule.from.variants() {
+                let text = if json {
+                    text.replace('\\', r"\\")
+                } else {
+                    text
+                };
+                variants.entry(text).or_insert(Variant {
+                    rule: index,
+                    form,
+                    lowercase_drive,
+                });
+            }
+        }
+        if variants.is_empty() {
+            return Ok(None);
+        }
+
+        let mut prefixes: Vec<&String> = variants.keys().collect();
+        prefixes.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        let alternatives: Vec<String> = prefixes.iter().map(|p| regex::escape(p)).collect();
+        let (separator, segment) = if json {
+            (r"(?:\\\\|/)", JSON_TAIL_SEGMENT)
+        } else {
+            (r"[\\/]", TAIL_SEGMENT)
+        };
+        let pattern = format!(
+            "(?P<prefix>{})(?P<tail>(?:{}{})*)",
+            alternatives.join("|"),
+            separator,
+            segment
+        );
+        Ok(Some((Regex::new(&pattern)?, variants)))
+    }
+
+    /// Whether there are no mappings
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Map a whole path with the longest matching prefix (None if none applies)
+    pub fn translate_path(&self, path: &str) -> Option<PathBuf> {
+        let source = SourcePath::parse(path)?;
+        self.rules
+            .iter()
+            .filter_map(|rule| Some((rule, source.strip_prefix(&rule.from)?)))
+            .max_by_key(|(rule, _)| rule.from.components.len())
+            .map(|(rule, rest)| {
+                rest.iter()
+                    .fold(PathBuf::from(&rule.to), |path, c| path.join(c))
+            })
+    }
+
+    /// Rewrite every mapped path and URI in a stored value
+    ///
+    /// Values that parse as JSON are rewritten with JSON-escaped backslashes.
+    pub fn rewrite(&self, text: &str) -> String {
+        let (Some(plain), Some(json)) = (&self.plain, &self.json) else {
+            return text.to_string();
+        };
+        if !plain.0.is_match(text) && !json.0.is_match(text) {
+            return text.to_string();
+        }
+
+        let is_json = serde_json::from_str::<serde::de::IgnoredAny>(text).is_ok();
+        let (regex, variants) = if is_json { json } else { plain };
+        regex
+            .replace_all(text, |caps: &Captures| {
+                let whole = caps.get(0).expect("match");
+                let tail = &caps["tail"];
+                // A prefix only matches whole path components
+                let next = text[whole.end()..].chars().next();
+                if !starts_path(&text[..whole.start()], is_json)
+                    || (tail.is_empty() && next.is_some_and(is_path_char))
+                {
+                    return whole.as_str().to_string();
+                }
+                self.render(&variants[&caps["prefix"]], tail, is_json)
+            })
+            .into_owned()
+    }
+
+    /// Write the new location in the matched form, converting the separators
+    /// of the rest of the path
+    fn render(&self, variant: &Variant, tail: &str, json: bool) -> String {
+        let rule = &self.rules[variant.rule];
+        let (base, separator) = match variant.form {
+            Form::Native => (rule.to.clone(), MAIN_SEPARATOR),
+            Form::UriPath => (rule.uri_path.clone(), '/'),
+            Form::Uri => (rule.uri.clone(), '/'),
+        };
+        let base = match (variant.lowercase_drive, variant.form) {
+            (false, _) => base,
+            (true, Form::Uri) => format!(
+                "file://{}",
+                normalize_drive_letter(base.trim_start_matches("file://"))
+            ),
+            (true, _) => normalize_drive_letter(&base),
+        };
+
+        let tail = if json {
+            tail.replace(r"\\", "/")
+        } else {
+            tail.replace('\\', "/")
+        };
+        let rendered = format!("{}{}", base, tail.replace('/', &separator.to_string()));
+        if json {
+            rendered.replace('\\', r"\\")
+        } else {
+            rendered
+        }
+    }
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-.%".contains(c)
+}
+
+/// Whether a path can start after `before`, i.e. not inside another path
+///
+/// In JSON text, a letter after an odd number of backslashes ends an escape
+/// sequence such as `\n`.
+fn starts_path(before: &str, json: bool) -> bool {
+    let mut chars = before.chars().rev();
+    match chars.next() {
+        None => true,
+        Some(c) if c == '/' || c == '\\' || c == ':' => false,
+        Some(c) if is_path_char(c) => {
+            json && c.is_ascii_alphabetic() && chars.take_while(|&c| c == '\\').count() % 2 == 1
+        }
+        Some(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translator(specs: &[&str]) -> PathTranslator {
+        let mappings: Vec<PathMapping> = specs
+            .iter()
+            .map(|spec| PathMapping::parse(spec).unwrap())
+            .collect();
+        PathTranslator::new(&mappings).unwrap()
+    }
+
+    #[test]
+    fn test_parse_mapping() {
+        assert_eq!(
+            PathMapping::parse(r"C:\Users\alice\=/home/alice/").unwrap(),
+            PathMapping {
+                from: r"C:\Users\alice".to_string(),
+                to: "/home/alice".to_string(),
+            }
+        );
+        assert!(PathMapping::parse("/Users/alice").is_err());
+        assert!(PathMapping::parse("=/home/alice").is_err());
+        assert!(PathTranslator::new(&[PathMapping::parse("alice=/home/alice").unwrap()]).is_err());
+    }
+
+    #[test]
+    fn test_normalize_drive_letter() {
+        assert_eq!(normalize_drive_letter(r"C:\Users"), r"c:\Users");
+        assert_eq!(normalize_drive_letter("/C:/Users"), "/c:/Users");
+        assert_eq!(normalize_drive_letter("C:"), "c:");
+        assert_eq!(normalize_drive_letter("/Users/C:/x"), "/Users/C:/x");
+        assert_eq!(normalize_drive_letter("Cx:/"), "Cx:/");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_translate_path() {
+        let t = translator(&[
+            "/Users/alice=/home/alice",
+            "/Users/alice/work=/srv/work",
+            r"C:\Users\bob=/home/bob",
+        ]);
+        assert_eq!(
+            t.translate_path("/Users/alice/proj"),
+            Some(PathBuf::from("/home/alice/proj"))
+        );
+        // The longest prefix wins
+        assert_eq!(
+            t.translate_path("/Users/alice/work/api"),
+            Some(PathBuf::from("/srv/work/api"))
+        );
+        // Prefixes only match whole components
+        assert_eq!(t.translate_path("/Users/alice2/proj"), None);
+        // Drive letters match in either case, with either separator
+        assert_eq!(
+            t.translate_path(r"c:\Users\bob\My Project"),
+            Some(PathBuf::from("/home/bob/My Project"))
+        );
+        assert_eq!(
+            t.translate_path(r"\\?\C:/Users/bob/x"),
+            Some(PathBuf::from("/home/bob/x"))
+        );
+        assert_eq!(t.translate_path(r"D:\Users\bob"), None);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_rewrite_posix_source() {
+        let t = translator(&["/Users/alice=/home/alice"]);
+        assert_eq!(
+            t.rewrite("file:///Users/alice/my%20proj/a.rs"),
+            "file:///home/alice/my%20proj/a.rs"
+        );
+        assert_eq!(
+            t.rewrite(r#"{"fsPath":"/Users/alice/proj/src/db.rs","other":"/Users/alice2"}"#),
+            r#"{"fsPath":"/home/alice/proj/src/db.rs","other":"/Users/alice2"}"#
+        );
+        assert_eq!(
+            t.rewrite("see /Users/alice/proj/a.rs, then /Users/alicex"),
+            "see /home/alice/proj/a.rs, then /Users/alicex"
+        );
+        assert_eq!(
+            t.rewrite("/data/Users/alice/proj and x/Users/alice"),
+            "/data/Users/alice/proj and x/Users/alice"
+        );
+        assert_eq!(
+            t.rewrite("vscode-remote://ssh-remote%2Bbox/srv/app"),
+            "vscode-remote://ssh-remote%2Bbox/srv/app"
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_rewrite_windows_source() {
+        let t = translator(&[r"C:\Users\alice=/home/alice"]);
+        // JSON values carry escaped backslashes
+        let value = serde_json::json!({
+            "fsPath": r"c:\Users\alice\proj\src\db.rs",
+            "path": "/c:/Users/alice/proj/src/db.rs",
+            "external": "file:///c%3A/Users/alice/proj/src/db.rs",
+            "folder": "file:///C:/Users/alice/proj",
+            "text": "line one\nC:\\Users\\alice\\proj\\a.rs\tdone",
+        })
+        .to_string();
+        let rewritten: serde_json::Value = serde_json::from_str(&t.rewrite(&value)).unwrap();
+        assert_eq!(rewritten["fsPath"], "/home/alice/proj/src/db.rs");
+        assert_eq!(rewritten["path"], "/home/alice/proj/src/db.rs");
+        assert_eq!(rewritten["external"], "file:///home/alice/proj/src/db.rs");
+        assert_eq!(rewritten["folder"], "file:///home/alice/proj");
+        assert_eq!(rewritten["text"], "line one\n/home/alice/proj/a.rs\tdone");
+
+        // Names with spaces keep all their separators converted
+        let value = serde_json::json!({
+            "fsPath": r"C:\Users\alice\My Project\src\a.rs",
+            "text": r"open C:\Users\alice\My Project\b.rs, not D:\Other\c.rs",
+        })
+        .to_string();
+        let rewritten: serde_json::Value = serde_json::from_str(&t.rewrite(&value)).unwrap();
+        assert_eq!(rewritten["fsPath"], "/home/alice/My Project/src/a.rs");
+        assert_eq!(
+            rewritten["text"],
+            r"open /home/alice/My Project/b.rs, not D:\Other\c.rs"
+        );
+
+        // Plain text is not unescaped
+        assert_eq!(
+            t.rewrite(r"C:\Users\alice\proj\README.md"),
+            "/home/alice/proj/README.md"
+        );
+    }
+}
@@ -0,0 +1,577 @@
+//! Persistent local index of chat history.
+//!
+//! Reading bubbles one by one out of a multi-GB global `state.vscdb` is slow.
+//! The index mirrors the `composerData:` and `bubbleId:` rows of every session
+//! listed in `composer.composerHeaders` into a small database in the
+//! cursor-helper cache directory, together with an FTS5 table over the
+//! searchable text of each bubble.
+//!
+//! The mirror uses the same `cursorDiskKV` table layout as Cursor, so code that
+//! reads sessions from the global database can read them from the index
+//! unchanged. Sessions are refreshed incrementally by comparing their
+//! `lastUpdatedAt` header with the value recorded at indexing time.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::chat_sessions::GLOBAL_HEADERS_KEY;
+
+/// Bumped whenever the index layout changes; older indexes are rebuilt.
+const INDEX_SCHEMA_VERSION: i64 = 1;
+
+/// Trigram FTS queries need at least this many characters.
+const MIN_FTS_QUERY_CHARS: usize = 3;
+
+/// Counts reported after refreshing the index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefreshStats {
+    /// Sessions (re)indexed because they were new or changed
+    pub updated: usize,
+    /// Sessions dropped because they no longer exist
+    pub removed: usize,
+    /// Sessions already up to date
+    pub unchanged: usize,
+}
+
+/// A persistent chat history index
+pub struct SearchIndex {
+    conn: Connection,
+}
+
+/// Get the path of the index database
+pub fn index_db_path() -> Result<PathBuf> {
+    Ok(crate::config::cursor_helper_cache_dir()?.join("chat-index.sqlite"))
+}
+
+/// Open the default index and bring it up to date with the global database
+pub fn open_refreshed(global_conn: &Connection) -> Result<(SearchIndex, RefreshStats)> {
+    let mut index = SearchIndex::open(&index_db_path()?)?;
+    let stats = index.refresh(global_conn)?;
+    Ok((index, stats))
+}
+
+/// Pick the database to read a session's bubbles from
+///
+/// Sessions missing from the index (e.g. legacy sessions not listed in the
+/// global registry) are read from the global database directly.
+pub fn session_connection<'a>(
+    index: Option<&'a SearchIndex>,
+    global_conn: &'a Connection,
+    composer_id: &str,
+) -> &'a Connection {
+    match index {
+        Some(index) if index.contains(composer_id).unwrap_or(false) => index.connection(),
+        _ => global_conn,
+    }
+}
+
+impl SearchIndex {
+    /// Open (or create) an index database at `path`
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open search index: {}", path.display()))?;
+        Self::from_connection(conn)
+    }
+
+    /// Wrap an existing connection, creating the schema if needed
+    pub fn from_connection(conn: Connection) -> Result<Self> {
+        let version: i64 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .context("Failed to read search index version")?;
+
+        if version != INDEX_SCHEMA_VERSION {
+            conn.execute_batch(
+                "DROP TABLE IF EXISTS sessions;
+                 DROP TABLE IF EXISTS cursorDiskKV;
+                 DROP TABLE IF EXISTS bubble_text;",
+            )
+            .context("Failed to reset search index")?;
+        }
+
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                 composer_id TEXT PRIMARY KEY,
+                 last_updated_at INTEGER
+             );
+             CREATE TABLE IF NOT EXISTS cursorDiskKV (key TEXT PRIMARY KEY, value TEXT);
+             CREATE VIRTUAL TABLE IF NOT EXISTS bubble_text USING fts5(
+                 composer_id UNINDEXED,
+                 bubble_id UNINDEXED,
+                 content,
+                 tokenize = 'trigram'
+             );
+             PRAGMA user_version = {INDEX_SCHEMA_VERSION};"
+        ))
+        .context("Failed to initialize search index")?;
+
+        Ok(Self { conn })
+    }
+
+    /// Connection to the mirrored `cursorDiskKV` rows
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Whether a session has been indexed
+    pub fn contains(&self, composer_id: &str) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT 1 FROM sessions WHERE composer_id = ?1",
+                params![composer_id],
+                |_| Ok(()),
+            )
+            .optional()
+            .context("Failed to query search index")?
+            .is_some())
+    }
+
+    /// Number of indexed sessions
+    pub fn session_count(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+            .context("Failed to query search index")?;
+        Ok(count as usize)
+    }
+
+    /// Drop every indexed session so the next refresh rebuilds from scratch
+    pub fn clear(&mut self) -> Result<()> {
+        self.conn
+            .execute_batch(
+                "DELETE FROM sessions;
+                 DELETE FROM cursorDiskKV;
+                 DELETE FROM bubble_text;",
+            )
+            .context("Failed to clear search index")
+    }
+
+    /// Re-index sessions whose `lastUpdatedAt` changed and drop deleted ones
+    pub fn refresh(&mut self, global_conn: &Connection) -> Result<RefreshStats> {
+        let headers = load_session_headers(global_conn)?;
+        let indexed = self.indexed_sessions()?;
+        let mut stats = RefreshStats::default();
+
+        let tx = self
+            .conn
+            .transaction()
+            .context("Failed to start search index transaction")?;
+
+        for (composer_id, updated_at) in &headers {
+            if indexed.get(composer_id) == Some(updated_at) {
+                stats.unchanged += 1;
+                continue;
+            }
+
+            reindex_session(&tx, global_conn, composer_id, *updated_at)?;
+            stats.updated += 1;
+        }
+
+        for composer_id in indexed.keys() {
+            if !headers.contains_key(composer_id) {
+                remove_session(&tx, composer_id)?;
+                stats.removed += 1;
+            }
+        }
+
+        tx.commit()
+            .context("Failed to commit search index transaction")?;
+
+        Ok(stats)
+    }
+
+    /// Sessions containing `query` as a case-insensitive substring.
+    ///
+    /// Returns `None` when the query is too short for the trigram index, in
+    /// which case every indexed session is a candidate.
+    pub fn matching_sessions(&self, query: &str) -> Result<Option<HashSet<String>>> {
+        if query.chars().count() < MIN_FTS_QUERY_CHARS {
+            return Ok(None);
+        }
+
+        let phrase = format!("\"{}\"", query.replace('"', "\"\""));
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT composer_id FROM bubble_text WHERE bubble_text MATCH ?1")
+            .context("Failed to prepare search index query")?;
+        let ids = stmt
+            .query_map(params![phrase], |row| row.get::<_, String>(0))
+            .context("Failed to query search index")?
+            .collect::<rusqlite::Result<HashSet<_>>>()?;
+
+        Ok(Some(ids))
+    }
+
+    /// Whether a session can be skipped given the `candidates` of a query
+    ///
+    /// Only indexed sessions can be ruled out; sessions missing from the index
+    /// (e.g. legacy ones) must still be scanned.
+    pub fn rules_out(&self, candidates: &HashSet<String>, composer_id: &str) -> bool {
+        !candidates.contains(composer_id) && self.contains(composer_id).unwrap_or(false)
+    }
+
+    fn indexed_sessions(&self) -> Result<HashMap<String, Option<i64>>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT composer_id, last_updated_at FROM sessions")
+            .context("Failed to query search index")?;
+        let sessions = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+        Ok(sessions)
+    }
+}
+
+/// Read composer IDs and their last update time from the global registry
+fn load_session_headers(global_conn: &Connection) -> Result<HashMap<String, Option<i64>>> {
+    let data: Option<String> = global_conn
+        .query_row(
+            "SELECT value FROM ItemTable WHERE key = ?1",
+            params![GLOBAL_HEADERS_KEY],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to query global composer headers")?;
+
+    let Some(data) = data else {
+        return Ok(HashMap::new());
+    };
+
+    let json: Value =
+        serde_json::from_str(&data).context("Failed to parse global composer headers")?;
+    let Some(composers) = json.get("allComposers").and_then(|v| v.as_array()) else {
+        return Ok(HashMap::new());
+    };
+
+    Ok(composers
+        .iter()
+        .filter_map(|composer| {
+            let id = composer.get("composerId").and_then(|v| v.as_str())?;
+            let updated_at = composer
+                .get("lastUpdatedAt")
+                .and_then(|v| v.as_i64())
+                .or_else(|| composer.get("createdAt").and_then(|v| v.as_i64()));
+            Some((id.to_string(), updated_at))
+        })
+        .collect())
+}
+
+fn reindex_session(
+    conn: &Connection,
+    global_conn: &Connection,
+    composer_id: &str,
+    updated_at: Option<i64>,
+) -> Result<()> {
+    remove_session(conn, composer_id)?;
+
+    let composer_key = format!("composerData:{}", composer_id);
+    let composer_data: Option<String> = global_conn
+        .query_row(
+            "SELECT value FROM cursorDiskKV WHERE key = ?1",
+            params![composer_key],
+            |row| row.get(0),
+        )
+        .optional()
+        .with_context(|| format!("Failed to read {}", composer_key))?;
+
+    if let Some(ref data) = composer_data {
+        conn.execute(
+            "INSERT INTO cursorDiskKV (key, value) VALUES (?1, ?2)",
+            params![composer_key, data],
+        )?;
+    }
+
+    // Bubble keys share the `bubbleId:<composer>:` prefix; ';' sorts right after ':'
+    let (lower, upper) = bubble_key_range(composer_id);
+    let mut stmt = global_conn
+        .prepare("SELECT key, value FROM cursorDiskKV WHERE key >= ?1 AND key < ?2")
+        .context("Failed to prepare bubble query")?;
+    let mut rows = stmt.query(params![lower, upper])?;
+
+    while let Some(row) = rows.next()? {
+        let key: String = row.get(0)?;
+        let Ok(value) = row.get::<_, String>(1) else {
+            continue;
+        };
+
+        conn.execute(
+            "INSERT INTO cursorDiskKV (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+
+        let text = serde_json::from_str::<Value>(&value)
+            .map(|bubble| bubble_search_text(&bubble))
+            .unwrap_or_default();
+        if !text.is_empty() {
+            let bubble_id = &key[lower.len()..];
+            conn.execute(
+                "INSERT INTO bubble_text (composer_id, bubble_id, content) VALUES (?1, ?2, ?3)",
+                params![composer_id, bubble_id, text],
+            )?;
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO sessions (composer_id, last_updated_at) VALUES (?1, ?2)",
+        params![composer_id, updated_at],
+    )?;
+
+    Ok(())
+}
+
+fn remove_session(conn: &Connection, composer_id: &str) -> Result<()> {
+    let (lower, upper) = bubble_key_range(composer_id);
+    conn.execute(
+        "DELETE FROM cursorDiskKV WHERE key = ?1 OR (key >= ?2 AND key < ?3)",
+        params![format!("composerData:{}", composer_id), lower, upper],
+    )?;
+    conn.execute(
+        "DELETE FROM bubble_text WHERE composer_id = ?1",
+        params![composer_id],
+    )?;
+    conn.execute(
+        "DELETE FROM sessions WHERE composer_id = ?1",
+        params![composer_id],
+    )?;
+    Ok(())
+}
+
+pub(crate) fn bubble_key_range(composer_id: &str) -> (String, String) {
+    (
+        format!("bubbleId:{}:", composer_id),
+        format!("bubbleId:{};", composer_id),
+    )
+}
+
+/// Collect the searchable text of a bubble: message, thinking, and tool call
+fn bubble_search_text(bubble: &Value) -> String {
+    let mut parts = Vec::new();
+
+    if let Some(text) = bubble.get("text").and_then(|v| v.as_str()) {
+        parts.push(text);
+    }
+    if let Some(thinking) = bubble.pointer("/thinking/text").and_then(|v| v.as_str()) {
+        parts.push(thinking);
+    }
+    if let Some(tool) = bubble.get("toolFormerData") {
+        for field in ["name", "params", "result"] {
+            if let Some(value) = tool.get(field).and_then(|v| v.as_str()) {
+                parts.push(value);
+            }
+        }
+    }
+
+    parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_global_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ItemTable (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE cursorDiskKV (key TEXT PRIMARY KEY, value TEXT NOT NULL);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn set_headers(conn: &Connection, sessions: &[(&str, i64)]) {
+        let composers: Vec<Value> = sessions
+            .iter()
+            .map(|(id, updated)| serde_json::json!({"composerId": id, "lastUpdatedAt": updated}))
+            .collect();
+        conn.execute(
+            "INSERT OR REPLACE INTO ItemTable (key, value) VALUES (?1, ?2)",
+            params![
+                GLOBAL_HEADERS_KEY,
+                serde_json::json!({ "allComposers": composers }).to_string()
+            ],
+        )
+        .unwrap();
+    }
+
+    fn insert_session(conn: &Connection, id: &str, texts: &[&str]) {
+        let headers: Vec<Value> = (0..texts.len())
+            .map(|i| serde_json::json!({"bubbleId": format!("b{}", i), "type": 1}))
+            .collect();
+        conn.execute(
+            "INSERT OR REPLACE INTO cursorDiskKV (key, value) VALUES (?1, ?2)",
+            params![
+                format!("composerData:{}", id),
+                serde_json::json!({ "fullConversationHeadersOnly": headers }).to_string()
+            ],
+        )
+        .unwrap();
+        for (i, text) in texts.iter().enumerate() {
+            conn.execute(
+                "INSERT OR REPLACE INTO cursorDiskKV (key, value) VALUES (?1, ?2)",
+                params![
+                    format!("bubbleId:{}:b{}", id, i),
+                    serde_json::json!({ "text": text }).to_string()
+                ],
+            )
+            .unwrap();
+        }
+    }
+
+    fn open_index() -> SearchIndex {
+        SearchIndex::from_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn refresh_indexes_new_sessions_and_mirrors_rows() {
+        let global = init_global_db();
+        insert_session(&global, "s1", &["fix the migration bug", "done"]);
+        insert_session(&global, "s2", &["unrelated"]);
+        set_headers(&global, &[("s1", 100), ("s2", 200)]);
+
+        let mut index = open_index();
+        let stats = index.refresh(&global).unwrap();
+
+        assert_eq!(stats.updated, 2);
+        assert_eq!(index.session_count().unwrap(), 2);
+        let mirrored: String = index
+            .connection()
+            .query_row(
+                "SELECT value FROM cursorDiskKV WHERE key = 'bubbleId:s1:b0'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(mirrored.contains("migration"));
+    }
+
+    #[test]
+    fn refresh_only_updates_changed_sessions() {
+        let global = init_global_db();
+        insert_session(&global, "s1", &["first"]);
+        insert_session(&global, "s2", &["second"]);
+        set_headers(&global, &[("s1", 100), ("s2", 200)]);
+
+        let mut index = open_index();
+        index.refresh(&global).unwrap();
+
+        insert_session(&global, "s2", &["second", "follow-up question"]);
+        set_headers(&global, &[("s1", 100), ("s2", 300)]);
+
+        let stats = index.refresh(&global).unwrap();
+        assert_eq!(
+            stats,
+            RefreshStats {
+                updated: 1,
+                removed: 0,
+                unchanged: 1,
+            }
+        );
+        assert_eq!(
+            index.matching_sessions("follow-up").unwrap(),
+            Some(HashSet::from(["s2".to_string()]))
+        );
+    }
+
+    #[test]
+    fn refresh_removes_deleted_sessions() {
+        let global = init_global_db();
+        insert_session(&global, "s1", &["first"]);
+        insert_session(&global, "s2", &["second"]);
+        set_headers(&global, &[("s1", 100), ("s2", 200)]);
+
+        let mut index = open_index();
+        index.refresh(&global).unwrap();
+
+        set_headers(&global, &[("s1", 100)]);
+        let stats = index.refresh(&global).unwrap();
+
+        assert_eq!(stats.removed, 1);
+        assert!(!index.contains("s2").unwrap());
+        let leftover: i64 = index
+            .connection()
+            .query_row(
+                "SELECT COUNT(*) FROM cursorDiskKV WHERE key LIKE '%s2%'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(leftover, 0);
+    }
+
+    #[test]
+    fn bubble_key_range_does_not_include_prefix_sibling_sessions() {
+        let global = init_global_db();
+        insert_session(&global, "s1", &["mine"]);
+        insert_session(&global, "s10", &["sibling"]);
+        set_headers(&global, &[("s1", 100)]);
+
+        let mut index = open_index();
+        index.refresh(&global).unwrap();
+
+        assert_eq!(
+            index.matching_sessions("sibling").unwrap(),
+            Some(HashSet::new())
+        );
+    }
+
+    #[test]
+    fn matching_sessions_is_case_insensitive_substring() {
+        let global = init_global_db();
+        insert_session(&global, "s1", &["The Migration fails"]);
+        set_headers(&global, &[("s1", 100)]);
+
+        let mut index = open_index();
+        index.refresh(&global).unwrap();
+
+        assert_eq!(
+            index.matching_sessions("migrat").unwrap(),
+            Some(HashSet::from(["s1".to_string()]))
+        );
+        assert_eq!(index.matching_sessions("ab").unwrap(), None);
+    }
+
+    #[test]
+    fn rules_out_only_indexed_non_matching_sessions() {
+        let global = init_global_db();
+        insert_session(&global, "s1", &["The Migration fails"]);
+        insert_session(&global, "s2", &["unrelated"]);
+        // A legacy session missing from the global registry is never indexed
+        insert_session(&global, "legacy", &["old migration notes"]);
+        set_headers(&global, &[("s1", 100), ("s2", 200)]);
+
+        let mut index = open_index();
+        index.refresh(&global).unwrap();
+
+        let candidates = index.matching_sessions("migration").unwrap().unwrap();
+        assert!(!index.rules_out(&candidates, "s1"));
+        assert!(index.rules_out(&candidates, "s2"));
+        assert!(!index.rules_out(&candidates, "legacy"));
+    }
+
+    #[test]
+    fn bubble_search_text_includes_thinking_and_tools() {
+        let bubble = serde_json::json!({
+            "text": "",
+            "thinking": {"text": "consider the schema"},
+            "toolFormerData": {"name": "run_terminal_cmd", "params": "{\"command\":\"ls\"}"}
+        });
+
+        let text = bubble_search_text(&bubble);
+        assert!(text.contains("consider the schema"));
+        assert!(text.contains("run_terminal_cmd"));
+        assert!(text.contains("\"command\""));
+    }
+}
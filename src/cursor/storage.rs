@@ -14,6 +14,7 @@ use std::path::Path;
 ///
 /// This updates:
 /// - backupWorkspaces.folders[].folderUri
+/// - backupWorkspaces.workspaces[].configURIPath (multi-root workspaces)
 /// - profileAssociations.workspaces (key rename)
 pub fn update_storage_json<P: AsRef<Path>>(
     storage_path: P,
@@ -48,6 +49,22 @@ pub fn update_storage_json<P: AsRef<Path>>(
         })
         .unwrap_or(false);
 
+    // Update backupWorkspaces.workspaces[].configURIPath
+    let workspaces_modified = json
+        .get_mut("backupWorkspaces")
+        .and_then(|b| b.get_mut("workspaces"))
+        .and_then(|w| w.as_array_mut())
+        .map(|arr| {
+            arr.iter_mut()
+                .filter_map(|workspace| workspace.get_mut("configURIPath"))
+                .filter(|uri| uri.as_str() == Some(old_uri))
+                .fold(false, |_, uri| {
+                    *uri = Value::String(new_uri.to_string());
+                    true
+                })
+        })
+        .unwrap_or(false);
+
     // Update profileAssociations.workspaces (rename key)
     let assoc_modified = json
         .get_mut("profileAssociations")
@@ -60,7 +77,7 @@ pub fn update_storage_json<P: AsRef<Path>>(
         })
         .unwrap_or(false);
 
-    let modified = folders_modified || assoc_modified;
+    let modified = folders_modified || workspaces_modified || assoc_modified;
 
     if modified && !dry_run {
         let new_content = serde_json::to_string_pretty(&json)?;
@@ -99,6 +116,32 @@ pub fn update_global_state_db<P: AsRef<Path>>(
     let conn = Connection::open(state_db)
         .with_context(|| format!("Failed to open global state DB: {}", state_db.display()))?;
 
+    update_global_state_refs(
+        &conn,
+        old_path,
+        new_path,
+        old_uri,
+        new_uri,
+        old_workspace_hash,
+        new_workspace_hash,
+        dry_run,
+    )
+}
+
+/// Same as [`update_global_state_db`], on an already open global state DB
+///
+/// Lets a batch of renames share one connection.
+#[allow(clippy::too_many_arguments)]
+pub fn update_global_state_refs(
+    conn: &Connection,
+    old_path: &str,
+    new_path: &str,
+    old_uri: &str,
+    new_uri: &str,
+    old_workspace_hash: &str,
+    new_workspace_hash: &str,
+    dry_run: bool,
+) -> Result<bool> {
     let mut modified = false;
 
     // Replace path, URI, and workspace hash references in all known text columns.
@@ -121,7 +164,7 @@ pub fn update_global_state_db<P: AsRef<Path>>(
     ];
 
     for (table, column) in targets {
-        if !table_exists(&conn, table)? || !column_exists(&conn, table, column)? {
+        if !table_exists(conn, table)? || !column_exists(conn, table, column)? {
             continue;
         }
 
@@ -171,6 +214,20 @@ pub fn update_global_state_db<P: AsRef<Path>>(
     Ok(modified)
 }
 
+/// Rewrite path, URI and workspace hash references in a single stored value,
+/// with the same matching rules as [`update_global_state_refs`]
+pub fn rewrite_workspace_refs(value: &str, replacements: &[(&str, &str)]) -> String {
+    let replacements: Vec<(String, String)> = replacements
+        .iter()
+        .map(|(old, new)| (old.to_string(), new.to_string()))
+        .collect();
+
+    if !has_workspace_scoped_reference(value, &replacements) {
+        return value.to_string();
+    }
+    normalize_text_replacements(value, &replacements)
+}
+
 fn has_workspace_scoped_reference(value: &str, replacements: &[(String, String)]) -> bool {
     let candidates: Vec<&str> = replacements
         .iter()
@@ -393,6 +450,34 @@ mod tests {
         assert!(!content.contains("file:///old/path"));
     }
 
+    #[test]
+    fn test_update_storage_json_multi_root_workspace() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"{{
+    "backupWorkspaces": {{
+        "workspaces": [
+            {{ "id": "abc", "configURIPath": "file:///old/mono.code-workspace" }}
+        ]
+    }}
+}}"#
+        )
+        .unwrap();
+
+        let modified = update_storage_json(
+            file.path(),
+            "file:///old/mono.code-workspace",
+            "file:///new/mono.code-workspace",
+            false,
+        )
+        .unwrap();
+
+        assert!(modified);
+        let content = fs::read_to_string(file.path()).unwrap();
+        assert!(content.contains("file:///new/mono.code-workspace"));
+    }
+
     #[test]
     fn test_update_global_state_db() {
         let temp_dir = TempDir::new().unwrap();
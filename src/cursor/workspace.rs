@@ -41,20 +41,28 @@ pub fn compute_workspace_hash<P: AsRef<Path>>(path: P) -> Result<String> {
     Ok(format!("{:x}", hash))
 }
 
+/// Compute the workspace storage hash of a multi-root workspace
+///
+/// Formula: MD5(configPath), lowercased except on Linux. Unlike folders, the
+/// birth time of the `.code-workspace` file is not part of the hash.
+pub fn compute_multi_root_hash<P: AsRef<Path>>(config_path: P) -> String {
+    let path_str = config_path.as_ref().to_string_lossy();
+
+    #[cfg(not(target_os = "linux"))]
+    let path_str = path_str.to_lowercase();
+
+    format!("{:x}", md5::compute(path_str.as_bytes()))
+}
+
 /// Normalize path for hash computation
 /// On Windows, Cursor uses lowercase drive letters (c: not C:)
 fn normalize_path_for_hash(path: &Path) -> String {
     let path_str = path.to_string_lossy();
 
+    // Lowercase the drive letter (C: -> c:)
     #[cfg(windows)]
-    {
-        // Lowercase the drive letter (C: -> c:)
-        if path_str.len() >= 2 && path_str.as_bytes()[1] == b':' {
-            let mut chars: Vec<char> = path_str.chars().collect();
-            chars[0] = chars[0].to_ascii_lowercase();
-            return chars.into_iter().collect();
-        }
-    }
+    let path_str: std::borrow::Cow<str> =
+        super::path_mapping::normalize_drive_letter(&path_str).into();
 
     path_str.into_owned()
 }
@@ -181,6 +189,15 @@ mod tests {
         );
     }
 
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_compute_multi_root_hash() {
+        assert_eq!(
+            compute_multi_root_hash("/home/me/mono.code-workspace"),
+            format!("{:x}", md5::compute("/home/me/mono.code-workspace"))
+        );
+    }
+
     #[cfg(not(windows))]
     #[test]
     fn test_normalize_path_for_hash_unix() {
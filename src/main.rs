@@ -5,7 +5,7 @@
 //! See DISCLAIMER.md for details.
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use owo_colors::OwoColorize;
 use std::path::PathBuf;
 
@@ -27,10 +27,12 @@ enum Commands {
     /// Rename or copy a Cursor project while preserving history
     Rename {
         /// Old project path
-        old_path: String,
+        #[arg(required_unless_present_any = ["resume", "rollback", "batch"])]
+        old_path: Option<String>,
 
         /// New project path
-        new_path: String,
+        #[arg(required_unless_present_any = ["resume", "rollback", "batch"])]
+        new_path: Option<String>,
 
         /// Show what would be done without making changes
         #[arg(short = 'n', long)]
@@ -43,6 +45,32 @@ enum Commands {
         /// Force a full composer index rebuild after copy
         #[arg(long)]
         force_index: bool,
+
+        /// Resume the most recent interrupted rename
+        #[arg(long, conflicts_with_all = ["old_path", "new_path", "rollback", "dry_run"])]
+        resume: bool,
+
+        /// Roll back the most recent interrupted rename
+        #[arg(long, conflicts_with_all = ["old_path", "new_path", "dry_run"])]
+        rollback: bool,
+
+        /// Rename many projects from a mapping file: OLD<TAB>NEW pairs or
+        /// OLD_ROOT=NEW_ROOT prefix rewrites, one per line
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["old_path", "new_path", "resume", "rollback"])]
+        batch: Option<String>,
+    },
+
+    /// Relink Cursor metadata after a project folder was moved outside the tool
+    Relink {
+        /// Project path before the move (no longer exists)
+        old_path: String,
+
+        /// Current project path
+        new_path: String,
+
+        /// Show what would be done without making changes
+        #[arg(short = 'n', long)]
+        dry_run: bool,
     },
 
     /// List all Cursor projects
@@ -66,58 +94,95 @@ enum Commands {
         /// Limit number of results
         #[arg(short = 'n', long)]
         limit: Option<usize>,
+
+        /// Output format: table, csv, or json
+        #[arg(long, default_value = "table")]
+        format: String,
     },
 
     /// Show usage statistics for a project
     Stats {
         /// Project path (prompts if omitted)
         project_path: Option<String>,
+
+        /// Output format: table, csv, or json
+        #[arg(long, default_value = "table")]
+        format: String,
     },
 
     /// Export chat history to a readable format
-    ExportChat {
-        /// Project path (local or remote, e.g., /home/user/project for SSH)
-        project_path: Option<String>,
+    ExportChat(Box<ExportChatArgs>),
 
-        /// Workspace ID (hash) - use instead of project_path for direct lookup
-        #[arg(long, conflicts_with = "project_path")]
-        workspace_id: Option<String>,
+    /// Search chat history across all projects
+    Search {
+        /// Text to search for
+        query: String,
 
-        /// Output format: md or json (default: md)
-        #[arg(long, short, default_value = "md")]
-        format: String,
+        /// Treat the query as a regular expression
+        #[arg(short = 'e', long)]
+        regex: bool,
+
+        /// Match case exactly (default: case-insensitive)
+        #[arg(short = 's', long)]
+        case_sensitive: bool,
 
-        /// Output file (prints to stdout if omitted)
+        /// Only search projects whose path contains this pattern
         #[arg(long, short)]
-        output: Option<String>,
+        project: Option<String>,
 
-        /// Include thinking/reasoning blocks
+        /// Include archived chat sessions
         #[arg(long)]
-        with_thinking: bool,
+        include_archived: bool,
 
-        /// Include tool calls (file reads, edits, commands)
+        /// Maximum number of matches to show
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+
+        /// Use the persistent search index (built on first use, refreshed incrementally)
         #[arg(long)]
-        with_tools: bool,
+        index: bool,
+    },
 
-        /// Include model info and token counts
+    /// Report token usage (and estimated spend) across all chat history
+    Usage {
+        /// Group rows by: model, session, project, day (default: model)
+        #[arg(long, short, default_value = "model")]
+        by: String,
+
+        /// Output format: table, csv, or json
+        #[arg(long, short, default_value = "table")]
+        format: String,
+
+        /// TOML price table (per million tokens) used to estimate spend
+        #[arg(long, value_name = "FILE")]
+        prices: Option<String>,
+
+        /// Only usage since this time (e.g., 30d, 2026-01-01)
         #[arg(long)]
-        with_stats: bool,
+        since: Option<String>,
 
-        /// Include all extra data (thinking, tools, stats)
-        #[arg(short, long)]
-        verbose: bool,
+        /// Only usage until this time (e.g., 1d, 2026-01-31)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Only include projects whose path contains this pattern
+        #[arg(long, short)]
+        project: Option<String>,
 
         /// Include archived chat sessions
         #[arg(long)]
         include_archived: bool,
+    },
 
-        /// Split output into separate files per session (requires --output as directory)
+    /// Build or refresh the persistent chat history index
+    Index {
+        /// Discard the existing index and rebuild it from scratch
         #[arg(long)]
-        split: bool,
+        rebuild: bool,
 
-        /// Exclude sessions with no messages
-        #[arg(long)]
-        exclude_blank: bool,
+        /// Delete the index database
+        #[arg(long, conflicts_with = "rebuild")]
+        remove: bool,
     },
 
     /// Remove orphaned workspace storage (projects that no longer exist)
@@ -132,21 +197,43 @@ enum Commands {
     },
 
     /// Backup Cursor metadata for a project
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Backup {
         /// Project path to backup
-        project_path: String,
+        #[arg(required_unless_present = "all")]
+        project_path: Option<String>,
 
         /// Output backup file (will add .tar.gz if not present)
-        backup_file: String,
+        #[arg(required_unless_present = "all")]
+        backup_file: Option<String>,
+
+        /// Back up every project, its chat history and storage.json into one
+        /// full-profile archive
+        #[arg(long, value_name = "BACKUP_FILE", conflicts_with_all = ["project_path", "backup_file"])]
+        all: Option<String>,
+
+        #[command(subcommand)]
+        action: Option<BackupAction>,
     },
 
     /// Restore Cursor metadata from a backup
     Restore {
         /// Backup file to restore from
-        backup_file: String,
+        #[arg(required_unless_present = "all")]
+        backup_file: Option<String>,
 
         /// New project path to restore to
-        new_path: String,
+        #[arg(required_unless_present = "all")]
+        new_path: Option<String>,
+
+        /// Restore every project of a full-profile backup (`backup --all`)
+        #[arg(long, value_name = "BACKUP_FILE", conflicts_with_all = ["backup_file", "new_path"])]
+        all: Option<String>,
+
+        /// Rewrite paths and file:// URIs starting with OLD to start with NEW,
+        /// across OSes (repeatable, e.g. --map C:\Users\alice=/home/alice)
+        #[arg(long = "map", value_name = "OLD=NEW")]
+        mappings: Vec<String>,
     },
 
     /// Clone a project with full chat history to a new location
@@ -161,6 +248,169 @@ enum Commands {
         #[arg(short = 'n', long)]
         dry_run: bool,
     },
+
+    /// Undo a past rename, copy or clone using its safety backup
+    Undo {
+        /// Operation ID from `undo --list` (default: the most recent)
+        id: Option<String>,
+
+        /// List recorded operations instead of undoing one
+        #[arg(long, conflicts_with_all = ["id", "force", "dry_run"])]
+        list: bool,
+
+        /// Undo even if Cursor data changed after the operation
+        #[arg(long)]
+        force: bool,
+
+        /// Show what would be done without making changes
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// List or prune the safety backups kept by rename and clone
+    Backups {
+        /// Delete backups outside the retention limits
+        #[arg(long)]
+        prune: bool,
+
+        /// Keep at most this many backups
+        #[arg(long, requires = "prune")]
+        keep_last: Option<usize>,
+
+        /// Delete backups older than this (e.g. 30d, 2w, 2026-01-31)
+        #[arg(long, requires = "prune")]
+        max_age: Option<String>,
+
+        /// Delete the oldest backups until the rest fit (e.g. 500MB, 5GB)
+        #[arg(long, requires = "prune")]
+        max_size: Option<String>,
+
+        /// Show what would be deleted without making changes
+        #[arg(short = 'n', long, requires = "prune")]
+        dry_run: bool,
+
+        /// Skip confirmation prompt
+        #[arg(short, long, requires = "prune")]
+        yes: bool,
+    },
+}
+
+/// Arguments of `export-chat`, boxed to keep `Commands` small
+#[derive(Args)]
+struct ExportChatArgs {
+    /// Project path (local or remote, e.g., /home/user/project for SSH)
+    project_path: Option<String>,
+
+    /// Workspace ID (hash) - use instead of project_path for direct lookup
+    #[arg(long, conflicts_with = "project_path")]
+    workspace_id: Option<String>,
+
+    /// Export every project into <output>/<project-slug>/ (requires --output as directory)
+    #[arg(long, conflicts_with_all = ["project_path", "workspace_id", "split"])]
+    all: bool,
+
+    /// Output format: md, json, jsonl, or html (default: md)
+    #[arg(long, short, default_value = "md")]
+    format: String,
+
+    /// Output file (prints to stdout if omitted)
+    #[arg(long, short)]
+    output: Option<String>,
+
+    /// Include thinking/reasoning blocks
+    #[arg(long)]
+    with_thinking: bool,
+
+    /// Include tool calls (file reads, edits, commands)
+    #[arg(long)]
+    with_tools: bool,
+
+    /// Include model info and token counts
+    #[arg(long)]
+    with_stats: bool,
+
+    /// Include all extra data (thinking, tools, stats)
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Include archived chat sessions
+    #[arg(long)]
+    include_archived: bool,
+
+    /// Nest sub-agent sessions under the session that spawned them
+    #[arg(long)]
+    include_children: bool,
+
+    /// Split output into separate files per session (requires --output as directory)
+    #[arg(long)]
+    split: bool,
+
+    /// Exclude sessions with no messages
+    #[arg(long)]
+    exclude_blank: bool,
+
+    /// Read messages from the persistent search index (refreshed first)
+    #[arg(long)]
+    index: bool,
+
+    /// Only sessions active since this time (e.g., 7d, 12h, 2026-01-31)
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Only sessions created until this time (e.g., 1d, 2026-01-31)
+    #[arg(long)]
+    until: Option<String>,
+
+    /// Only export this session ID (repeatable)
+    #[arg(long = "session", value_name = "COMPOSER_ID")]
+    sessions: Vec<String>,
+
+    /// Only sessions whose title contains this text (case-insensitive)
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Only the N most recently active sessions
+    #[arg(long, value_name = "N")]
+    last: Option<usize>,
+
+    /// Only rewrite files for sessions changed since the last split export
+    #[arg(long)]
+    incremental: bool,
+
+    /// Max characters of tool parameters to keep, or "unlimited" (default: 500)
+    #[arg(long, value_name = "N")]
+    max_tool_params: Option<String>,
+
+    /// Max characters of tool results to keep, or "unlimited" (default: 1000)
+    #[arg(long, value_name = "N")]
+    max_tool_result: Option<String>,
+
+    /// Keep tool parameters and results in full
+    #[arg(long, conflicts_with_all = ["max_tool_params", "max_tool_result"])]
+    no_truncate: bool,
+
+    /// With --incremental, delete files for sessions that no longer exist
+    #[arg(
+        long,
+        requires = "incremental",
+        conflicts_with_all = ["since", "until", "sessions", "title", "last"]
+    )]
+    prune: bool,
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Show the manifest, contents and chat sessions of a backup
+    Inspect {
+        /// Backup file to inspect
+        backup_file: String,
+    },
+
+    /// Check a backup's checksums, databases and chat data
+    Verify {
+        /// Backup file to verify
+        backup_file: String,
+    },
 }
 
 fn main() -> Result<()> {
@@ -173,11 +423,41 @@ fn main() -> Result<()> {
             dry_run,
             copy,
             force_index,
+            resume,
+            rollback,
+            batch,
+        } => {
+            if resume {
+                commands::rename::resume()?;
+            } else if rollback {
+                commands::rename::rollback()?;
+            } else {
+                if dry_run {
+                    println!("{}", "(DRY-RUN MODE - no changes will be made)".blue());
+                }
+                match (batch, old_path, new_path) {
+                    (Some(mapping_file), _, _) => {
+                        commands::rename_batch::execute(&mapping_file, dry_run, copy, force_index)?
+                    }
+                    (None, Some(old_path), Some(new_path)) => {
+                        commands::rename::execute(&old_path, &new_path, dry_run, copy, force_index)?
+                    }
+                    _ => {
+                        unreachable!("clap requires both paths without --resume/--rollback/--batch")
+                    }
+                }
+            }
+        }
+
+        Commands::Relink {
+            old_path,
+            new_path,
+            dry_run,
         } => {
             if dry_run {
                 println!("{}", "(DRY-RUN MODE - no changes will be made)".blue());
             }
-            commands::rename::execute(&old_path, &new_path, dry_run, copy, force_index)?;
+            commands::relink::execute(&old_path, &new_path, dry_run)?;
         }
 
         Commands::List {
@@ -186,6 +466,7 @@ fn main() -> Result<()> {
             reverse,
             filter,
             limit,
+            format,
         } => {
             let options = commands::list::ListOptions {
                 with_id,
@@ -193,6 +474,8 @@ fn main() -> Result<()> {
                 reverse,
                 filter,
                 limit,
+                format: commands::utils::ReportFormat::from_str(&format)
+                    .context("Invalid format. Use 'table', 'csv', or 'json'")?,
             };
             let (output, warnings) = commands::list::execute(options)?;
             if let Some(warnings) = warnings {
@@ -201,37 +484,91 @@ fn main() -> Result<()> {
             println!("{}", output);
         }
 
-        Commands::Stats { project_path } => {
+        Commands::Stats {
+            project_path,
+            format,
+        } => {
+            let format = commands::utils::ReportFormat::from_str(&format)
+                .context("Invalid format. Use 'table', 'csv', or 'json'")?;
             let project_path = project_path.map(PathBuf::from);
             let stats = commands::stats::stats(project_path)?;
-            println!("{}", commands::stats::format_stats(&stats));
+            println!("{}", commands::stats::format_stats_as(&stats, format)?);
         }
 
-        Commands::ExportChat {
-            project_path,
-            workspace_id,
-            format,
-            output,
-            with_thinking,
-            with_tools,
-            with_stats,
-            verbose,
-            include_archived,
-            split,
-            exclude_blank,
-        } => {
+        Commands::ExportChat(args) => {
+            let ExportChatArgs {
+                project_path,
+                workspace_id,
+                all,
+                format,
+                output,
+                with_thinking,
+                with_tools,
+                with_stats,
+                verbose,
+                include_archived,
+                include_children,
+                split,
+                exclude_blank,
+                index,
+                since,
+                until,
+                sessions,
+                title,
+                last,
+                incremental,
+                prune,
+                max_tool_params,
+                max_tool_result,
+                no_truncate,
+            } = *args;
+            if incremental && !(split || all) {
+                anyhow::bail!("--incremental requires --split or --all");
+            }
             let format = commands::export_chat::ExportFormat::from_str(&format)
-                .context("Invalid format. Use 'md' or 'json'")?;
+                .context("Invalid format. Use 'md', 'json', 'jsonl', or 'html'")?;
+            let now = chrono::Utc::now();
+            let filter = cursor::chat_sessions::SessionFilter {
+                since_ms: since
+                    .map(|s| commands::utils::parse_time_bound(&s, now, false))
+                    .transpose()?,
+                until_ms: until
+                    .map(|s| commands::utils::parse_time_bound(&s, now, true))
+                    .transpose()?,
+                session_ids: sessions,
+                title,
+                last,
+            };
             let options = commands::export_chat::ExportOptions {
                 with_thinking: with_thinking || verbose,
                 with_tools: with_tools || verbose,
                 with_stats: with_stats || verbose,
                 include_archived,
                 exclude_blank,
+                use_index: index,
+                filter,
+                incremental,
+                prune,
+                tool_params_limit: match max_tool_params {
+                    Some(limit) => commands::export_chat::parse_truncation_limit(&limit)?,
+                    None if no_truncate => None,
+                    None => Some(commands::export_chat::DEFAULT_TOOL_PARAMS_LIMIT),
+                },
+                tool_result_limit: match max_tool_result {
+                    Some(limit) => commands::export_chat::parse_truncation_limit(&limit)?,
+                    None if no_truncate => None,
+                    None => Some(commands::export_chat::DEFAULT_TOOL_RESULT_LIMIT),
+                },
+                include_children,
             };
 
-            // Either project_path or workspace_id must be provided
+            // Either project_path, workspace_id, or --all must be provided
             match (project_path, workspace_id) {
+                _ if all => {
+                    let output = output
+                        .context("--all requires --output to specify the output directory")?;
+                    commands::export_chat::execute_all(format, &output, &options)?;
+                }
                 (Some(path), None) => {
                     commands::export_chat::execute(
                         &path,
@@ -251,7 +588,7 @@ fn main() -> Result<()> {
                     )?;
                 }
                 (None, None) => {
-                    anyhow::bail!("Either project_path or --workspace-id must be provided");
+                    anyhow::bail!("Either project_path, --workspace-id, or --all must be provided");
                 }
                 (Some(_), Some(_)) => {
                     // This case is prevented by clap's conflicts_with
@@ -260,6 +597,66 @@ fn main() -> Result<()> {
             }
         }
 
+        Commands::Search {
+            query,
+            regex,
+            case_sensitive,
+            project,
+            include_archived,
+            limit,
+            index,
+        } => {
+            let options = commands::search::SearchOptions {
+                query,
+                regex,
+                case_sensitive,
+                project,
+                include_archived,
+                limit,
+                use_index: index,
+            };
+            commands::search::execute(&options)?;
+        }
+
+        Commands::Usage {
+            by,
+            format,
+            prices,
+            since,
+            until,
+            project,
+            include_archived,
+        } => {
+            let group_by = commands::usage::UsageGroup::from_str(&by)
+                .context("Invalid grouping. Use 'model', 'session', 'project', or 'day'")?;
+            let format = commands::utils::ReportFormat::from_str(&format)
+                .context("Invalid format. Use 'table', 'csv', or 'json'")?;
+            let now = chrono::Utc::now();
+            let filter = cursor::chat_sessions::SessionFilter {
+                since_ms: since
+                    .map(|s| commands::utils::parse_time_bound(&s, now, false))
+                    .transpose()?,
+                until_ms: until
+                    .map(|s| commands::utils::parse_time_bound(&s, now, true))
+                    .transpose()?,
+                ..Default::default()
+            };
+            let options = commands::usage::UsageOptions {
+                group_by,
+                filter,
+                project,
+                include_archived,
+                prices: prices
+                    .map(|path| commands::usage::PriceTable::load(&PathBuf::from(path)))
+                    .transpose()?,
+            };
+            println!("{}", commands::usage::execute(&options, format)?);
+        }
+
+        Commands::Index { rebuild, remove } => {
+            commands::index::execute(rebuild, remove)?;
+        }
+
         Commands::Clean { dry_run, yes } => {
             if dry_run {
                 println!("{}", "(DRY-RUN MODE - no changes will be made)".blue());
@@ -270,16 +667,34 @@ fn main() -> Result<()> {
         Commands::Backup {
             project_path,
             backup_file,
-        } => {
-            commands::backup::execute(&project_path, &backup_file)?;
-        }
+            all,
+            action,
+        } => match (action, all, project_path, backup_file) {
+            (Some(BackupAction::Inspect { backup_file }), _, _, _) => {
+                commands::backup_inspect::inspect(&backup_file)?
+            }
+            (Some(BackupAction::Verify { backup_file }), _, _, _) => {
+                commands::backup_inspect::verify(&backup_file)?
+            }
+            (None, Some(backup_file), _, _) => commands::backup::execute_all(&backup_file)?,
+            (None, None, Some(project_path), Some(backup_file)) => {
+                commands::backup::execute(&project_path, &backup_file)?
+            }
+            _ => unreachable!("clap requires both paths without a subcommand or --all"),
+        },
 
         Commands::Restore {
             backup_file,
             new_path,
-        } => {
-            commands::restore::execute(&backup_file, &new_path)?;
-        }
+            all,
+            mappings,
+        } => match (all, backup_file, new_path) {
+            (Some(backup_file), _, _) => commands::restore::execute_all(&backup_file, &mappings)?,
+            (None, Some(backup_file), Some(new_path)) => {
+                commands::restore::execute(&backup_file, &new_path, &mappings)?
+            }
+            _ => unreachable!("clap requires both paths without --all"),
+        },
 
         Commands::Clone {
             old_path,
@@ -291,6 +706,45 @@ fn main() -> Result<()> {
             }
             commands::clone::execute(&old_path, &new_path, dry_run)?;
         }
+
+        Commands::Undo {
+            id,
+            list,
+            force,
+            dry_run,
+        } => {
+            if list {
+                println!("{}", commands::undo::list()?);
+            } else {
+                if dry_run {
+                    println!("{}", "(DRY-RUN MODE - no changes will be made)".blue());
+                }
+                commands::undo::execute(id.as_deref(), force, dry_run)?;
+            }
+        }
+
+        Commands::Backups {
+            prune,
+            keep_last,
+            max_age,
+            max_size,
+            dry_run,
+            yes,
+        } => {
+            if prune {
+                if dry_run {
+                    println!("{}", "(DRY-RUN MODE - no changes will be made)".blue());
+                }
+                let limits = commands::safety_backups::RetentionPolicy {
+                    keep_last,
+                    max_age,
+                    max_size,
+                };
+                commands::safety_backups::prune(limits, dry_run, yes)?;
+            } else {
+                println!("{}", commands::safety_backups::list()?);
+            }
+        }
     }
 
     Ok(())